@@ -0,0 +1,302 @@
+use crate::rendering;
+use super::{with_clip, Bounds, Drawable, Size, Widget};
+
+/// Roughly how many seconds a [SmoothedValue] takes to reach its target
+const SMOOTHING_FACTOR: f32 = 0.2;
+
+/// Moves `curr_val` towards `target`, all the way once [SMOOTHING_FACTOR] seconds have passed.
+pub fn update_smooth_value(curr_val: f32, target: f32, elapsed: &std::time::Duration) -> f32 {
+    let progress = (elapsed.as_secs_f32() / SMOOTHING_FACTOR).clamp(0.0, 1.0);
+    curr_val * (1.0 - progress) + target * progress
+}
+
+/// A value in the range [0.0, 1.0] that can approach a new value over time instead of jumping to it.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SmoothedValue {
+    value: f32,
+    target: f32,
+}
+
+impl SmoothedValue {
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Jumps straight to `value`.
+    pub fn set(&mut self, value: f32) {
+        self.value = value.clamp(0.0, 1.0);
+        self.target = self.value;
+    }
+
+    /// Sets the value to approach with each [SmoothedValue::update].
+    pub fn set_smoothed(&mut self, value: f32) {
+        self.target = value.clamp(0.0, 1.0);
+    }
+
+    /// Moves towards the target by the given time, returning the new value.
+    pub fn update(&mut self, elapsed: &std::time::Duration) -> f32 {
+        self.value = update_smooth_value(self.value, self.target, elapsed);
+        self.value
+    }
+}
+
+/// A dial with a needle pointing at a value in the range [0.0, 1.0], like a speedometer.
+pub struct GaugeWidget {
+    value: SmoothedValue,
+    start_angle: f32,
+    sweep: f32,
+    ticks: usize,
+}
+
+impl GaugeWidget {
+    /// Creates a gauge going clockwise from bottom left to bottom right.
+    pub fn new() -> Self {
+        GaugeWidget { value: SmoothedValue::default(), start_angle: -135.0, sweep: 270.0, ticks: 5 }
+    }
+
+    /// Sets where the dial starts and how far it goes, in degrees clockwise from 12 o'clock.
+    pub fn with_angles(mut self, start_angle: f32, sweep: f32) -> Self {
+        self.start_angle = start_angle;
+        self.sweep = sweep.clamp(0.0, 360.0);
+        self
+    }
+
+    /// Sets how many tick marks are spread over the dial, including both ends.
+    pub fn with_ticks(mut self, ticks: usize) -> Self {
+        self.ticks = ticks;
+        self
+    }
+
+    /// Sets the value to be displayed
+    pub fn set_value(&mut self, value: f32) {
+        self.value.set(value);
+    }
+    /// Sets the target value to be displayed. The actual value will approach this over time.
+    pub fn set_value_smoothed(&mut self, value: f32) {
+        self.value.set_smoothed(value);
+    }
+}
+
+impl Default for GaugeWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drawable for GaugeWidget {
+    fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
+        let value = self.value.update(elapsed);
+        let Some((cx, cy, radius)) = fit_arc(bounds, self.start_angle, self.sweep) else { return; };
+        with_clip(canvas, bounds, |canvas| {
+            canvas.draw_arc(cx, cy, radius, 1, self.start_angle, self.start_angle + self.sweep);
+            let tick_length = (radius / 4).max(1) as f32;
+            for tick in 0..self.ticks {
+                let fraction = if self.ticks > 1 { tick as f32 / (self.ticks - 1) as f32 } else { 0.0 };
+                let (x0, y0) = point_at(cx, cy, radius as f32 - 1.0, self.start_angle + fraction * self.sweep);
+                let (x1, y1) = point_at(cx, cy, radius as f32 - tick_length, self.start_angle + fraction * self.sweep);
+                canvas.draw_line(x0, y0, x1, y1);
+            }
+            let (x, y) = point_at(cx, cy, radius as f32 - tick_length - 1.0, self.start_angle + value * self.sweep);
+            canvas.draw_line(cx, cy, x, y);
+            canvas.fill_circle(cx, cy, (radius / 8).max(1));
+        });
+    }
+}
+impl Widget<(), ()> for GaugeWidget {
+    fn size(&self) -> Size<(), ()> {
+        Size { width: (), height: () }
+    }
+}
+
+/// A thick arc that fills up clockwise with a value in the range [0.0, 1.0]. The empty part is outlined.
+pub struct ArcMeterWidget {
+    value: SmoothedValue,
+    start_angle: f32,
+    sweep: f32,
+    thickness: usize,
+}
+
+impl ArcMeterWidget {
+    /// Creates a half circle meter, going clockwise from 9 to 3 o'clock.
+    pub fn new() -> Self {
+        ArcMeterWidget { value: SmoothedValue::default(), start_angle: -90.0, sweep: 180.0, thickness: 6 }
+    }
+
+    /// Sets where the meter starts and how far it goes, in degrees clockwise from 12 o'clock.
+    pub fn with_angles(mut self, start_angle: f32, sweep: f32) -> Self {
+        self.start_angle = start_angle;
+        self.sweep = sweep.clamp(0.0, 360.0);
+        self
+    }
+
+    pub fn with_thickness(mut self, thickness: usize) -> Self {
+        self.thickness = thickness;
+        self
+    }
+
+    /// Sets the value to be displayed
+    pub fn set_value(&mut self, value: f32) {
+        self.value.set(value);
+    }
+    /// Sets the target value to be displayed. The actual value will approach this over time.
+    pub fn set_value_smoothed(&mut self, value: f32) {
+        self.value.set_smoothed(value);
+    }
+}
+
+impl Default for ArcMeterWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drawable for ArcMeterWidget {
+    fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
+        let value = self.value.update(elapsed);
+        draw_meter(canvas, bounds, self.start_angle, self.sweep, self.thickness, value);
+    }
+}
+impl Widget<(), ()> for ArcMeterWidget {
+    fn size(&self) -> Size<(), ()> {
+        Size { width: (), height: () }
+    }
+}
+
+/// A ring that fills up clockwise from 12 o'clock with a value in the range [0.0, 1.0].
+pub struct RingProgressWidget {
+    value: SmoothedValue,
+    thickness: usize,
+}
+
+impl RingProgressWidget {
+    pub fn new() -> Self {
+        RingProgressWidget { value: SmoothedValue::default(), thickness: 4 }
+    }
+
+    pub fn with_thickness(mut self, thickness: usize) -> Self {
+        self.thickness = thickness;
+        self
+    }
+
+    /// Sets the value to be displayed
+    pub fn set_value(&mut self, value: f32) {
+        self.value.set(value);
+    }
+    /// Sets the target value to be displayed. The actual value will approach this over time.
+    pub fn set_value_smoothed(&mut self, value: f32) {
+        self.value.set_smoothed(value);
+    }
+}
+
+impl Default for RingProgressWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drawable for RingProgressWidget {
+    fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
+        let value = self.value.update(elapsed);
+        draw_meter(canvas, bounds, 0.0, 360.0, self.thickness, value);
+    }
+}
+impl Widget<(), ()> for RingProgressWidget {
+    fn size(&self) -> Size<(), ()> {
+        Size { width: (), height: () }
+    }
+}
+
+/// Draws the outline of a thick arc, filled up to `value`.
+fn draw_meter(canvas: &mut rendering::Bitmap, bounds: Bounds, start_angle: f32, sweep: f32, thickness: usize, value: f32) {
+    let Some((cx, cy, radius)) = fit_arc(bounds, start_angle, sweep) else { return; };
+    let thickness = thickness.clamp(1, radius + 1);
+    let end_angle = start_angle + sweep;
+    with_clip(canvas, bounds, |canvas| {
+        canvas.draw_arc(cx, cy, radius, 1, start_angle, end_angle);
+        if thickness > 1 {
+            canvas.draw_arc(cx, cy, radius + 1 - thickness, 1, start_angle, end_angle);
+        }
+        if sweep < 360.0 {
+            for angle in [start_angle, end_angle] {
+                let (x0, y0) = point_at(cx, cy, radius as f32, angle);
+                let (x1, y1) = point_at(cx, cy, (radius + 1 - thickness) as f32, angle);
+                canvas.draw_line(x0, y0, x1, y1);
+            }
+        }
+        canvas.draw_arc(cx, cy, radius, thickness, start_angle, start_angle + value * sweep);
+    });
+}
+
+/// The center and radius of the largest arc (and its center) that fits in `bounds`, or nothing if it's too small.
+fn fit_arc(bounds: Bounds, start_angle: f32, sweep: f32) -> Option<(i32, i32, usize)> {
+    // The extent of the arc on a unit circle: its ends, the center, and any of 12, 3, 6 and 9 o'clock it passes
+    let mut angles = vec![start_angle, start_angle + sweep];
+    angles.extend((0..4).map(|i| i as f32 * 90.0).filter(|angle| (angle - start_angle).rem_euclid(360.0) <= sweep));
+    let points = angles.iter().map(|angle| (angle.to_radians().sin(), -angle.to_radians().cos())).chain([(0.0, 0.0)]);
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0f32, 0f32, 0f32, 0f32);
+    for (x, y) in points {
+        (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+    }
+
+    let (width, height) = (bounds.size.width as f32 - 1.0, bounds.size.height as f32 - 1.0);
+    let radius = (width / (max_x - min_x).max(f32::EPSILON)).min(height / (max_y - min_y).max(f32::EPSILON)).floor();
+    if radius < 1.0 { return None; }
+    // Center the arc in any space left over
+    let left = bounds.pos.x as f32 + (width - (max_x - min_x) * radius) / 2.0;
+    let top = bounds.pos.y as f32 + (height - (max_y - min_y) * radius) / 2.0;
+    Some(((left - min_x * radius).round() as i32, (top - min_y * radius).round() as i32, radius as usize))
+}
+
+/// The pixel `distance` from (`cx`, `cy`) in the direction of `angle`, in degrees clockwise from 12 o'clock.
+fn point_at(cx: i32, cy: i32, distance: f32, angle: f32) -> (i32, i32) {
+    let angle = angle.to_radians();
+    (cx + (distance * angle.sin()).round() as i32, cy - (distance * angle.cos()).round() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::components::EmptyBounds;
+
+    #[test]
+    fn gauges_snapshot() {
+        struct Dashboard(GaugeWidget, ArcMeterWidget, RingProgressWidget);
+        impl Drawable for Dashboard {
+            fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &Duration) {
+                let third = EmptyBounds::new().with_y(bounds.pos.y).with_height(bounds.size.height).with_width(bounds.size.width / 3);
+                self.0.draw(canvas, third.with_x(0), elapsed);
+                self.1.draw(canvas, third.with_x(third.size.width as i32), elapsed);
+                self.2.draw(canvas, third.with_x(2 * third.size.width as i32), elapsed);
+            }
+        }
+        let mut dashboard = Dashboard(GaugeWidget::new(), ArcMeterWidget::new().with_thickness(4), RingProgressWidget::new().with_thickness(3));
+        dashboard.0.set_value(0.0);
+        dashboard.0.set_value_smoothed(0.8);
+        dashboard.1.set_value_smoothed(0.6);
+        dashboard.2.set_value_smoothed(0.4);
+        crate::snapshot!("gauges", 60, 20)
+            .with_interval(Duration::from_millis(100), 3)
+            .assert_matches(&mut dashboard);
+    }
+
+    #[test]
+    fn values_approach_their_target() {
+        let mut value = SmoothedValue::default();
+        value.set_smoothed(1.5);
+        assert_eq!(value.value(), 0.0);
+        assert_eq!(value.update(&Duration::from_millis(50)), 0.25);
+        assert_eq!(value.update(&Duration::from_secs(1)), 1.0);
+        value.set(0.5);
+        assert_eq!(value.update(&Duration::from_millis(50)), 0.5);
+    }
+
+    #[test]
+    fn arcs_fit_their_bounds() {
+        let bounds = EmptyBounds::new().with_x(10).with_y(0).with_width(21).with_height(21);
+        assert_eq!(fit_arc(bounds, 0.0, 360.0), Some((20, 10, 10)));
+        // A half circle only needs half the height, so it can be as wide as the bounds
+        assert_eq!(fit_arc(bounds.with_height(11), -90.0, 180.0), Some((20, 10, 10)));
+        assert_eq!(fit_arc(bounds.with_height(1), 0.0, 360.0), None);
+    }
+}
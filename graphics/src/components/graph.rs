@@ -0,0 +1,212 @@
+use std::collections::VecDeque;
+
+use crate::rendering;
+use super::{with_clip, Bounds, Drawable, Size, Widget};
+
+/// How a [GraphWidget] draws its values.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum GraphStyle {
+    /// A line through the values
+    #[default]
+    Line,
+    /// A line through the values, with the area under it filled
+    Area,
+    /// One column per value
+    Bars,
+}
+
+/// The values at the bottom and top edges of a [GraphWidget].
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum GraphScale {
+    /// From 0 (or the lowest value, if negative) to the highest value currently shown
+    #[default]
+    Auto,
+    Fixed { min: f32, max: f32 },
+}
+
+/// Plots the last `capacity` values pushed to it, newest at the right edge. Fills its bounds.
+pub struct GraphWidget {
+    values: VecDeque<f32>,
+    capacity: usize,
+    style: GraphStyle,
+    scale: GraphScale,
+    grid_ticks: usize,
+}
+
+impl GraphWidget {
+    pub fn new(capacity: usize) -> Self {
+        GraphWidget {
+            values: VecDeque::with_capacity(capacity),
+            capacity,
+            style: GraphStyle::default(),
+            scale: GraphScale::default(),
+            grid_ticks: 0,
+        }
+    }
+
+    pub fn with_style(mut self, style: GraphStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: GraphScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Draws this many dotted horizontal lines, evenly spaced between the bottom and top edges.
+    pub fn with_grid_ticks(mut self, ticks: usize) -> Self {
+        self.grid_ticks = ticks;
+        self
+    }
+
+    /// Adds a value at the right edge, dropping the oldest one if the graph is full.
+    pub fn push(&mut self, value: f32) {
+        if self.capacity == 0 { return; }
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    /// Replaces all values, oldest first. Only the last `capacity` are kept.
+    pub fn set_values<I: IntoIterator<Item = f32>>(&mut self, values: I) {
+        self.values.clear();
+        for value in values {
+            self.push(value);
+        }
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = f32> + '_ {
+        self.values.iter().copied()
+    }
+
+    /// The values at the bottom and top edges, given the current values.
+    pub fn range(&self) -> (f32, f32) {
+        let (min, max) = match self.scale {
+            GraphScale::Fixed { min, max } => (min, max),
+            GraphScale::Auto => (
+                self.values().fold(0.0, f32::min),
+                self.values().fold(f32::NEG_INFINITY, f32::max),
+            ),
+        };
+        // Flat or empty graphs would divide by zero
+        if max > min { (min, max) } else { (min, min + 1.0) }
+    }
+}
+
+impl Drawable for GraphWidget {
+    fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, _elapsed: &std::time::Duration) {
+        let (width, height) = (bounds.size.width as i32, bounds.size.height as i32);
+        if width == 0 || height == 0 || self.capacity == 0 { return; }
+        with_clip(canvas, bounds, |canvas| {
+            let bottom = bounds.bottom() - 1;
+            for tick in 1..=self.grid_ticks as i32 {
+                let y = bottom - ((tick * (height - 1)) as f32 / (self.grid_ticks as i32 + 1) as f32).round() as i32;
+                for x in (bounds.pos.x..bounds.right()).step_by(2) {
+                    canvas.draw_pixel(x, y);
+                }
+            }
+
+            let (min, max) = self.range();
+            let fraction = |value: f32| ((value - min) / (max - min)).clamp(0.0, 1.0);
+            // Values fill the slots at the right, so a graph that isn't full yet grows in from the right edge
+            let first_slot = (self.capacity - self.values.len()) as i32;
+            let capacity = self.capacity as i32;
+            match self.style {
+                GraphStyle::Line | GraphStyle::Area => {
+                    let point = |i: usize, value: f32| {
+                        let slot = first_slot + i as i32;
+                        let x = if capacity > 1 { slot * (width - 1) / (capacity - 1) } else { width - 1 };
+                        (bounds.pos.x + x, bottom - (fraction(value) * (height - 1) as f32).round() as i32)
+                    };
+                    let points = self.values().enumerate().map(|(i, value)| point(i, value)).collect::<Vec<_>>();
+                    if let [(x, y)] = points[..] {
+                        canvas.draw_pixel(x, y);
+                    }
+                    for pair in points.windows(2) {
+                        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+                        if self.style == GraphStyle::Area {
+                            for x in x0..=x1 {
+                                let y = if x1 > x0 { y0 + ((y1 - y0) as f32 * (x - x0) as f32 / (x1 - x0) as f32).round() as i32 } else { y1 };
+                                canvas.draw_rect(x, y, 1, (bottom - y + 1) as usize);
+                            }
+                        }
+                        canvas.draw_line(x0, y0, x1, y1);
+                    }
+                    if let (GraphStyle::Area, Some((x, y))) = (self.style, points.first()) {
+                        canvas.draw_rect(*x, *y, 1, (bottom - y + 1) as usize);
+                    }
+                },
+                GraphStyle::Bars => {
+                    for (i, value) in self.values().enumerate() {
+                        let slot = first_slot + i as i32;
+                        let (x0, x1) = (slot * width / capacity, (slot + 1) * width / capacity);
+                        // Leave a gap between bars when there is room for one
+                        let bar_width = if x1 - x0 > 1 { x1 - x0 - 1 } else { x1 - x0 };
+                        let bar_height = (fraction(value) * height as f32).round() as i32;
+                        canvas.draw_rect(bounds.pos.x + x0, bottom - bar_height + 1, bar_width as usize, bar_height as usize);
+                    }
+                },
+            }
+        });
+    }
+}
+
+impl Widget<(), ()> for GraphWidget {
+    fn size(&self) -> Size<(), ()> {
+        Size { width: (), height: () }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::EmptyBounds;
+
+    const VALUES: [f32; 6] = [0.2, 0.5, 0.4, 0.9, 0.1, 0.6];
+
+    #[test]
+    fn graph_styles_snapshot() {
+        struct Graphs(Vec<GraphWidget>);
+        impl Drawable for Graphs {
+            fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
+                for (i, graph) in self.0.iter_mut().enumerate() {
+                    let graph_bounds = EmptyBounds::new().with_x(bounds.pos.x).with_width(bounds.size.width).with_y(i as i32 * 9).with_height(8);
+                    graph.draw(canvas, graph_bounds, elapsed);
+                }
+            }
+        }
+        let fixed = GraphScale::Fixed { min: 0.0, max: 1.0 };
+        let mut graphs = Graphs(vec![
+            GraphWidget::new(8).with_scale(fixed).with_grid_ticks(1),
+            GraphWidget::new(8).with_style(GraphStyle::Area),
+            GraphWidget::new(8).with_style(GraphStyle::Bars).with_scale(fixed),
+        ]);
+        for graph in graphs.0.iter_mut() {
+            graph.set_values(VALUES);
+        }
+        crate::snapshot!("graph_styles", 22, 26).assert_matches(&mut graphs);
+    }
+
+    #[test]
+    fn old_values_are_dropped() {
+        let mut graph = GraphWidget::new(4);
+        graph.set_values(VALUES);
+        assert_eq!(graph.values().collect::<Vec<_>>(), [0.4, 0.9, 0.1, 0.6]);
+        graph.push(0.3);
+        assert_eq!(graph.values().collect::<Vec<_>>(), [0.9, 0.1, 0.6, 0.3]);
+    }
+
+    #[test]
+    fn auto_scale_fits_the_values() {
+        let mut graph = GraphWidget::new(4);
+        assert_eq!(graph.range(), (0.0, 1.0));
+        graph.set_values([2.0, 6.0]);
+        assert_eq!(graph.range(), (0.0, 6.0));
+        graph.push(-3.0);
+        assert_eq!(graph.range(), (-3.0, 6.0));
+        let graph = GraphWidget::new(4).with_scale(GraphScale::Fixed { min: 0.0, max: 100.0 });
+        assert_eq!(graph.range(), (0.0, 100.0));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::rendering::{self, Ditherer, Dithering};
+
+use super::{with_clip, Bounds, Drawable};
+
+/// Wraps a drawable, optionally dithering everything it draws before it is combined with the rest of the
+/// canvas. Useful for photos and videos, which would lose most of their detail when the finished canvas
+/// is thresholded.
+pub struct Layer<D: Drawable> {
+    inner: D,
+    ditherer: Option<Ditherer>,
+    /// What the inner drawable is drawn to before dithering
+    scratch: rendering::Bitmap,
+}
+
+impl<D: Drawable> Layer<D> {
+    /// Creates a layer that draws `inner` as is.
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            ditherer: None,
+            scratch: rendering::Bitmap::new(0, 0),
+        }
+    }
+
+    pub fn with_dithering(mut self, dithering: Dithering) -> Self {
+        self.ditherer = Some(Ditherer::new(dithering));
+        self
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+}
+
+impl<D: Drawable> Drawable for Layer<D> {
+    fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
+        let Some(ditherer) = self.ditherer.as_mut() else {
+            self.inner.draw(canvas, bounds, elapsed);
+            return;
+        };
+        if self.scratch.width != canvas.width || self.scratch.height != canvas.height {
+            self.scratch = rendering::Bitmap::new(canvas.width, canvas.height);
+        }
+        self.scratch.clear();
+        self.inner.draw(&mut self.scratch, bounds, elapsed);
+        ditherer.dither(&mut self.scratch);
+
+        with_clip(canvas, bounds, |canvas| canvas.draw_bitmap(0, 0, &self.scratch));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::components::BitmapWidget;
+
+    #[test]
+    fn layers_dither_only_their_content() {
+        let gray = rendering::Bitmap::from_raw_bytes(&[0x80; 16 * 4], 16);
+        let mut plain = Layer::new(BitmapWidget::new(gray.clone()));
+        let mut dithered = Layer::new(BitmapWidget::new(gray)).with_dithering(Dithering::Bayer(2));
+
+        let mut canvas = rendering::Bitmap::new(16, 8);
+        let bounds = Bounds::cover_bitmap(&canvas);
+        canvas.buffer[16 * 7] = 0x20;
+        plain.draw(&mut canvas, bounds, &Duration::ZERO);
+        dithered.draw(&mut canvas, bounds.move_y(4), &Duration::ZERO);
+
+        assert!(canvas.buffer[..16 * 4].iter().all(|p| *p == 0x80));
+        let lit = canvas.buffer[16 * 4..].iter().filter(|p| **p == 0xff).count();
+        assert_eq!(lit, 16 * 4 / 2);
+        // The layer's dark pixels don't cover what is under them
+        assert_eq!(canvas.buffer[16 * 7], 0x20);
+    }
+}
@@ -28,6 +28,12 @@ impl EmptyBounds {
     }
 }
 
+impl Default for EmptyBounds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Bounds {
     pub fn cover_bitmap(bitmap: &Bitmap) -> Self {
         Bounds {
@@ -104,7 +110,7 @@ impl<X: Copy, Y: Copy, H: Copy> Rect<X, Y, u32, H> {
     pub fn center_hor_in<Y2: Copy, H2: Copy>(&self, other: &Rect<i32, Y2, u32, H2>) -> Rect<i32, Y, u32, H> {
         Rect {
             pos: Point {
-                x: other.pos.x + (other.size.width - self.size.width) as i32 / 2,
+                x: other.pos.x + (other.size.width as i32 - self.size.width as i32) / 2,
                 y: self.pos.y,
             },
             size: self.size,
@@ -149,7 +155,7 @@ impl<X: Copy, Y: Copy, W: Copy> Rect<X, Y, W, u32> {
         Rect {
             pos: Point {
                 x: self.pos.x,
-                y: other.pos.y + (other.size.height - self.size.height) as i32 / 2,
+                y: other.pos.y + (other.size.height as i32 - self.size.height as i32) / 2,
             },
             size: self.size,
         }
@@ -160,7 +166,7 @@ impl<X: Copy, Y: Copy, W: Copy> Rect<X, Y, W, u32> {
 impl<X: Copy, Y: Copy> Rect<X, Y, u32, u32> {
     /// Positions `self` in the horizontal and vertical center of `other`.
     pub fn center_in(&self, other: &Bounds) -> Bounds {
-        self.center_hor_in(&other).center_ver_in(&other)
+        self.center_hor_in(other).center_ver_in(other)
     }
 }
 
@@ -200,6 +206,11 @@ impl<X: Copy, Y: Copy, W: Copy, H: Copy> Rect<X, Y, W, H> {
             size: self.size,
         }
     }
+    /// Moves `self` vertically so that a baseline `baseline` pixels below its top edge ends up at `y`. Text widgets
+    /// placed like this line up, whatever their font and size (see [super::TextWidget::baseline]).
+    pub fn with_baseline_at(&self, y: i32, baseline: u32) -> Rect<X, i32, W, H> {
+        self.with_y(y - baseline as i32)
+    }
     pub fn with_width(&self, value: u32) -> Rect<X, Y, u32, H> {
         Rect {
             pos: self.pos,
@@ -1,9 +1,15 @@
 mod text;
 mod layout;
 mod video;
+mod layer;
+mod graph;
+mod gauge;
 pub use text::*;
 pub use layout::*;
 pub use video::*;
+pub use layer::*;
+pub use graph::*;
+pub use gauge::*;
 
 use crate::rendering;
 
@@ -12,23 +18,43 @@ pub trait Drawable {
     fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: layout::Bounds, elapsed: &std::time::Duration);
 }
 
+impl<D: Drawable + ?Sized> Drawable for Box<D> {
+    fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: layout::Bounds, elapsed: &std::time::Duration) {
+        self.as_mut().draw(canvas, bounds, elapsed);
+    }
+}
+
+/// Runs `draw` with everything it draws to `canvas` clipped to `bounds`.
+pub fn with_clip<R>(canvas: &mut rendering::Bitmap, bounds: layout::Bounds, draw: impl FnOnce(&mut rendering::Bitmap) -> R) -> R {
+    canvas.push_clip(bounds.pos.x, bounds.pos.y, bounds.size.width as usize, bounds.size.height as usize);
+    let result = draw(canvas);
+    canvas.pop_clip();
+    result
+}
+
 pub trait Widget<W: Copy, H: Copy> : Drawable {
     fn size(&self) -> Size<W, H>;
 }
 
 pub struct BitmapWidget {
     bitmap: rendering::Bitmap,
+    blend_mode: rendering::BlendMode,
 }
 
 impl BitmapWidget {
     pub fn new(bitmap: rendering::Bitmap) -> Self {
-        BitmapWidget { bitmap }
+        BitmapWidget { bitmap, blend_mode: rendering::BlendMode::default() }
+    }
+
+    /// Sets how the bitmap is combined with what is already drawn under it.
+    pub fn with_blend_mode(mut self, blend_mode: rendering::BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
     }
 }
 impl Drawable for BitmapWidget {
     fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: layout::Bounds, _elapsed: &std::time::Duration) {
-        // TODO: replace with call that respects bounds
-        canvas.draw_bitmap(bounds.pos.x, bounds.pos.y, &self.bitmap);
+        with_clip(canvas, bounds, |canvas| canvas.draw_bitmap_with(bounds.pos.x, bounds.pos.y, &self.bitmap, self.blend_mode));
     }
 }
 impl Widget<u32, u32> for BitmapWidget {
@@ -39,3 +65,29 @@ impl Widget<u32, u32> for BitmapWidget {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitmap_widget_snapshot() {
+        let mut bitmap = rendering::Bitmap::new(6, 4);
+        for (i, pixel) in bitmap.buffer.iter_mut().enumerate() {
+            *pixel = (i * 11) as u8;
+        }
+        let mut widget = BitmapWidget::new(bitmap);
+        crate::snapshot!("bitmap_widget", 8, 6).assert_matches(&mut widget);
+    }
+
+    #[test]
+    fn bitmap_widget_stays_in_bounds() {
+        let mut canvas = rendering::Bitmap::new(8, 4);
+        let mut widget = BitmapWidget::new(rendering::Bitmap::from_raw_bytes(&[0xff; 16], 4))
+            .with_blend_mode(rendering::BlendMode::Overwrite);
+        let bounds = EmptyBounds::new().with_x(2).with_y(1).with_width(2).with_height(2);
+        widget.draw(&mut canvas, bounds, &std::time::Duration::ZERO);
+        let lit = canvas.buffer.iter().enumerate().filter(|(_, p)| **p > 0).map(|(i, _)| (i % 8, i / 8)).collect::<Vec<_>>();
+        assert_eq!(lit, vec![(2, 1), (3, 1), (2, 2), (3, 2)]);
+    }
+}
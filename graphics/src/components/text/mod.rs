@@ -1,8 +1,10 @@
 mod simple_text;
 mod scrolling_text;
+mod paragraph;
 
 pub use simple_text::*;
 pub use scrolling_text::*;
+pub use paragraph::*;
 
 use super::Widget;
 
@@ -12,4 +14,7 @@ pub trait TextWidget<W: Copy, H: Copy> : Widget<W, H> {
     /// The text will be drawn on the next [super::Widget::draw] call.
     /// Returns `true` if the new text was different from the previous one.
     fn set_text(&mut self, text: &str) -> bool;
+
+    /// The distance from the top of the widget to the baseline of its (first line of) text.
+    fn baseline(&self) -> u32;
 }
@@ -0,0 +1,258 @@
+use crate::components::{with_clip, Drawable};
+use crate::rendering;
+use super::TextWidget;
+use super::scrolling_text::{Alignment, ScrollingState};
+use super::super::{ Widget, Bounds, Size };
+
+const SCROLL_SPEED: f32 = 20.0; // text scrolling speed in pixels per second
+const ELLIPSIS: &str = "...";
+
+/// What a [ParagraphWidget] does with lines that don't fit in its bounds.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Overflow {
+    /// Leave them out, ending the last line that fits with an ellipsis
+    Ellipsis,
+    /// Scroll down to them and back up, waiting this long at the top and bottom
+    Scroll(std::time::Duration),
+}
+
+/// Text wrapped by words to fill its bounds. Newlines in the text start a new line.
+pub struct ParagraphWidget<'a> {
+    text: String,
+    font: &'a fontdue::Font,
+    font_size: f32,
+    alignment: Alignment,
+    line_spacing: u32,
+    overflow: Overflow,
+    /// The rendered lines and the size they were laid out for, if the text has been laid out
+    layout: Option<((u32, u32), Vec<Line>)>,
+    state: ScrollingState,
+}
+
+struct Line {
+    bitmap: rendering::Bitmap,
+    /// The distance from the top of the bitmap to the baseline
+    base_height: i32,
+}
+
+impl<'a> ParagraphWidget<'a> {
+    pub fn new(text: String, font: &'a fontdue::Font, font_size: f32) -> Self {
+        Self {
+            text,
+            font,
+            font_size,
+            alignment: Alignment::Left,
+            line_spacing: 0,
+            overflow: Overflow::Ellipsis,
+            layout: None,
+            state: ScrollingState::WaitingStart(std::time::Duration::ZERO),
+        }
+    }
+
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Sets the number of empty pixels between lines.
+    pub fn with_line_spacing(mut self, line_spacing: u32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self.reset_scroll();
+        self
+    }
+
+    /// The height of a line of text, not counting the line spacing.
+    pub fn line_height(&self) -> u32 {
+        let metrics = rendering::measure_text("", self.font, self.font_size);
+        ((metrics.ascent + metrics.descent).ceil() as u32).max(1)
+    }
+
+    /// The distance from the top of a line to its baseline.
+    fn ascent(&self) -> i32 {
+        rendering::measure_text("", self.font, self.font_size).ascent.round() as i32
+    }
+
+    /// The height needed to show all of the text without overflowing, when wrapped to `width`.
+    pub fn height_for(&self, width: u32) -> u32 {
+        let lines = self.wrap(width).len() as u32;
+        (lines * (self.line_height() + self.line_spacing)).saturating_sub(self.line_spacing)
+    }
+
+    /// Splits the text into lines no wider than `width`. Words wider than that are split wherever they have to.
+    fn wrap(&self, width: u32) -> Vec<String> {
+        let fits = |line: &str| rendering::measure_text(line, self.font, self.font_size).width as u32 <= width;
+        let mut lines = Vec::new();
+        for paragraph in self.text.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+                if fits(&candidate) {
+                    line = candidate;
+                    continue;
+                }
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                for character in word.chars() {
+                    line.push(character);
+                    if !fits(&line) && line.chars().count() > 1 {
+                        line.pop();
+                        lines.push(std::mem::replace(&mut line, character.to_string()));
+                    }
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Wraps the text to `width` and renders it, unless that was already done.
+    fn lay_out(&mut self, width: u32, height: u32) {
+        if matches!(self.layout, Some((size, _)) if size == (width, height)) {
+            return;
+        }
+        let mut lines = self.wrap(width);
+        if self.overflow == Overflow::Ellipsis {
+            let max_lines = ((height + self.line_spacing) / (self.line_height() + self.line_spacing)).max(1) as usize;
+            if lines.len() > max_lines {
+                lines.truncate(max_lines);
+                let last = lines.last_mut().unwrap();
+                while !last.is_empty() && rendering::measure_text(&format!("{}{}", last, ELLIPSIS), self.font, self.font_size).width as u32 > width {
+                    last.pop();
+                }
+                last.truncate(last.trim_end().len());
+                last.push_str(ELLIPSIS);
+            }
+        }
+        let lines = lines.iter().map(|line| Line {
+            bitmap: rendering::Bitmap::from_text(line, self.font_size, self.font),
+            base_height: rendering::measure_text(line, self.font, self.font_size).baseline as i32,
+        }).collect();
+        self.layout = Some(((width, height), lines));
+    }
+
+    fn reset_scroll(&mut self) {
+        self.state = match self.overflow {
+            Overflow::Scroll(wait) => ScrollingState::WaitingStart(wait),
+            Overflow::Ellipsis => ScrollingState::WaitingStart(std::time::Duration::ZERO),
+        };
+    }
+
+    /// Moves the text up by the elapsed time, `overflow_height` being how far it can move.
+    fn update(&mut self, elapsed: &std::time::Duration, overflow_height: i32) {
+        let Overflow::Scroll(wait) = self.overflow else { return; };
+        if overflow_height <= 0 {
+            self.state = ScrollingState::WaitingStart(wait);
+            return;
+        }
+        self.state = match self.state {
+            ScrollingState::WaitingStart(remaining) => match remaining.checked_sub(*elapsed) {
+                Some(remaining) if remaining > std::time::Duration::ZERO => ScrollingState::WaitingStart(remaining),
+                _ => ScrollingState::Moving(0.0),
+            },
+            ScrollingState::Moving(y_pos) => {
+                let y_pos = y_pos - SCROLL_SPEED * elapsed.as_secs_f32();
+                if -y_pos >= overflow_height as f32 { ScrollingState::WaitingEnd(wait) } else { ScrollingState::Moving(y_pos) }
+            },
+            ScrollingState::WaitingEnd(remaining) => match remaining.checked_sub(*elapsed) {
+                Some(remaining) if remaining > std::time::Duration::ZERO => ScrollingState::WaitingEnd(remaining),
+                _ => ScrollingState::WaitingStart(wait),
+            },
+        };
+    }
+}
+
+impl<'a> TextWidget<(), ()> for ParagraphWidget<'a> {
+    fn set_text(&mut self, text: &str) -> bool {
+        if self.text == text {
+            return false;
+        }
+        self.text = text.to_string();
+        self.layout = None;
+        self.reset_scroll();
+        true
+    }
+
+    fn baseline(&self) -> u32 {
+        self.ascent() as u32
+    }
+}
+
+impl<'a> Drawable for ParagraphWidget<'a> {
+    fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
+        self.lay_out(bounds.size.width, bounds.size.height);
+        let line_height = self.line_height();
+        let ascent = self.ascent();
+        let Some((_, lines)) = &self.layout else { unreachable!() };
+        let text_height = (lines.len() as u32 * (line_height + self.line_spacing)).saturating_sub(self.line_spacing);
+        self.update(elapsed, text_height as i32 - bounds.size.height as i32);
+
+        let offset = match self.state {
+            ScrollingState::Moving(y_pos) => y_pos as i32,
+            ScrollingState::WaitingEnd(_) => bounds.size.height as i32 - text_height as i32,
+            ScrollingState::WaitingStart(_) => 0,
+        };
+        let Some((_, lines)) = &self.layout else { unreachable!() };
+        with_clip(canvas, bounds, |canvas| {
+            for (i, line) in lines.iter().enumerate() {
+                let baseline = bounds.pos.y + offset + i as i32 * (line_height + self.line_spacing) as i32 + ascent;
+                let free_width = bounds.size.width.saturating_sub(line.bitmap.width as u32) as i32;
+                let x = match self.alignment {
+                    Alignment::Left => bounds.pos.x,
+                    Alignment::Center => bounds.pos.x + free_width / 2,
+                    Alignment::Right => bounds.pos.x + free_width,
+                };
+                canvas.draw_bitmap(x, baseline - line.base_height, &line.bitmap);
+            }
+        });
+    }
+}
+
+impl<'a> Widget<(), ()> for ParagraphWidget<'a> {
+    fn size(&self) -> Size<(), ()> {
+        Size { width: (), height: () }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::testing::test_font;
+
+    const TEXT: &str = "The quick brown fox jumps\nover the lazy dog";
+
+    #[test]
+    fn words_are_wrapped() {
+        let widget = ParagraphWidget::new(TEXT.to_string(), test_font(), 9.0);
+        assert_eq!(widget.wrap(64), ["The quick", "brown fox", "jumps", "over the", "lazy dog"]);
+        assert_eq!(widget.wrap(1000), ["The quick brown fox jumps", "over the lazy dog"]);
+        // Words that don't fit on a line of their own are split
+        let widget = ParagraphWidget::new("abcdefgh\n\nok".to_string(), test_font(), 9.0);
+        assert_eq!(widget.wrap(30), ["abcde", "fgh", "", "ok"]);
+        assert_eq!(widget.height_for(30), 4 * widget.line_height());
+    }
+
+    #[test]
+    fn paragraph_is_aligned_and_truncated() {
+        let mut widget = ParagraphWidget::new(TEXT.to_string(), test_font(), 9.0)
+            .with_alignment(Alignment::Center)
+            .with_line_spacing(1);
+        crate::snapshot!("paragraph_ellipsis", 64, 32).assert_matches(&mut widget);
+    }
+
+    #[test]
+    fn paragraph_scrolls() {
+        let mut widget = ParagraphWidget::new(TEXT.to_string(), test_font(), 9.0)
+            .with_alignment(Alignment::Right)
+            .with_overflow(Overflow::Scroll(Duration::from_millis(300)));
+        crate::snapshot!("paragraph_scrolls", 64, 24)
+            .with_interval(Duration::from_millis(500), 6)
+            .assert_matches(&mut widget);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::components::Drawable;
+use crate::components::{with_clip, Drawable};
 use crate::rendering;
 use super::TextWidget;
 use super::simple_text::SimpleTextWidget;
@@ -15,6 +15,7 @@ pub struct ScrollingTextWidget<'a> {
     wait_end: std::time::Duration,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Alignment {
     Left,
     Center,
@@ -79,7 +80,7 @@ impl<'a> ScrollingTextWidget<'a> {
     }
 }
 
-enum ScrollingState {
+pub(super) enum ScrollingState {
     WaitingStart(std::time::Duration),
     Moving(f32),
     WaitingEnd(std::time::Duration),
@@ -93,6 +94,10 @@ impl<'a> TextWidget<(), u32> for ScrollingTextWidget<'a> {
         }
         false
     }
+
+    fn baseline(&self) -> u32 {
+        self.text.baseline()
+    }
 }
 
 impl<'a> Drawable for ScrollingTextWidget<'a> {
@@ -119,7 +124,8 @@ impl<'a> Drawable for ScrollingTextWidget<'a> {
                 },
             }
         };
-        self.text.draw(canvas, bounds.with_x(x), elapsed);
+        // The text is moved around within our bounds, so it can't clip itself
+        with_clip(canvas, bounds, |canvas| self.text.draw(canvas, bounds.with_x(x).with_width(text_size.width), elapsed));
     }
 }
 
@@ -130,4 +136,26 @@ impl<'a> Widget<(), u32> for ScrollingTextWidget<'a> {
             height: self.text.size().height,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::testing::test_font;
+
+    #[test]
+    fn short_text_is_aligned() {
+        let mut widget = ScrollingTextWidget::new("hi".to_string(), test_font(), 9.0, Alignment::Right,
+            Duration::from_millis(500), Duration::from_millis(500));
+        crate::snapshot!("scrolling_text_aligned", 32, 10).assert_matches(&mut widget);
+    }
+
+    #[test]
+    fn long_text_scrolls_and_resets() {
+        let mut widget = ScrollingTextWidget::new("scrolling text".to_string(), test_font(), 9.0, Alignment::Left,
+            Duration::from_millis(200), Duration::from_millis(200));
+        crate::snapshot!("scrolling_text_scrolls", 32, 10)
+            .with_interval(Duration::from_millis(250), 12)
+            .assert_matches(&mut widget);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::components::Drawable;
+use crate::components::{with_clip, Drawable};
 use crate::rendering;
 
 use super::super::{ Size, Widget, Bounds };
@@ -8,6 +8,7 @@ use super::TextWidget;
 pub struct SimpleTextWidget<'a> {
     text: String,
     rendered_text: rendering::Bitmap,
+    metrics: rendering::TextMetrics,
     font_size: f32,
     font: &'a fontdue::Font,
 }
@@ -16,38 +17,84 @@ impl<'a> SimpleTextWidget<'a> {
     pub fn new(text: String, font: &'a fontdue::Font, font_size: f32) -> Self {
         let bmp = rendering::Bitmap::from_text(&text, font_size, font);
         SimpleTextWidget {
+            metrics: rendering::measure_text(&text, font, font_size),
             text,
             rendered_text: bmp,
             font_size,
             font,
         }
     }
+
+    /// The size and baseline of the current text.
+    pub fn metrics(&self) -> &rendering::TextMetrics {
+        &self.metrics
+    }
 }
 
 impl<'a> TextWidget<u32, u32> for SimpleTextWidget<'a> {
     fn set_text(&mut self, text: &str ) -> bool {
         if self.text.ne(&text) {
+            self.text = text.to_string();
             self.rendered_text = rendering::Bitmap::from_text(text, self.font_size, self.font);
+            self.metrics = rendering::measure_text(text, self.font, self.font_size);
             return true;
         }
         false
     }
+
+    fn baseline(&self) -> u32 {
+        self.metrics.baseline as u32
+    }
 }
 
 impl<'a> Drawable for SimpleTextWidget<'a> {
     fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, _elapsed: &std::time::Duration) {
-        // TODO: figure out how to expose base height
-        // TODO: use size, avoid drawing out of bounds
-        canvas.draw_bitmap(bounds.pos.x, bounds.pos.y, &self.rendered_text);
+        with_clip(canvas, bounds, |canvas| canvas.draw_bitmap(bounds.pos.x, bounds.pos.y, &self.rendered_text));
     }
 }
 
 impl<'a> Widget<u32, u32> for SimpleTextWidget<'a> {
+    /// The size of the rendered text. Its height depends on the glyphs in the text, so use
+    /// [TextWidget::baseline] to line up text that changes.
     fn size(&self) -> Size<u32, u32> {
-        // TODO: allow controlling which height to use (total height or base height)
         Size {
             width: self.rendered_text.width.try_into().unwrap(),
             height: self.rendered_text.height.try_into().unwrap(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::EmptyBounds;
+    use crate::testing::test_font;
+
+    #[test]
+    fn simple_text_snapshot() {
+        let mut widget = SimpleTextWidget::new("OcuLED 42".to_string(), test_font(), 9.0);
+        crate::snapshot!("simple_text", 64, 12).assert_matches(&mut widget);
+        widget.set_text("ok");
+        crate::snapshot!("simple_text_changed", 64, 12).assert_matches(&mut widget);
+        assert!(!widget.set_text("ok"));
+    }
+
+    #[test]
+    fn text_lines_up_on_its_baseline() {
+        struct Temperature<'a>(SimpleTextWidget<'a>, SimpleTextWidget<'a>);
+        impl<'a> Drawable for Temperature<'a> {
+            fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
+                let baseline = bounds.pos.y + 14;
+                let value_bounds = EmptyBounds::new().with_size(self.0.size()).with_x(0).with_baseline_at(baseline, self.0.baseline());
+                self.0.draw(canvas, value_bounds, elapsed);
+                let unit_bounds = EmptyBounds::new().with_size(self.1.size()).right_of(&value_bounds).with_baseline_at(baseline, self.1.baseline());
+                self.1.draw(canvas, unit_bounds, elapsed);
+            }
+        }
+        let mut temperature = Temperature(
+            SimpleTextWidget::new("42".to_string(), test_font(), 18.0),
+            SimpleTextWidget::new("Cg".to_string(), test_font(), 9.0),
+        );
+        crate::snapshot!("text_baselines", 48, 18).assert_matches(&mut temperature);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::rendering::Video;
 
-use super::{Widget, Size, Bounds, Drawable};
+use super::{with_clip, Widget, Size, Bounds, Drawable};
 
 
 pub struct VideoWidget {
@@ -31,7 +31,7 @@ impl Drawable for VideoWidget {
             self.video.advance();
         }
         let frame = self.video.current_frame();
-        canvas.draw_bitmap(bounds.pos.x, bounds.pos.y, frame);
+        with_clip(canvas, bounds, |canvas| canvas.draw_bitmap(bounds.pos.x, bounds.pos.y, frame));
     }
 }
 
@@ -44,3 +44,20 @@ impl Widget<u32, u32> for VideoWidget {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::testing::SnapshotFormat;
+
+    #[test]
+    fn video_advances_with_time() {
+        let video = Video::from_gif(include_bytes!("../../../oculed/resources/gifs/infinity.gif"), true).unwrap();
+        let mut widget = VideoWidget::new(video, 10.0);
+        crate::snapshot!("video", 64, 64)
+            .with_frames(&[Duration::ZERO, Duration::from_millis(50), Duration::from_millis(60), Duration::from_millis(250)])
+            .with_format(SnapshotFormat::Png)
+            .assert_matches(&mut widget);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod components;
 pub mod rendering;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
@@ -8,25 +8,188 @@ pub struct BinaryBitmap {
     pub buffer: Vec<u8>,
 }
 
+/// The order pixels are packed into bytes in, see [BinaryBitmap::encode].
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum PixelLayout {
+    /// Rows from top to bottom, 8 pixels per byte with the leftmost pixel in the most significant bit.
+    /// This is the layout of [BinaryBitmap::buffer].
+    #[default]
+    RowMajorMsbFirst,
+    /// Rows from top to bottom, 8 pixels per byte with the leftmost pixel in the least significant bit.
+    RowMajorLsbFirst,
+    /// The SSD1306 controller's memory layout: pages of 8 rows from top to bottom, each page being one byte
+    /// per column from left to right, with the topmost pixel in the least significant bit.
+    Ssd1306Pages,
+    /// The SH1106 controller's memory layout. Like [PixelLayout::Ssd1306Pages], but the controller has
+    /// [SH1106_COLUMNS] columns, and the display shows them starting at `column_offset` (typically 2).
+    /// Columns outside the image are sent as 0, so every page can be written in one go.
+    Sh1106Pages { column_offset: usize },
+}
+
+/// The number of columns in SH1106 display memory.
+pub const SH1106_COLUMNS: usize = 132;
+
+impl PixelLayout {
+    /// The number of bytes a bitmap of the given size takes up in this layout.
+    pub fn encoded_len(&self, width: usize, height: usize) -> usize {
+        match self {
+            PixelLayout::RowMajorMsbFirst | PixelLayout::RowMajorLsbFirst => (width * height).div_ceil(8),
+            PixelLayout::Ssd1306Pages => width * height.div_ceil(8),
+            PixelLayout::Sh1106Pages { .. } => SH1106_COLUMNS * height.div_ceil(8),
+        }
+    }
+
+    /// The byte index and bit mask of a pixel.
+    fn position(&self, width: usize, x: usize, y: usize) -> (usize, u8) {
+        match self {
+            PixelLayout::RowMajorMsbFirst => ((y * width + x) / 8, 0x80 >> ((y * width + x) % 8)),
+            PixelLayout::RowMajorLsbFirst => ((y * width + x) / 8, 1 << ((y * width + x) % 8)),
+            PixelLayout::Ssd1306Pages => ((y / 8) * width + x, 1 << (y % 8)),
+            PixelLayout::Sh1106Pages { column_offset } => ((y / 8) * SH1106_COLUMNS + column_offset + x, 1 << (y % 8)),
+        }
+    }
+}
+
+impl BinaryBitmap {
+    /// Whether the pixel at (`x`, `y`) is on.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let (index, mask) = PixelLayout::RowMajorMsbFirst.position(self.width, x, y);
+        self.buffer[index] & mask != 0
+    }
+
+    /// Packs the pixels in the given layout, e.g. to send them to a display controller as is.
+    pub fn encode(&self, layout: PixelLayout) -> Vec<u8> {
+        if layout == PixelLayout::RowMajorMsbFirst {
+            return self.buffer.clone();
+        }
+        if let PixelLayout::Sh1106Pages { column_offset } = layout {
+            assert!(column_offset + self.width <= SH1106_COLUMNS, "Bitmap too wide for SH1106 memory");
+        }
+        let mut encoded = vec![0u8; layout.encoded_len(self.width, self.height)];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.pixel(x, y) {
+                    let (index, mask) = layout.position(self.width, x, y);
+                    encoded[index] |= mask;
+                }
+            }
+        }
+        encoded
+    }
+
+    /// Unpacks bytes encoded with [BinaryBitmap::encode].
+    pub fn decode(bytes: &[u8], width: usize, height: usize, layout: PixelLayout) -> Self {
+        assert_eq!(bytes.len(), layout.encoded_len(width, height), "Wrong number of bytes for bitmap size");
+        let mut bitmap = BinaryBitmap { width, height, buffer: vec![0u8; (width * height).div_ceil(8)] };
+        for y in 0..height {
+            for x in 0..width {
+                let (index, mask) = layout.position(width, x, y);
+                if bytes[index] & mask != 0 {
+                    let (index, mask) = PixelLayout::RowMajorMsbFirst.position(width, x, y);
+                    bitmap.buffer[index] |= mask;
+                }
+            }
+        }
+        bitmap
+    }
+}
+
 impl From<&super::bitmap::Bitmap> for BinaryBitmap {
     fn from(bitmap: &super::bitmap::Bitmap) -> Self {
-        assert!(bitmap.buffer.len() % 8 == 0);
+        assert!(bitmap.buffer.len().is_multiple_of(8));
 
         let mut buffer = vec![0u8; bitmap.width*bitmap.height/8];
-        for i in 0..buffer.len() {
-            let mut byte = 0u8;
+        for (i, byte) in buffer.iter_mut().enumerate() {
             for bit in 0..8 {
                 if bitmap.buffer[8*i + bit] > 0x40 {
-                    byte |= 1 << (7 - bit);
+                    *byte |= 1 << (7 - bit);
                 }
             }
-            buffer[i] = byte;
         }
 
         BinaryBitmap{
             width: bitmap.width,
             height: bitmap.height,
-            buffer: buffer,
+            buffer,
+        }
+    }
+}
+
+impl From<&BinaryBitmap> for super::bitmap::Bitmap {
+    /// Unpacks the bitmap, setting pixels to either 0x00 or 0xff.
+    fn from(bitmap: &BinaryBitmap) -> Self {
+        let mut unpacked = super::bitmap::Bitmap::new(bitmap.width, bitmap.height);
+        for (i, pixel) in unpacked.buffer.iter_mut().enumerate() {
+            if bitmap.buffer[i / 8] & (1 << (7 - i % 8)) != 0 {
+                *pixel = 0xff;
+            }
+        }
+        unpacked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::Bitmap;
+
+    const LAYOUTS: [PixelLayout; 4] = [
+        PixelLayout::RowMajorMsbFirst,
+        PixelLayout::RowMajorLsbFirst,
+        PixelLayout::Ssd1306Pages,
+        PixelLayout::Sh1106Pages { column_offset: 2 },
+    ];
+
+    /// A 16x16 bitmap with the top left pixel, a pixel on the second page and a diagonal line lit.
+    fn test_bitmap() -> BinaryBitmap {
+        let mut bitmap = Bitmap::new(16, 16);
+        bitmap.draw_pixel(0, 0);
+        bitmap.draw_pixel(3, 9);
+        bitmap.draw_line(15, 0, 0, 15);
+        (&bitmap).into()
+    }
+
+    #[test]
+    fn layouts_place_pixels_where_controllers_expect_them() {
+        let bitmap = test_bitmap();
+        let row_major = bitmap.encode(PixelLayout::RowMajorMsbFirst);
+        assert_eq!(row_major[0..2], [0b1000_0000, 0b0000_0001]);
+
+        let lsb_first = bitmap.encode(PixelLayout::RowMajorLsbFirst);
+        assert_eq!(lsb_first[0..2], [0b0000_0001, 0b1000_0000]);
+
+        let ssd1306 = bitmap.encode(PixelLayout::Ssd1306Pages);
+        assert_eq!(ssd1306.len(), 32);
+        // Column 0 of page 0 has the top left pixel, column 15 the top of the diagonal
+        assert_eq!(ssd1306[0], 0b0000_0001);
+        assert_eq!(ssd1306[15], 0b0000_0001);
+        // (3, 9) is bit 1 of column 3 on page 1, and the diagonal passes (3, 12)
+        assert_eq!(ssd1306[16 + 3], 0b0001_0010);
+
+        let sh1106 = bitmap.encode(PixelLayout::Sh1106Pages { column_offset: 2 });
+        assert_eq!(sh1106.len(), 2 * SH1106_COLUMNS);
+        assert_eq!(sh1106[0..3], [0, 0, 0b0000_0001]);
+        assert_eq!(sh1106[SH1106_COLUMNS + 2 + 3], 0b0001_0010);
+    }
+
+    #[test]
+    fn layouts_round_trip() {
+        let bitmap = test_bitmap();
+        for layout in LAYOUTS {
+            let decoded = BinaryBitmap::decode(&bitmap.encode(layout), 16, 16, layout);
+            assert_eq!(decoded.buffer, bitmap.buffer, "{:?}", layout);
+            assert_eq!(Bitmap::from(&decoded).buffer, Bitmap::from(&bitmap).buffer);
         }
     }
+
+    #[test]
+    fn pages_are_padded_for_odd_heights() {
+        let mut bitmap = Bitmap::new(8, 12);
+        bitmap.draw_pixel(7, 11);
+        let binary = BinaryBitmap::from(&bitmap);
+        let encoded = binary.encode(PixelLayout::Ssd1306Pages);
+        assert_eq!(encoded.len(), 16);
+        assert_eq!(encoded[15], 0b0000_1000);
+        assert_eq!(BinaryBitmap::decode(&encoded, 8, 12, PixelLayout::Ssd1306Pages).buffer, binary.buffer);
+    }
 }
@@ -1,27 +1,79 @@
 use image::GenericImageView;
 
+use super::glyph_cache::{Glyph, GlyphCache};
+
 /// A monochrome buffer we can draw to. Pixels are 1 byte each.
+///
+/// Drawing is limited to the clip rectangle set with [Bitmap::push_clip], if any.
 #[derive(Clone)]
 pub struct Bitmap {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u8>,
+    /// Active clip rectangles, each one already intersected with the ones before it
+    clip_stack: Vec<ClipRect>,
+}
+
+/// How the pixels of a bitmap drawn with [Bitmap::draw_bitmap_with] are combined with the pixels under it.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum BlendMode {
+    /// Replace the pixels under the bitmap
+    Overwrite,
+    /// Keep the brightest pixel
+    #[default]
+    Or,
+    /// Keep the darkest pixel, i.e. only keep what is under the bitmap's lit pixels
+    And,
+    Xor,
+    /// Invert the pixels under the bitmap's lit pixels, leaving the rest untouched. Keeps shapes visible on
+    /// both dark and light backgrounds.
+    InvertUnder,
+    /// Like [BlendMode::Overwrite], but pixels that are 0 in the bitmap are transparent
+    Masked,
+}
+
+impl BlendMode {
+    fn blend(self, under: u8, over: u8) -> u8 {
+        match self {
+            BlendMode::Overwrite => over,
+            BlendMode::Or => under | over,
+            BlendMode::And => under & over,
+            BlendMode::Xor => under ^ over,
+            // Partially lit pixels (e.g. antialiased text edges) partially invert
+            BlendMode::InvertUnder => {
+                let (under, over) = (under as u32, over as u32);
+                ((under * (255 - over) + (255 - under) * over) / 255) as u8
+            },
+            BlendMode::Masked => if over == 0 { under } else { over },
+        }
+    }
+}
+
+/// The area drawing is limited to, as half-open ranges.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct ClipRect {
+    pub x_start: usize,
+    pub x_end: usize,
+    pub y_start: usize,
+    pub y_end: usize,
 }
 
 impl Bitmap {
     pub fn new(width: usize, height: usize) -> Self {
-        return Bitmap{
+        Bitmap {
             width,
             height,
             buffer: vec![0u8; width * height],
+            clip_stack: Vec::new(),
         }
     }
 
     pub fn from_raw_bytes(bytes: &[u8], width: usize) -> Self {
-        return Bitmap {
+        Bitmap {
             width,
             height: bytes.len() / width,
             buffer: bytes.to_vec(),
+            clip_stack: Vec::new(),
         }
     }
 
@@ -46,69 +98,95 @@ impl Bitmap {
             width: dimensions.0 as usize,
             height: dimensions.1 as usize,
             buffer: buf,
+            clip_stack: Vec::new(),
         }
     }
 
+    /// Renders a single line of text, cropped to the glyphs. See [measure_text] for where the baseline ends up.
     pub fn from_text(text: &str, font_size: f32, font: &fontdue::Font) -> Self {
         let text_metrics = measure_text(text, font, font_size);
-        let baseline = text_metrics.base_height as i32;
-        let mut next_x = 0.0;
+        let baseline = text_metrics.baseline as i32;
 
         let mut bmp = Bitmap::new(text_metrics.width, text_metrics.height);
-        for character in text.chars() {
-            let (metrics, buffer) = font.rasterize(character, font_size);
-
-            let padding = metrics.advance_width - metrics.width as f32;
+        for (glyph, x) in glyph_positions(text, font, font_size).0 {
+            let metrics = glyph.metrics;
             let top = baseline - metrics.height as i32 - metrics.ymin;
-            let char_bmp = Bitmap{ width: metrics.width, height: metrics.height, buffer: buffer};
-            bmp.draw_bitmap((next_x + padding / 2.0) as i32, top, &char_bmp);
-
-            next_x += metrics.advance_width;
+            bmp.draw_bitmap((x + metrics.xmin as f32).round() as i32, top, &glyph.bitmap);
         }
         bmp
-   }
+    }
 
     pub fn clear(&mut self) {
         self.buffer.fill(0);
     }
 
+    /// Limits drawing to the given rectangle (intersected with any previous one), until [Bitmap::pop_clip]
+    /// is called.
+    pub fn push_clip(&mut self, x: i32, y: i32, width: usize, height: usize) {
+        let current = self.clip();
+        let clamp = |value: i32, start: usize, end: usize| (value.max(0) as usize).clamp(start, end);
+        let x_start = clamp(x, current.x_start, current.x_end);
+        let y_start = clamp(y, current.y_start, current.y_end);
+        self.clip_stack.push(ClipRect {
+            x_start,
+            x_end: clamp(x.saturating_add(width as i32), x_start, current.x_end),
+            y_start,
+            y_end: clamp(y.saturating_add(height as i32), y_start, current.y_end),
+        });
+    }
+
+    /// Removes the clip rectangle added by the last call to [Bitmap::push_clip].
+    pub fn pop_clip(&mut self) {
+        self.clip_stack.pop().expect("pop_clip called without matching push_clip");
+    }
+
+    /// The area that may currently be drawn to.
+    pub(crate) fn clip(&self) -> ClipRect {
+        self.clip_stack.last().copied().unwrap_or(ClipRect {
+            x_start: 0,
+            x_end: self.width,
+            y_start: 0,
+            y_end: self.height,
+        })
+    }
+
+    /// Draws `bitmap` with its top left corner at (`x`, `y`), keeping the brightest of the new and existing
+    /// pixels. See [Bitmap::draw_bitmap_with] for other ways of combining them.
     pub fn draw_bitmap(&mut self, x: i32, y: i32, bitmap: &Bitmap) {
+        self.draw_bitmap_with(x, y, bitmap, BlendMode::Or);
+    }
+
+    pub fn draw_bitmap_with(&mut self, x: i32, y: i32, bitmap: &Bitmap, mode: BlendMode) {
+        let clip = self.clip();
         // could use memcpy or vectorization for better performance
         for bmp_y in 0..bitmap.height {
             let actual_y = y + bmp_y as i32;
-            if actual_y < 0 { continue; }
-            if actual_y as usize >= self.height { return; }
+            if actual_y < clip.y_start as i32 { continue; }
+            if actual_y >= clip.y_end as i32 { return; }
             for bmp_x in 0..bitmap.width {
                 let actual_x = x + bmp_x as i32;
-                if actual_x < 0 { continue; }
-                if actual_x as usize >= self.width { break; }
+                if actual_x < clip.x_start as i32 { continue; }
+                if actual_x >= clip.x_end as i32 { break; }
 
-                self.buffer[(actual_y * self.width as i32 + actual_x) as usize] |= bitmap.buffer[bmp_x + bmp_y * bitmap.width];
+                let pixel = &mut self.buffer[(actual_y * self.width as i32 + actual_x) as usize];
+                *pixel = mode.blend(*pixel, bitmap.buffer[bmp_x + bmp_y * bitmap.width]);
             }
         }
     }
 
     pub fn draw_rect(&mut self, x: i32, y: i32, width: usize, height: usize) {
-        let x_start = x.max(0).min(self.width as i32) as usize;
-        let x_end = (x + width as i32).max(0).min(self.width as i32) as usize;
-        let y_start = y.max(0).min(self.height as i32) as usize;
-        let y_end = (y + height as i32).max(0).min(self.height as i32) as usize;
-
-        for y_draw in y_start..y_end {
-            for x_draw in x_start..x_end {
-                self.buffer[y_draw * self.width + x_draw] = 0xff;
-            }
-        }
+        self.draw_rect_with_slits(x, y, width, height, 0);
     }
+    /// Draws a filled rectangle with diagonal gaps every `slit_interval` pixels. An interval of 0 gives a
+    /// solid rectangle.
     pub fn draw_rect_with_slits(&mut self, x: i32, y: i32, width: usize, height: usize, slit_interval: usize) {
-        let x_start = x.max(0).min(self.width as i32) as usize;
-        let x_end = (x + width as i32).max(0).min(self.width as i32) as usize;
-        let y_start = y.max(0).min(self.height as i32) as usize;
-        let y_end = (y + height as i32).max(0).min(self.height as i32) as usize;
-
-        for y_draw in y_start..y_end {
-            for x_draw in x_start..x_end {
-                if y_draw % slit_interval == x_draw % slit_interval { continue; }
+        self.push_clip(x, y, width, height);
+        let area = self.clip();
+        self.pop_clip();
+
+        for y_draw in area.y_start..area.y_end {
+            for x_draw in area.x_start..area.x_end {
+                if slit_interval > 0 && y_draw % slit_interval == x_draw % slit_interval { continue; }
                 self.buffer[y_draw * self.width + x_draw] = 0xff;
             }
         }
@@ -116,35 +194,160 @@ impl Bitmap {
 }
 
 /// Describes the size of a string of text for some font and font size
-struct TextMetrics {
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TextMetrics {
+    /// The width of the rendered text, including the advance of the last character
     pub width: usize,
+    /// The height of the rendered text, from the top of its tallest glyph to the bottom of its lowest
     pub height: usize,
-    /* The distance from the top of the text to the baseline */
-    pub base_height: usize,
+    /// The distance from the top of the rendered text to the baseline
+    pub baseline: usize,
+    /// How far the font reaches above the baseline, whatever the text. Use this rather than [TextMetrics::baseline]
+    /// to space lines evenly.
+    pub ascent: f32,
+    /// How far the font reaches below the baseline, whatever the text. Positive, unlike fontdue's.
+    pub descent: f32,
 }
 
-/// Measures the size of some text without rendering it. The metrics return
-/// describe the size the text would have if rendered.
-fn measure_text(text: &str, font: &fontdue::Font, font_size: f32) -> TextMetrics {
-    let mut x = 0f32;
-    let mut base_height = 0i32;
+/// Measures the size of some text without rendering it. The metrics returned
+/// describe the size the text would have if rendered with [Bitmap::from_text].
+pub fn measure_text(text: &str, font: &fontdue::Font, font_size: f32) -> TextMetrics {
+    let mut right = 0f32;
+    let mut top = 0i32;
     let mut bottom = 0i32;
 
+    let (glyphs, advance) = glyph_positions(text, font, font_size);
+    for (glyph, x) in glyphs {
+        let metrics = glyph.metrics;
+        if metrics.width > 0 {
+            right = right.max((x + metrics.xmin as f32).round() + metrics.width as f32);
+        }
+        if metrics.height > 0 {
+            top = top.max(metrics.height as i32 + metrics.ymin);
+            bottom = bottom.max(-metrics.ymin);
+        }
+    }
+    let (ascent, descent) = match font.horizontal_line_metrics(font_size) {
+        Some(line_metrics) => (line_metrics.ascent, -line_metrics.descent),
+        None => (top as f32, bottom as f32),
+    };
+    TextMetrics {
+        width: right.max(advance.round()) as usize,
+        height: (top + bottom) as usize,
+        baseline: top as usize,
+        ascent,
+        descent,
+    }
+}
+
+/// The glyphs of `text` and where the pen is for each, with kerning applied, and where it ends up after the last
+/// one. The glyphs come from [GlyphCache::shared].
+fn glyph_positions(text: &str, font: &fontdue::Font, font_size: f32) -> (Vec<(Glyph, f32)>, f32) {
+    let mut cache = GlyphCache::shared().lock().unwrap();
+    let mut x = 0f32;
+    let mut previous = None;
+    let mut positions = Vec::with_capacity(text.len());
     for character in text.chars() {
-        let metrics = font.metrics(character, font_size);
-        let top = -(metrics.height as i32) - metrics.ymin;
-        if top < base_height {
-            base_height = top;
+        if let Some(previous) = previous {
+            x += font.horizontal_kern(previous, character, font_size).unwrap_or(0.0);
         }
-        if -metrics.ymin > bottom {
-            bottom = -metrics.ymin;
+        let glyph = cache.glyph(font, font_size, character);
+        let advance = glyph.metrics.advance_width;
+        positions.push((glyph, x));
+        x += advance;
+        previous = Some(character);
+    }
+    (positions, x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_modes() {
+        let under = [0x00, 0x00, 0xff, 0xff, 0x80];
+        let over = [0x00, 0xff, 0x00, 0xff, 0xff];
+        let expected: [(BlendMode, [u8; 5]); 6] = [
+            (BlendMode::Overwrite, [0x00, 0xff, 0x00, 0xff, 0xff]),
+            (BlendMode::Or, [0x00, 0xff, 0xff, 0xff, 0xff]),
+            (BlendMode::And, [0x00, 0x00, 0x00, 0xff, 0x80]),
+            (BlendMode::Xor, [0x00, 0xff, 0xff, 0x00, 0x7f]),
+            (BlendMode::InvertUnder, [0x00, 0xff, 0xff, 0x00, 0x7f]),
+            (BlendMode::Masked, [0x00, 0xff, 0xff, 0xff, 0xff]),
+        ];
+        for (mode, expected) in expected {
+            let mut canvas = Bitmap::from_raw_bytes(&under, 5);
+            canvas.draw_bitmap_with(0, 0, &Bitmap::from_raw_bytes(&over, 5), mode);
+            assert_eq!(canvas.buffer, expected, "{:?}", mode);
         }
+        // Half lit pixels half invert
+        assert_eq!(BlendMode::InvertUnder.blend(0x00, 0x80), 0x80);
+        assert_eq!(BlendMode::InvertUnder.blend(0xff, 0x80), 0x7f);
+    }
 
-        x += metrics.advance_width;
+    #[test]
+    fn text_is_kerned() {
+        let font = fontdue::Font::from_bytes(&include_bytes!("../../../oculed/resources/fonts/Roadrage.otf")[..], fontdue::FontSettings::default()).unwrap();
+        let kern = font.horizontal_kern('A', 'V', 30.0).unwrap();
+        assert!(kern < 0.0);
+        let (positions, advance) = glyph_positions("AV", &font, 30.0);
+        assert_eq!(positions[1].1, font.metrics('A', 30.0).advance_width + kern);
+        assert_eq!(advance, positions[1].1 + font.metrics('V', 30.0).advance_width);
     }
-    return TextMetrics {
-        width: x as usize,
-        height: (bottom - base_height) as usize,
-        base_height: (-base_height) as usize,
-    };
-}
\ No newline at end of file
+
+    #[test]
+    fn text_metrics_describe_the_rendered_text() {
+        let font = crate::testing::test_font();
+        let metrics = measure_text("Cg", font, 9.0);
+        let rendered = Bitmap::from_text("Cg", 9.0, font);
+        assert_eq!((metrics.width, metrics.height), (rendered.width, rendered.height));
+        // The g reaches below the baseline
+        assert!(metrics.baseline < metrics.height);
+        assert_eq!(measure_text("C", font, 9.0).baseline, metrics.baseline);
+        assert!(metrics.ascent >= metrics.baseline as f32 && metrics.descent > 0.0);
+        assert_eq!((measure_text("", font, 9.0).ascent, measure_text("", font, 9.0).descent), (metrics.ascent, metrics.descent));
+    }
+
+    #[test]
+    fn text_uses_the_shared_glyph_cache() {
+        let font = crate::testing::test_font();
+        let rendered = Bitmap::from_text("cached", 11.0, font);
+        // Other tests use the cache at the same time, so only the lower bound is known
+        let hits = GlyphCache::shared().lock().unwrap().stats().hits;
+        assert_eq!(Bitmap::from_text("cached", 11.0, font).buffer, rendered.buffer);
+        measure_text("cached", font, 11.0);
+        assert!(GlyphCache::shared().lock().unwrap().stats().hits >= hits + 12);
+    }
+
+    #[test]
+    fn clip_rects_nest() {
+        let mut canvas = Bitmap::new(6, 4);
+        canvas.push_clip(1, -2, 10, 5);
+        canvas.push_clip(-1, 1, 4, 10);
+        assert_eq!(canvas.clip(), ClipRect { x_start: 1, x_end: 3, y_start: 1, y_end: 3 });
+        canvas.draw_rect(-10, -10, 20, 20);
+        canvas.pop_clip();
+        canvas.draw_pixel(5, 0);
+        canvas.pop_clip();
+        canvas.draw_pixel(0, 3);
+        let expected = [
+            0, 0, 0, 0, 0, 0xff,
+            0, 0xff, 0xff, 0, 0, 0,
+            0, 0xff, 0xff, 0, 0, 0,
+            0xff, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(canvas.buffer, expected);
+    }
+
+    #[test]
+    fn empty_clip_draws_nothing() {
+        let mut canvas = Bitmap::new(4, 4);
+        canvas.push_clip(2, 2, 2, 2);
+        canvas.push_clip(0, 0, 2, 2);
+        canvas.draw_bitmap(0, 0, &Bitmap::from_raw_bytes(&[0xff; 16], 4));
+        canvas.fill_circle(2, 2, 3);
+        canvas.draw_line(0, 0, 3, 3);
+        assert!(canvas.buffer.iter().all(|p| *p == 0));
+    }
+}
@@ -0,0 +1,211 @@
+use super::{Bitmap, BinaryBitmap};
+
+/// The golden ratio's fractional part. Stepping by it visits thresholds evenly, however many frames pass.
+const TEMPORAL_STEP: f32 = 0.618_034;
+
+/// A strategy for reducing grayscale pixels to fully on or off.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dithering {
+    /// Pixels brighter than the level are on. Best for text and line art.
+    Threshold(u8),
+    /// Ordered dithering with a Bayer matrix of the given size (2, 4 or 8). Gives a regular pattern that
+    /// stays stable between frames, so it works well for animations.
+    Bayer(usize),
+    /// Floyd–Steinberg error diffusion. Keeps the most detail in photos, but patterns shift when the image
+    /// changes.
+    FloydSteinberg,
+    /// Atkinson error diffusion. Only diffuses part of the error, giving more contrast than Floyd–Steinberg.
+    Atkinson,
+    /// A threshold that changes every frame, so gray pixels flicker between on and off. At high frame rates
+    /// this looks like actual gray levels.
+    Temporal,
+}
+
+impl Default for Dithering {
+    fn default() -> Self {
+        Dithering::Threshold(0x40)
+    }
+}
+
+/// Applies a [Dithering] to consecutive frames.
+#[derive(Clone, Debug)]
+pub struct Ditherer {
+    dithering: Dithering,
+    /// The number of frames dithered so far, used by [Dithering::Temporal]
+    frame: u32,
+}
+
+impl Ditherer {
+    pub fn new(dithering: Dithering) -> Self {
+        Self { dithering, frame: 0 }
+    }
+
+    pub fn dithering(&self) -> Dithering {
+        self.dithering
+    }
+
+    /// Dithers `bitmap` in place, setting every pixel to either 0x00 or 0xff.
+    pub fn dither(&mut self, bitmap: &mut Bitmap) {
+        let width = bitmap.width;
+        match self.dithering {
+            Dithering::Threshold(level) => {
+                for pixel in bitmap.buffer.iter_mut() {
+                    *pixel = if *pixel > level { 0xff } else { 0 };
+                }
+            },
+            Dithering::Bayer(size) => {
+                let matrix = bayer_matrix(size);
+                for (i, pixel) in bitmap.buffer.iter_mut().enumerate() {
+                    let threshold = matrix[(i / width % size) * size + i % width % size];
+                    *pixel = if *pixel as f32 > threshold * 255.0 { 0xff } else { 0 };
+                }
+            },
+            Dithering::FloydSteinberg => diffuse_error(bitmap, &[(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)], 16),
+            Dithering::Atkinson => diffuse_error(bitmap, &[(1, 0, 1), (2, 0, 1), (-1, 1, 1), (0, 1, 1), (1, 1, 1), (0, 2, 1)], 8),
+            Dithering::Temporal => {
+                let matrix = bayer_matrix(4);
+                let offset = (self.frame as f32 * TEMPORAL_STEP).fract();
+                for (i, pixel) in bitmap.buffer.iter_mut().enumerate() {
+                    let threshold = (matrix[(i / width % 4) * 4 + i % width % 4] + offset).fract();
+                    *pixel = if *pixel as f32 > threshold * 255.0 { 0xff } else { 0 };
+                }
+            },
+        }
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    /// Dithers a copy of `bitmap` and packs it, leaving `bitmap` untouched.
+    pub fn to_binary(&mut self, bitmap: &Bitmap) -> BinaryBitmap {
+        let mut dithered = bitmap.clone();
+        self.dither(&mut dithered);
+        (&dithered).into()
+    }
+}
+
+/// Thresholds in the range (0, 1) for ordered dithering, row by row. `size` must be a power of two.
+fn bayer_matrix(size: usize) -> Vec<f32> {
+    assert!(size.is_power_of_two(), "Bayer matrix size must be a power of two");
+    // Each level is built from four copies of the previous one: [4m, 4m+2; 4m+3, 4m+1]
+    let mut matrix = vec![0u32];
+    let mut n = 1;
+    while n < size {
+        let mut next = vec![0u32; 4 * n * n];
+        for y in 0..n {
+            for x in 0..n {
+                let value = 4 * matrix[y * n + x];
+                next[y * 2 * n + x] = value;
+                next[y * 2 * n + x + n] = value + 2;
+                next[(y + n) * 2 * n + x] = value + 3;
+                next[(y + n) * 2 * n + x + n] = value + 1;
+            }
+        }
+        matrix = next;
+        n *= 2;
+    }
+    matrix.into_iter().map(|value| (value as f32 + 0.5) / (size * size) as f32).collect()
+}
+
+/// Thresholds every pixel at the midpoint, spreading the difference to neighbouring pixels. `weights`
+/// are (x offset, y offset, weight) and are divided by `divisor`.
+fn diffuse_error(bitmap: &mut Bitmap, weights: &[(i32, i32, i32)], divisor: i32) {
+    let (width, height) = (bitmap.width as i32, bitmap.height as i32);
+    let mut values = bitmap.buffer.iter().map(|pixel| *pixel as i32).collect::<Vec<i32>>();
+    for y in 0..height {
+        for x in 0..width {
+            let value = values[(y * width + x) as usize];
+            let output = if value >= 0x80 { 0xff } else { 0 };
+            bitmap.buffer[(y * width + x) as usize] = output as u8;
+            let error = value - output;
+            for (dx, dy, weight) in weights {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx >= 0 && nx < width && ny < height {
+                    values[(ny * width + nx) as usize] += error * weight / divisor;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A horizontal gradient from black to white.
+    fn gradient(width: usize, height: usize) -> Bitmap {
+        let mut bitmap = Bitmap::new(width, height);
+        for (i, pixel) in bitmap.buffer.iter_mut().enumerate() {
+            *pixel = ((i % width) * 255 / (width - 1)) as u8;
+        }
+        bitmap
+    }
+
+    fn lit_fraction(bitmap: &Bitmap) -> f32 {
+        bitmap.buffer.iter().filter(|p| **p == 0xff).count() as f32 / bitmap.buffer.len() as f32
+    }
+
+    #[test]
+    fn bayer_matrices_contain_every_level_once() {
+        assert_eq!(bayer_matrix(2), vec![0.125, 0.625, 0.875, 0.375]);
+        let mut levels = bayer_matrix(8).into_iter().map(|t| (t * 64.0) as u32).collect::<Vec<u32>>();
+        levels.sort();
+        assert_eq!(levels, (0..64).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn every_strategy_preserves_brightness() {
+        for dithering in [Dithering::Bayer(2), Dithering::Bayer(8), Dithering::FloydSteinberg, Dithering::Temporal] {
+            for gray in [0x00u8, 0x40, 0x80, 0xc0, 0xff] {
+                let mut bitmap = Bitmap::from_raw_bytes(&[gray; 64 * 32], 64);
+                Ditherer::new(dithering).dither(&mut bitmap);
+                assert!(bitmap.buffer.iter().all(|p| *p == 0 || *p == 0xff));
+                let expected = gray as f32 / 255.0;
+                assert!((lit_fraction(&bitmap) - expected).abs() < 0.04, "{:?} at {}: {}", dithering, gray, lit_fraction(&bitmap));
+            }
+        }
+    }
+
+    #[test]
+    fn atkinson_loses_some_error() {
+        // A quarter of the error is dropped, pushing midtones apart
+        let mut dark = Bitmap::from_raw_bytes(&[0x30; 64 * 32], 64);
+        Ditherer::new(Dithering::Atkinson).dither(&mut dark);
+        assert!(lit_fraction(&dark) < 0x30 as f32 / 255.0);
+        let mut white = Bitmap::from_raw_bytes(&[0xff; 64 * 32], 64);
+        Ditherer::new(Dithering::Atkinson).dither(&mut white);
+        assert_eq!(lit_fraction(&white), 1.0);
+    }
+
+    #[test]
+    fn temporal_dithering_averages_over_frames() {
+        let mut ditherer = Ditherer::new(Dithering::Temporal);
+        let mut on_count = 0;
+        for _ in 0..100 {
+            let mut pixel = Bitmap::from_raw_bytes(&[0x40], 1);
+            ditherer.dither(&mut pixel);
+            on_count += (pixel.buffer[0] == 0xff) as u32;
+        }
+        assert!((20..30).contains(&on_count), "{}", on_count);
+    }
+
+    #[test]
+    fn threshold_is_the_default() {
+        let bitmap = Bitmap::from_raw_bytes(&[0x40, 0x41, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00], 8);
+        let binary = Ditherer::new(Dithering::default()).to_binary(&bitmap);
+        assert_eq!(binary.buffer, vec![0b0110_0000]);
+        assert_eq!(binary.buffer, BinaryBitmap::from(&bitmap).buffer);
+    }
+
+    #[test]
+    fn gradient_snapshots() {
+        for (name, dithering) in [
+            ("dithering_threshold", Dithering::Threshold(0x80)),
+            ("dithering_bayer", Dithering::Bayer(4)),
+            ("dithering_floyd_steinberg", Dithering::FloydSteinberg),
+            ("dithering_atkinson", Dithering::Atkinson),
+        ] {
+            let mut bitmap = gradient(48, 8);
+            Ditherer::new(dithering).dither(&mut bitmap);
+            crate::snapshot!(name, 48, 8).assert_frames_match(&[bitmap]);
+        }
+    }
+}
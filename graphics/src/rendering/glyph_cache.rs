@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::Bitmap;
+
+/// How many bytes of glyph bitmaps [GlyphCache::shared] keeps by default. Enough for a few fonts at a few sizes.
+pub const DEFAULT_CAPACITY: usize = 512 * 1024;
+/// What each glyph costs besides its pixels, so tiny glyphs like spaces can't fill the cache unnoticed
+const ENTRY_OVERHEAD: usize = 64;
+
+/// A rasterized character, see [fontdue::Font::rasterize].
+#[derive(Clone)]
+pub struct Glyph {
+    pub metrics: fontdue::Metrics,
+    pub bitmap: Arc<Bitmap>,
+}
+
+/// How well a [GlyphCache] is doing.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct GlyphCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// How many glyphs were dropped to stay within the capacity
+    pub evictions: u64,
+    /// How many glyphs are cached right now
+    pub glyphs: usize,
+    /// How much memory the cached glyphs use, in bytes
+    pub bytes: usize,
+}
+
+impl GlyphCacheStats {
+    /// The share of lookups that didn't need to rasterize, from 0.0 to 1.0.
+    pub fn hit_rate(&self) -> f32 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f32 / lookups as f32,
+        }
+    }
+}
+
+/// Which glyph of which font, at which size. Fonts are told apart by the hash of their file.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct GlyphKey {
+    font_hash: usize,
+    size_bits: u32,
+    character: char,
+}
+
+struct Entry {
+    glyph: Glyph,
+    /// When the glyph was last looked up, to evict the least recently used glyphs first
+    last_used: u64,
+}
+
+/// Rasterized glyphs keyed by font, size and character, so text that is rendered over and over only rasterizes
+/// each glyph once. The least recently used glyphs are dropped once they take up more than the capacity.
+///
+/// Fonts loaded from the same file share their glyphs, so they should all be loaded with the same
+/// [fontdue::FontSettings].
+///
+/// [Bitmap::from_text] and [super::measure_text] share the cache returned by [GlyphCache::shared].
+pub struct GlyphCache {
+    entries: HashMap<GlyphKey, Entry>,
+    /// The keys of the entries by [Entry::last_used], least recently used first
+    order: BTreeMap<u64, GlyphKey>,
+    capacity: usize,
+    /// Counts lookups, as a clock for [Entry::last_used]
+    tick: u64,
+    stats: GlyphCacheStats,
+}
+
+impl GlyphCache {
+    /// Creates a cache keeping at most `capacity` bytes of glyphs.
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: HashMap::new(), order: BTreeMap::new(), capacity, tick: 0, stats: GlyphCacheStats::default() }
+    }
+
+    /// The cache used for all text rendering.
+    pub fn shared() -> &'static Mutex<GlyphCache> {
+        static SHARED: OnceLock<Mutex<GlyphCache>> = OnceLock::new();
+        SHARED.get_or_init(|| Mutex::new(GlyphCache::new(DEFAULT_CAPACITY)))
+    }
+
+    /// Returns the glyph for `character`, rasterizing it unless it's cached.
+    pub fn glyph(&mut self, font: &fontdue::Font, font_size: f32, character: char) -> Glyph {
+        self.tick += 1;
+        let key = GlyphKey {
+            font_hash: font.file_hash(),
+            size_bits: font_size.to_bits(),
+            character,
+        };
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.order.remove(&entry.last_used);
+            self.order.insert(self.tick, key);
+            entry.last_used = self.tick;
+            self.stats.hits += 1;
+            return entry.glyph.clone();
+        }
+        self.stats.misses += 1;
+
+        let (metrics, buffer) = font.rasterize(character, font_size);
+        let mut bitmap = Bitmap::new(metrics.width, metrics.height);
+        bitmap.buffer = buffer;
+        let glyph = Glyph { metrics, bitmap: Arc::new(bitmap) };
+        let size = entry_size(&glyph);
+        if size > self.capacity {
+            return glyph;
+        }
+        while self.stats.bytes + size > self.capacity {
+            self.evict_least_recently_used();
+        }
+        self.entries.insert(key, Entry { glyph: glyph.clone(), last_used: self.tick });
+        self.order.insert(self.tick, key);
+        self.stats.bytes += size;
+        self.stats.glyphs = self.entries.len();
+        glyph
+    }
+
+    pub fn stats(&self) -> GlyphCacheStats {
+        self.stats
+    }
+
+    /// Sets how many bytes of glyphs to keep, dropping glyphs if there already are more.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.stats.bytes > self.capacity {
+            self.evict_least_recently_used();
+        }
+    }
+
+    /// Drops all glyphs. The hit and miss counts are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.stats.glyphs = 0;
+        self.stats.bytes = 0;
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let Some((_, key)) = self.order.pop_first() else { return; };
+        let entry = self.entries.remove(&key).unwrap();
+        self.stats.bytes -= entry_size(&entry.glyph);
+        self.stats.glyphs = self.entries.len();
+        self.stats.evictions += 1;
+    }
+}
+
+fn entry_size(glyph: &Glyph) -> usize {
+    glyph.bitmap.buffer.len() + ENTRY_OVERHEAD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_font;
+
+    #[test]
+    fn glyphs_are_rasterized_once() {
+        let mut cache = GlyphCache::new(DEFAULT_CAPACITY);
+        let first = cache.glyph(test_font(), 9.0, 'a');
+        let second = cache.glyph(test_font(), 9.0, 'a');
+        assert!(Arc::ptr_eq(&first.bitmap, &second.bitmap));
+        assert_eq!(first.bitmap.buffer, test_font().rasterize('a', 9.0).1);
+        // Other sizes are other glyphs
+        cache.glyph(test_font(), 10.0, 'a');
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.glyphs), (1, 2, 2));
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn least_recently_used_glyphs_are_evicted() {
+        let size = |character| test_font().rasterize(character, 9.0).1.len() + ENTRY_OVERHEAD;
+        let mut cache = GlyphCache::new(size('a') + size('b') + size('c') - 1);
+        cache.glyph(test_font(), 9.0, 'a');
+        cache.glyph(test_font(), 9.0, 'b');
+        cache.glyph(test_font(), 9.0, 'a');
+        cache.glyph(test_font(), 9.0, 'c');
+        let stats = cache.stats();
+        assert_eq!((stats.glyphs, stats.evictions, stats.bytes), (2, 1, size('a') + size('c')));
+        // 'a' was used more recently than 'b', so it's still cached
+        cache.glyph(test_font(), 9.0, 'a');
+        assert_eq!(cache.stats().hits, 2);
+
+        cache.set_capacity(0);
+        assert_eq!((cache.stats().glyphs, cache.stats().bytes), (0, 0));
+        cache.glyph(test_font(), 9.0, 'a');
+        assert_eq!(cache.stats().glyphs, 0);
+    }
+
+    #[test]
+    fn fonts_are_told_apart_by_their_file() {
+        let bytes = include_bytes!("../../../oculed/resources/fonts/pixeloid/PixeloidMono.ttf");
+        let font = fontdue::Font::from_bytes(&bytes[..], fontdue::FontSettings::default()).unwrap();
+        let other = fontdue::Font::from_bytes(&include_bytes!("../../../oculed/resources/fonts/Roadrage.otf")[..], fontdue::FontSettings::default()).unwrap();
+
+        let mut cache = GlyphCache::new(DEFAULT_CAPACITY);
+        let test_glyph = cache.glyph(test_font(), 9.0, 'a');
+        // A second copy of the same file shares its glyphs
+        let glyph = cache.glyph(&font, 9.0, 'a');
+        assert!(Arc::ptr_eq(&glyph.bitmap, &test_glyph.bitmap));
+        let other_glyph = cache.glyph(&other, 9.0, 'a');
+        assert_eq!(other_glyph.bitmap.buffer, other.rasterize('a', 9.0).1);
+        assert_eq!((cache.stats().hits, cache.stats().misses), (1, 2));
+    }
+}
@@ -1,7 +1,12 @@
 mod bitmap;
 mod binary_bitmap;
 mod video;
+mod primitives;
+mod dithering;
+mod glyph_cache;
 
-pub use bitmap::Bitmap;
-pub use binary_bitmap::BinaryBitmap;
+pub use bitmap::{Bitmap, BlendMode, TextMetrics, measure_text};
+pub use binary_bitmap::{BinaryBitmap, PixelLayout, SH1106_COLUMNS};
 pub use video::Video;
+pub use dithering::{Dithering, Ditherer};
+pub use glyph_cache::{Glyph, GlyphCache, GlyphCacheStats};
@@ -0,0 +1,327 @@
+//! Shape drawing on [Bitmap]s. Shapes are drawn at full brightness, and any part outside the bitmap (or its
+//! clip rectangle) is clipped, so coordinates may be negative or larger than the bitmap.
+
+use super::Bitmap;
+
+impl Bitmap {
+    /// Sets a single pixel, if it is inside the clip rectangle.
+    pub fn draw_pixel(&mut self, x: i32, y: i32) {
+        let clip = self.clip();
+        if x >= clip.x_start as i32 && y >= clip.y_start as i32 && x < clip.x_end as i32 && y < clip.y_end as i32 {
+            self.buffer[y as usize * self.width + x as usize] = 0xff;
+        }
+    }
+
+    /// Draws a one pixel wide line between two points (both included), using Bresenham's algorithm.
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y) = (x0, y0);
+        let mut error = dx + dy;
+        loop {
+            self.draw_pixel(x, y);
+            if x == x1 && y == y1 { return; }
+            let error2 = 2 * error;
+            if error2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if error2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Draws the outline of a circle centered at (`cx`, `cy`).
+    pub fn draw_circle(&mut self, cx: i32, cy: i32, radius: usize) {
+        self.draw_ellipse(cx, cy, radius, radius);
+    }
+
+    /// Draws a filled circle centered at (`cx`, `cy`).
+    pub fn fill_circle(&mut self, cx: i32, cy: i32, radius: usize) {
+        self.fill_ellipse(cx, cy, radius, radius);
+    }
+
+    /// Draws the outline of an ellipse centered at (`cx`, `cy`), with horizontal radius `rx` and vertical
+    /// radius `ry`.
+    pub fn draw_ellipse(&mut self, cx: i32, cy: i32, rx: usize, ry: usize) {
+        for_each_ellipse_point(rx, ry, |x, y| {
+            self.draw_pixel(cx + x, cy + y);
+            self.draw_pixel(cx - x, cy + y);
+            self.draw_pixel(cx + x, cy - y);
+            self.draw_pixel(cx - x, cy - y);
+        });
+    }
+
+    /// Draws a filled ellipse centered at (`cx`, `cy`), covering the same pixels as [Bitmap::draw_ellipse] and
+    /// everything inside it.
+    pub fn fill_ellipse(&mut self, cx: i32, cy: i32, rx: usize, ry: usize) {
+        for_each_ellipse_point(rx, ry, |x, y| {
+            self.fill_span(cy + y, cx - x, cx + x);
+            self.fill_span(cy - y, cx - x, cx + x);
+        });
+    }
+
+    /// Draws part of a ring centered at (`cx`, `cy`), e.g. for gauges.
+    ///
+    /// `radius` is the outer radius and `thickness` how far the ring extends inwards from it. Angles are in
+    /// degrees, clockwise from 12 o'clock; the arc goes clockwise from `start_angle` to `end_angle`.
+    /// A difference of 360 degrees or more draws the whole ring.
+    pub fn draw_arc(&mut self, cx: i32, cy: i32, radius: usize, thickness: usize, start_angle: f32, end_angle: f32) {
+        let sweep = end_angle - start_angle;
+        if sweep <= 0.0 || thickness == 0 { return; }
+        let outer = radius as f32 + 0.5;
+        let inner = (outer - thickness as f32).max(0.0);
+        let start = start_angle.rem_euclid(360.0);
+
+        let r = radius as i32;
+        let clip = self.clip();
+        for dy in (-r).max(clip.y_start as i32 - cy)..=r.min(clip.y_end as i32 - 1 - cy) {
+            for dx in (-r).max(clip.x_start as i32 - cx)..=r.min(clip.x_end as i32 - 1 - cx) {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                if distance >= outer || distance < inner { continue; }
+                let angle = (dx as f32).atan2(-dy as f32).to_degrees();
+                if sweep >= 360.0 || (angle - start).rem_euclid(360.0) <= sweep {
+                    self.draw_pixel(cx + dx, cy + dy);
+                }
+            }
+        }
+    }
+
+    /// Draws the outline of a rectangle with corners rounded by `radius`. The radius is limited to half the
+    /// shortest side.
+    pub fn draw_rounded_rect(&mut self, x: i32, y: i32, width: usize, height: usize, radius: usize) {
+        if width == 0 || height == 0 { return; }
+        let (right, bottom) = (x + width as i32 - 1, y + height as i32 - 1);
+        let r = radius.min((width - 1) / 2).min((height - 1) / 2) as i32;
+        self.fill_span(y, x + r, right - r);
+        self.fill_span(bottom, x + r, right - r);
+        for row in y + r..=bottom - r {
+            self.draw_pixel(x, row);
+            self.draw_pixel(right, row);
+        }
+        for_each_ellipse_point(r as usize, r as usize, |px, py| {
+            self.draw_pixel(x + r - px, y + r - py);
+            self.draw_pixel(right - r + px, y + r - py);
+            self.draw_pixel(x + r - px, bottom - r + py);
+            self.draw_pixel(right - r + px, bottom - r + py);
+        });
+    }
+
+    /// Draws a filled rectangle with corners rounded by `radius`, covering the same pixels as
+    /// [Bitmap::draw_rounded_rect] and everything inside it.
+    pub fn fill_rounded_rect(&mut self, x: i32, y: i32, width: usize, height: usize, radius: usize) {
+        if width == 0 || height == 0 { return; }
+        let (right, bottom) = (x + width as i32 - 1, y + height as i32 - 1);
+        let r = radius.min((width - 1) / 2).min((height - 1) / 2) as i32;
+        let clip = self.clip();
+        for row in (y + r).max(clip.y_start as i32)..=(bottom - r).min(clip.y_end as i32 - 1) {
+            self.fill_span(row, x, right);
+        }
+        for_each_ellipse_point(r as usize, r as usize, |px, py| {
+            self.fill_span(y + r - py, x + r - px, right - r + px);
+            self.fill_span(bottom - r + py, x + r - px, right - r + px);
+        });
+    }
+
+    /// Draws the outline of a closed polygon through `points`.
+    pub fn draw_polygon(&mut self, points: &[(i32, i32)]) {
+        for (i, (x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(i + 1) % points.len()];
+            self.draw_line(*x0, *y0, x1, y1);
+        }
+    }
+
+    /// Draws a filled polygon through `points`, including its outline. Self-intersecting polygons are filled
+    /// using the even-odd rule.
+    pub fn fill_polygon(&mut self, points: &[(i32, i32)]) {
+        if points.is_empty() { return; }
+        let clip = self.clip();
+        let top = points.iter().map(|p| p.1).min().unwrap().max(clip.y_start as i32);
+        let bottom = points.iter().map(|p| p.1).max().unwrap().min(clip.y_end as i32 - 1);
+        let mut crossings = Vec::new();
+        for row in top..=bottom {
+            // Sample through the middle of the row, so vertices never lie exactly on it
+            let scan_y = row as f32 + 0.5;
+            crossings.clear();
+            for (i, (x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(i + 1) % points.len()];
+                let (y0, y1) = (*y0 as f32, y1 as f32);
+                if (y0 <= scan_y) != (y1 <= scan_y) {
+                    let t = (scan_y - y0) / (y1 - y0);
+                    crossings.push(*x0 as f32 + t * (x1 - *x0) as f32);
+                }
+            }
+            crossings.sort_by(|a, b| a.total_cmp(b));
+            for pair in crossings.chunks_exact(2) {
+                self.fill_span(row, pair[0].round() as i32, pair[1].round() as i32);
+            }
+        }
+        self.draw_polygon(points);
+    }
+
+    /// Sets the pixels from `x0` to `x1` (both included) on row `y`.
+    fn fill_span(&mut self, y: i32, x0: i32, x1: i32) {
+        let clip = self.clip();
+        if y < clip.y_start as i32 || y >= clip.y_end as i32 { return; }
+        let start = x0.max(clip.x_start as i32);
+        let end = x1.min(clip.x_end as i32 - 1);
+        if start > end { return; }
+        let row = y as usize * self.width;
+        self.buffer[row + start as usize..=row + end as usize].fill(0xff);
+    }
+}
+
+/// Calls `plot` with every point of the bottom right quarter of an ellipse centered at the origin, using
+/// the midpoint algorithm. The other quarters are mirror images.
+fn for_each_ellipse_point<F: FnMut(i32, i32)>(rx: usize, ry: usize, mut plot: F) {
+    if ry == 0 {
+        for x in 0..=rx as i32 {
+            plot(x, 0);
+        }
+        return;
+    }
+    let (rx2, ry2) = ((rx * rx) as f64, (ry * ry) as f64);
+    let (mut x, mut y) = (0i32, ry as i32);
+    let mut dx = 0.0;
+    let mut dy = 2.0 * rx2 * y as f64;
+
+    // Where the slope is less than 1, step along x
+    let mut d1 = ry2 - rx2 * ry as f64 + 0.25 * rx2;
+    while dx < dy {
+        plot(x, y);
+        x += 1;
+        dx += 2.0 * ry2;
+        if d1 < 0.0 {
+            d1 += dx + ry2;
+        } else {
+            y -= 1;
+            dy -= 2.0 * rx2;
+            d1 += dx - dy + ry2;
+        }
+    }
+
+    // Where it's steeper, step along y
+    let mut d2 = ry2 * (x as f64 + 0.5).powi(2) + rx2 * ((y - 1) as f64).powi(2) - rx2 * ry2;
+    while y >= 0 {
+        plot(x, y);
+        y -= 1;
+        dy -= 2.0 * rx2;
+        if d2 > 0.0 {
+            d2 += rx2 - dy;
+        } else {
+            x += 1;
+            dx += 2.0 * ry2;
+            d2 += dx - dy + rx2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_snapshot(name: &str, bitmap: Bitmap) {
+        crate::snapshot!(name, bitmap.width, bitmap.height).assert_frames_match(&[bitmap]);
+    }
+
+    #[test]
+    fn lines_in_every_direction() {
+        let mut bitmap = Bitmap::new(16, 12);
+        bitmap.draw_line(0, 0, 15, 5);
+        bitmap.draw_line(15, 11, 0, 6);
+        bitmap.draw_line(2, 11, 4, 0);
+        bitmap.draw_line(12, 3, 12, 3);
+        assert_snapshot("primitives_lines", bitmap);
+    }
+
+    /// Draws a shape offset by the given amount in both directions.
+    type Shape = dyn Fn(&mut Bitmap, i32);
+
+    #[test]
+    fn clipping_matches_cropped_drawing() {
+        // Draw each shape on a small bitmap, and with an offset on a large one that fits the whole shape
+        let shapes: [&Shape; 6] = [
+            &|b, o| b.draw_line(o - 10, o - 6, o + 12, o + 5),
+            &|b, o| b.fill_ellipse(o - 3, o + 2, 6, 4),
+            &|b, o| b.draw_circle(o + 1, o - 2, 5),
+            &|b, o| b.draw_arc(o - 2, o + 3, 6, 2, 200.0, 400.0),
+            &|b, o| b.fill_rounded_rect(o - 5, o - 5, 9, 12, 3),
+            &|b, o| b.fill_polygon(&[(o - 6, o - 8), (o + 5, o + 1), (o - 3, o + 6)]),
+        ];
+        for (i, shape) in shapes.iter().enumerate() {
+            let mut small = Bitmap::new(8, 8);
+            shape(&mut small, 0);
+            let mut large = Bitmap::new(48, 48);
+            shape(&mut large, 20);
+            let cropped = (20..28).flat_map(|y| large.buffer[y * 48 + 20..y * 48 + 28].to_vec()).collect::<Vec<u8>>();
+            assert_eq!(small.buffer, cropped, "shape {}", i);
+        }
+    }
+
+    #[test]
+    fn circles_and_ellipses() {
+        let mut bitmap = Bitmap::new(32, 16);
+        bitmap.draw_circle(7, 7, 6);
+        bitmap.fill_circle(7, 7, 2);
+        bitmap.draw_ellipse(23, 4, 7, 3);
+        bitmap.fill_ellipse(23, 12, 5, 2);
+        assert_snapshot("primitives_circles", bitmap);
+    }
+
+    #[test]
+    fn filled_shapes_cover_their_outline() {
+        for (rx, ry) in [(0, 0), (1, 4), (5, 5), (9, 3), (4, 0)] {
+            let (mut outline, mut filled) = (Bitmap::new(24, 12), Bitmap::new(24, 12));
+            outline.draw_ellipse(12, 6, rx, ry);
+            filled.fill_ellipse(12, 6, rx, ry);
+            assert!(outline.buffer.iter().zip(&filled.buffer).all(|(o, f)| *o == 0 || *f > 0), "ellipse {}x{}", rx, ry);
+        }
+        for radius in [0, 2, 5, 20] {
+            let (mut outline, mut filled) = (Bitmap::new(24, 12), Bitmap::new(24, 12));
+            outline.draw_rounded_rect(1, 1, 20, 9, radius);
+            filled.fill_rounded_rect(1, 1, 20, 9, radius);
+            assert!(outline.buffer.iter().zip(&filled.buffer).all(|(o, f)| *o == 0 || *f > 0), "radius {}", radius);
+        }
+    }
+
+    #[test]
+    fn shapes_are_clipped_at_negative_coordinates() {
+        let mut bitmap = Bitmap::new(16, 8);
+        bitmap.fill_circle(-2, -1, 5);
+        bitmap.draw_ellipse(14, 10, 6, 4);
+        bitmap.fill_rounded_rect(-4, 5, 10, 10, 3);
+        bitmap.fill_polygon(&[(10, -5), (20, 3), (12, 4)]);
+        bitmap.draw_arc(-1, 4, 4, 2, 0.0, 360.0);
+        assert_snapshot("primitives_clipped", bitmap);
+    }
+
+    #[test]
+    fn arcs() {
+        let mut bitmap = Bitmap::new(40, 14);
+        // A gauge from 7 to 5 o'clock
+        bitmap.draw_arc(7, 7, 6, 2, -135.0, 135.0);
+        // A quarter, crossing 12 o'clock
+        bitmap.draw_arc(20, 7, 6, 1, -45.0, 45.0);
+        bitmap.draw_arc(33, 7, 6, 3, 0.0, 360.0);
+        assert_snapshot("primitives_arcs", bitmap);
+    }
+
+    #[test]
+    fn rounded_rects() {
+        let mut bitmap = Bitmap::new(32, 12);
+        bitmap.draw_rounded_rect(0, 0, 14, 12, 4);
+        bitmap.fill_rounded_rect(16, 1, 16, 10, 3);
+        assert_snapshot("primitives_rounded_rects", bitmap);
+    }
+
+    #[test]
+    fn polygons() {
+        let mut bitmap = Bitmap::new(32, 16);
+        bitmap.fill_polygon(&[(1, 14), (7, 1), (13, 14)]);
+        // A star, whose center is left empty by the even-odd rule
+        bitmap.fill_polygon(&[(23, 0), (27, 14), (16, 5), (30, 5), (19, 14)]);
+        assert_snapshot("primitives_polygons", bitmap);
+    }
+}
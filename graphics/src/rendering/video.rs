@@ -21,7 +21,7 @@ impl Video {
             gray = image::imageops::contrast(&gray, 40.0);
             Bitmap::from_raw_bytes(gray.as_bytes(), gray.width() as usize)
         }).collect::<Vec<Bitmap>>();
-        assert!(bitmaps.len() > 0);
+        assert!(!bitmaps.is_empty());
         Ok(Self {
             frames: bitmaps,
             pos: 0,
@@ -40,7 +40,7 @@ impl Video {
 
     pub fn advance(&mut self) {
         self.pos += 1;
-        self.pos = self.pos % self.frames.len();
+        self.pos %= self.frames.len();
     }
     pub fn reset(&mut self) {
         self.pos = 0;
@@ -0,0 +1,253 @@
+//! Snapshot tests for [Drawable]s.
+//!
+//! A [Snapshot] draws a drawable for a scripted sequence of `elapsed` durations and compares every frame
+//! against a golden file. Run the tests with `UPDATE_SNAPSHOTS=1` to (re)write the golden files after an
+//! intentional change, and review the diff before committing them.
+//!
+//! ```ignore
+//! graphics::snapshot!("clock", 128, 64)
+//!     .with_frames(&[Duration::ZERO, Duration::from_millis(500)])
+//!     .assert_matches(&mut clock_screen);
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::components::{Bounds, Drawable};
+use crate::rendering::Bitmap;
+
+/// Set this environment variable to overwrite golden files instead of comparing against them.
+pub const UPDATE_ENV_VAR: &str = "UPDATE_SNAPSHOTS";
+
+/// Characters used for pixel values in ascii snapshots, from darkest to brightest.
+const ASCII_RAMP: [char; 5] = [' ', '.', '+', '*', '#'];
+
+/// Creates a [Snapshot] stored in the `snapshots` directory of the calling crate.
+#[macro_export]
+macro_rules! snapshot {
+    ($name:expr, $width:expr, $height:expr) => {
+        $crate::testing::Snapshot::new(concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots"), $name, $width, $height)
+    };
+}
+
+/// How a snapshot is stored on disk.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SnapshotFormat {
+    /// A text file with one character per pixel. Easy to review, but only keeps 5 levels of brightness.
+    Ascii,
+    /// A grayscale png with the frames stacked vertically. Exact.
+    Png,
+}
+
+pub struct Snapshot {
+    directory: PathBuf,
+    name: String,
+    width: usize,
+    height: usize,
+    frames: Vec<Duration>,
+    format: SnapshotFormat,
+}
+
+impl Snapshot {
+    /// Creates a snapshot of a single frame drawn with an `elapsed` of zero, stored as ascii.
+    ///
+    /// `name` is the file name of the golden file, without extension. See also [snapshot!].
+    pub fn new<P: Into<PathBuf>>(directory: P, name: &str, width: usize, height: usize) -> Self {
+        Self {
+            directory: directory.into(),
+            name: name.to_string(),
+            width,
+            height,
+            frames: vec![Duration::ZERO],
+            format: SnapshotFormat::Ascii,
+        }
+    }
+
+    /// Draws one frame for each duration, passing it as `elapsed`.
+    pub fn with_frames(mut self, frames: &[Duration]) -> Self {
+        self.frames = frames.to_vec();
+        self
+    }
+
+    /// Draws `count` frames, `interval` apart. The first frame is drawn with an `elapsed` of zero.
+    pub fn with_interval(mut self, interval: Duration, count: usize) -> Self {
+        self.frames = (0..count).map(|i| if i == 0 { Duration::ZERO } else { interval }).collect();
+        self
+    }
+
+    pub fn with_format(mut self, format: SnapshotFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Draws `drawable` and panics if the result differs from the golden file.
+    pub fn assert_matches(&self, drawable: &mut dyn Drawable) {
+        let frames = render_frames(drawable, self.width, self.height, &self.frames);
+        self.assert_frames_match(&frames);
+    }
+
+    /// Like [Snapshot::assert_matches], but for frames that were already rendered.
+    pub fn assert_frames_match(&self, frames: &[Bitmap]) {
+        let path = self.path("");
+        if std::env::var_os(UPDATE_ENV_VAR).is_some() {
+            std::fs::create_dir_all(&self.directory).expect("Failed to create snapshot directory");
+            self.write(&path, frames);
+            return;
+        }
+        if !path.exists() {
+            panic!("Snapshot {:?} doesn't exist. Run with {}=1 to create it.", path, UPDATE_ENV_VAR);
+        }
+        if let Err(message) = self.compare(&path, frames) {
+            let actual_path = self.path(".actual");
+            self.write(&actual_path, frames);
+            panic!(
+                "Snapshot {:?} doesn't match: {}\nThe new output was written to {:?}. Run with {}=1 to accept it.",
+                path, message, actual_path, UPDATE_ENV_VAR,
+            );
+        }
+    }
+
+    fn path(&self, suffix: &str) -> PathBuf {
+        let extension = match self.format {
+            SnapshotFormat::Ascii => "txt",
+            SnapshotFormat::Png => "png",
+        };
+        self.directory.join(format!("{}{}.{}", self.name, suffix, extension))
+    }
+
+    fn write(&self, path: &Path, frames: &[Bitmap]) {
+        match self.format {
+            SnapshotFormat::Ascii => std::fs::write(path, to_ascii(frames, &self.frames)),
+            SnapshotFormat::Png => stack_frames(frames).save(path).map_err(std::io::Error::other),
+        }.expect("Failed to write snapshot");
+    }
+
+    fn compare(&self, path: &Path, frames: &[Bitmap]) -> Result<(), String> {
+        match self.format {
+            SnapshotFormat::Ascii => {
+                // Normalize line endings, in case git converted them
+                let expected = std::fs::read_to_string(path).expect("Failed to read snapshot").replace("\r\n", "\n");
+                let actual = to_ascii(frames, &self.frames);
+                let expected_lines = expected.lines().collect::<Vec<&str>>();
+                let actual_lines = actual.lines().collect::<Vec<&str>>();
+                if expected_lines.len() != actual_lines.len() {
+                    return Err(format!("expected {} lines, got {}", expected_lines.len(), actual_lines.len()));
+                }
+                match expected_lines.iter().zip(&actual_lines).position(|(e, a)| e != a) {
+                    Some(line) => Err(format!(
+                        "first difference on line {}\nexpected: {}\n  actual: {}",
+                        line + 1, expected_lines[line], actual_lines[line],
+                    )),
+                    None => Ok(()),
+                }
+            },
+            SnapshotFormat::Png => {
+                let expected = image::open(path).expect("Failed to read snapshot").into_luma8();
+                let actual = stack_frames(frames);
+                if expected.dimensions() != actual.dimensions() {
+                    return Err(format!("expected size {:?}, got {:?}", expected.dimensions(), actual.dimensions()));
+                }
+                let differing = expected.pixels().zip(actual.pixels()).filter(|(e, a)| e != a).count();
+                match differing {
+                    0 => Ok(()),
+                    n => Err(format!("{} pixels differ", n)),
+                }
+            },
+        }
+    }
+}
+
+/// Draws `drawable` once for each duration in `frames` (passed as `elapsed`) on a cleared canvas, returning
+/// every frame.
+pub fn render_frames(drawable: &mut dyn Drawable, width: usize, height: usize, frames: &[Duration]) -> Vec<Bitmap> {
+    let mut canvas = Bitmap::new(width, height);
+    let bounds = Bounds::cover_bitmap(&canvas);
+    frames.iter().map(|elapsed| {
+        canvas.clear();
+        drawable.draw(&mut canvas, bounds, elapsed);
+        canvas.clone()
+    }).collect()
+}
+
+fn to_ascii(frames: &[Bitmap], elapsed: &[Duration]) -> String {
+    let mut text = String::new();
+    let mut time = Duration::ZERO;
+    for (i, (frame, elapsed)) in frames.iter().zip(elapsed).enumerate() {
+        time += *elapsed;
+        text += &format!("frame {} at {}ms\n", i, time.as_millis());
+        for row in frame.buffer.chunks(frame.width) {
+            text.push('|');
+            text.extend(row.iter().map(|pixel| ASCII_RAMP[(*pixel as usize * ASCII_RAMP.len()) / 256]));
+            text += "|\n";
+        }
+    }
+    text
+}
+
+fn stack_frames(frames: &[Bitmap]) -> image::GrayImage {
+    let width = frames.first().map(|f| f.width).unwrap_or(0);
+    let buffer = frames.iter().flat_map(|f| f.buffer.iter().copied()).collect::<Vec<u8>>();
+    let height = buffer.len().checked_div(width).unwrap_or(0);
+    image::GrayImage::from_raw(width as u32, height as u32, buffer).expect("Frames have different sizes")
+}
+
+/// A pixel font for tests in this crate, borrowed from the application's resources.
+#[cfg(test)]
+pub(crate) fn test_font() -> &'static fontdue::Font {
+    static FONT: std::sync::OnceLock<fontdue::Font> = std::sync::OnceLock::new();
+    FONT.get_or_init(|| {
+        let bytes = include_bytes!("../../oculed/resources/fonts/pixeloid/PixeloidMono.ttf");
+        fontdue::Font::from_bytes(&bytes[..], fontdue::FontSettings::default()).expect("Failed to load font")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fills one more column every frame.
+    struct Wipe {
+        columns: usize,
+    }
+
+    impl Drawable for Wipe {
+        fn draw(&mut self, canvas: &mut Bitmap, bounds: Bounds, _elapsed: &Duration) {
+            self.columns += 1;
+            for x in 0..self.columns.min(bounds.size.width as usize) {
+                for y in 0..canvas.height {
+                    canvas.buffer[y * canvas.width + x] = (x * 0x40).min(0xff) as u8;
+                }
+            }
+        }
+    }
+
+    fn temp_snapshot(name: &str, format: SnapshotFormat) -> Snapshot {
+        let directory = std::env::temp_dir().join(format!("graphics-snapshots-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        Snapshot::new(directory, name, 5, 2)
+            .with_interval(Duration::from_millis(100), 3)
+            .with_format(format)
+    }
+
+    #[test]
+    fn ascii_shows_every_frame() {
+        let frames = render_frames(&mut Wipe { columns: 0 }, 5, 2, &[Duration::ZERO, Duration::from_millis(100)]);
+        assert_eq!(to_ascii(&frames, &[Duration::ZERO, Duration::from_millis(100)]),
+            "frame 0 at 0ms\n|     |\n|     |\nframe 1 at 100ms\n| .   |\n| .   |\n");
+    }
+
+    #[test]
+    fn matching_snapshots_pass_and_others_fail() {
+        for (name, format) in [("wipe-ascii", SnapshotFormat::Ascii), ("wipe-png", SnapshotFormat::Png)] {
+            let snapshot = temp_snapshot(name, format);
+            let frames = render_frames(&mut Wipe { columns: 0 }, 5, 2, &snapshot.frames);
+            snapshot.write(&snapshot.path(""), &frames);
+
+            assert_eq!(snapshot.compare(&snapshot.path(""), &frames), Ok(()));
+            let mut changed = frames.clone();
+            changed[2].buffer[4] = 0xff;
+            assert!(snapshot.compare(&snapshot.path(""), &changed).is_err());
+            std::fs::remove_file(snapshot.path("")).unwrap();
+        }
+    }
+}
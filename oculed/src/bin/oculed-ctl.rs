@@ -0,0 +1,77 @@
+//! Sends a command to a running OcuLED through its control socket and prints the response.
+//!
+//! Usage: `oculed-ctl [--socket <path>] [--display <name>] <command> [arguments]`
+//!
+//! Commands: `next-screen`, `prev-screen`, `move-screens <offset>`, `show-screen <index|name>`,
+//! `screensaver-on`, `screensaver-off`, `notify <text> [duration_ms]`, `state`, `quit`.
+
+#[cfg(unix)]
+fn main() -> anyhow::Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+
+    let mut socket = None;
+    let mut display = None;
+    let mut args = std::env::args().skip(1);
+    let command = loop {
+        match args.next().as_deref() {
+            Some("--socket") => socket = Some(std::path::PathBuf::from(args.next().ok_or_else(|| anyhow::anyhow!("Missing value after --socket"))?)),
+            Some("--display") => display = Some(args.next().ok_or_else(|| anyhow::anyhow!("Missing value after --display"))?),
+            Some(command) => break command.to_string(),
+            None => anyhow::bail!("Usage: oculed-ctl [--socket <path>] [--display <name>] <command> [arguments]"),
+        }
+    };
+    let arguments = args.collect::<Vec<_>>();
+
+    let mut request = serde_json::Map::new();
+    request.insert("command".to_string(), command.clone().into());
+    if let Some(display) = display {
+        request.insert("display".to_string(), display.into());
+    }
+    match (command.as_str(), arguments.as_slice()) {
+        ("move-screens", [offset]) => {
+            request.insert("offset".to_string(), offset.parse::<i32>()?.into());
+        },
+        ("show-screen", [screen]) => {
+            // Numbers are indices, anything else is a name
+            let screen = screen.parse::<usize>().map(Into::into).unwrap_or_else(|_| screen.clone().into());
+            request.insert("screen".to_string(), screen);
+        },
+        ("notify", [text, rest @ ..]) if rest.len() <= 1 => {
+            request.insert("text".to_string(), text.clone().into());
+            if let [duration_ms] = rest {
+                request.insert("duration_ms".to_string(), duration_ms.parse::<u64>()?.into());
+            }
+        },
+        ("move-screens" | "show-screen" | "notify", _) => anyhow::bail!("Wrong arguments for {}", command),
+        (_, []) => {},
+        (_, _) => anyhow::bail!("{} takes no arguments", command),
+    }
+
+    let path = socket.unwrap_or_else(default_socket_path);
+    let mut stream = std::os::unix::net::UnixStream::connect(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to connect to {}, is OcuLED running? {}", path.display(), e))?;
+    stream.write_all(format!("{}\n", serde_json::Value::Object(request)).as_bytes())?;
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    print!("{}", response);
+
+    let response: serde_json::Value = serde_json::from_str(&response)?;
+    if response["ok"] != true {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Must match `control::default_socket_path` in OcuLED.
+#[cfg(unix)]
+fn default_socket_path() -> std::path::PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => std::path::PathBuf::from(dir).join("oculed.sock"),
+        None => std::env::temp_dir().join(format!("oculed-{}.sock", unsafe { libc::getuid() })),
+    }
+}
+
+#[cfg(not(unix))]
+fn main() -> anyhow::Result<()> {
+    anyhow::bail!("The control socket is only available on unix")
+}
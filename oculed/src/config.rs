@@ -0,0 +1,870 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::output::{Compression, Protocol, TerminalStyle};
+use crate::rendering;
+use crate::input::Press;
+use crate::UserInput;
+
+pub const DEFAULT_BAUD_RATE: u32 = 115200;
+
+/// Runtime configuration, read from a TOML file. See `config.example.toml` for all keys.
+///
+/// Every key is optional; missing keys get the values shown in the example file.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub display: DisplayConfig,
+    pub output: OutputConfig,
+    pub input: InputConfig,
+    /// The screens to cycle between, in order
+    pub screens: Vec<ScreenConfig>,
+    pub overlays: OverlaysConfig,
+    /// More displays to drive besides the main one
+    pub displays: Vec<DisplaySetup>,
+    pub control: ControlConfig,
+    pub http: HttpConfig,
+}
+
+/// The HTTP API and live preview. Only available when built with the `http` feature.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    /// `host:port` to listen on
+    pub address: String,
+}
+
+/// The control API, see [crate::control]. Only available on unix.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    /// Where to create the socket, instead of the default
+    pub socket: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// Shown in messages about the display
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// One display, with its own output, screens and hotkeys. See [Config::all_displays].
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DisplaySetup {
+    pub name: String,
+    #[serde(default = "default_width")]
+    pub width: usize,
+    #[serde(default = "default_height")]
+    pub height: usize,
+    pub output: OutputConfig,
+    #[serde(default = "default_screens")]
+    pub screens: Vec<ScreenConfig>,
+    /// Hotkeys controlling this display
+    #[serde(default)]
+    pub bindings: Vec<BindingConfig>,
+}
+
+/// Where to send rendered frames.
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum OutputConfig {
+    /// A display connected over wifi
+    Udp {
+        /// `host:port` of the display. When not set, the display is found with [crate::discovery].
+        #[serde(default)]
+        address: Option<String>,
+        /// The name of the display to use, when discovering it
+        #[serde(default)]
+        device: Option<String>,
+        /// Where to send discovery probes
+        #[serde(default = "default_discovery_address")]
+        discovery_address: String,
+        #[serde(default)]
+        dithering: DitheringConfig,
+        #[serde(default)]
+        layout: LayoutConfig,
+        /// Frames between full updates, see [crate::output::UdpOutput]
+        #[serde(default = "default_keyframe_interval")]
+        keyframe_interval: u32,
+        /// The protocol version to use. Defaults to what a discovered display reports, or version 1.
+        #[serde(default)]
+        protocol: Option<Protocol>,
+        #[serde(default)]
+        compression: Compression,
+    },
+    /// A display attached to a serial port
+    Serial {
+        port: String,
+        #[serde(default = "default_baud_rate")]
+        baud_rate: u32,
+        #[serde(default)]
+        dithering: DitheringConfig,
+        #[serde(default)]
+        layout: LayoutConfig,
+    },
+    /// Draw to the terminal, for developing without a display
+    Terminal {
+        #[serde(default = "default_terminal_style")]
+        style: TerminalStyle,
+        #[serde(default)]
+        dithering: DitheringConfig,
+    },
+}
+
+impl OutputConfig {
+    /// How frames are converted to black and white before they are sent.
+    pub fn dithering(&self) -> DitheringConfig {
+        match self {
+            OutputConfig::Udp { dithering, .. } | OutputConfig::Serial { dithering, .. } | OutputConfig::Terminal { dithering, .. } => *dithering,
+        }
+    }
+}
+
+/// How pixels are packed into bytes for the display, see [rendering::PixelLayout].
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum LayoutConfig {
+    #[default]
+    RowMajorMsbFirst,
+    RowMajorLsbFirst,
+    Ssd1306,
+    Sh1106 {
+        #[serde(default = "default_column_offset")]
+        column_offset: usize,
+    },
+}
+
+impl From<LayoutConfig> for rendering::PixelLayout {
+    fn from(config: LayoutConfig) -> Self {
+        match config {
+            LayoutConfig::RowMajorMsbFirst => rendering::PixelLayout::RowMajorMsbFirst,
+            LayoutConfig::RowMajorLsbFirst => rendering::PixelLayout::RowMajorLsbFirst,
+            LayoutConfig::Ssd1306 => rendering::PixelLayout::Ssd1306Pages,
+            LayoutConfig::Sh1106 { column_offset } => rendering::PixelLayout::Sh1106Pages { column_offset },
+        }
+    }
+}
+
+/// How grayscale pixels are reduced to on/off, see [rendering::Dithering].
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum DitheringConfig {
+    Threshold {
+        /// Pixels brighter than this (0-255) are on
+        #[serde(default = "default_threshold")]
+        level: u8,
+    },
+    Bayer {
+        /// The size of the pattern: 2, 4 or 8
+        #[serde(default = "default_bayer_size")]
+        size: usize,
+    },
+    FloydSteinberg,
+    Atkinson,
+    Temporal,
+}
+
+impl Default for DitheringConfig {
+    fn default() -> Self {
+        DitheringConfig::Threshold { level: default_threshold() }
+    }
+}
+
+impl DitheringConfig {
+    fn validate(&self, key: &str) -> Result<(), ConfigError> {
+        match self {
+            DitheringConfig::Bayer { size } if ![2, 4, 8].contains(size) => Err(invalid(&format!("{}.size", key), "must be 2, 4 or 8")),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<DitheringConfig> for rendering::Dithering {
+    fn from(config: DitheringConfig) -> Self {
+        match config {
+            DitheringConfig::Threshold { level } => rendering::Dithering::Threshold(level),
+            DitheringConfig::Bayer { size } => rendering::Dithering::Bayer(size),
+            DitheringConfig::FloydSteinberg => rendering::Dithering::FloydSteinberg,
+            DitheringConfig::Atkinson => rendering::Dithering::Atkinson,
+            DitheringConfig::Temporal => rendering::Dithering::Temporal,
+        }
+    }
+}
+
+impl ScreenConfig {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(match self.kind {
+            ScreenKind::Clock { .. } => "clock",
+            ScreenKind::Media {} => "media",
+            ScreenKind::PerformanceMemory {} => "performance-memory",
+            ScreenKind::PerformanceTemperature {} => "performance-temperature",
+            ScreenKind::PerformanceHistory {} => "performance-history",
+            ScreenKind::Stickfight { .. } => "stickfight",
+        })
+    }
+}
+
+impl From<ScreenKind> for ScreenConfig {
+    fn from(kind: ScreenKind) -> Self {
+        ScreenConfig { kind, name: None, dithering: None }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    /// The UDP port to receive input from the display on
+    pub network_port: u16,
+    /// Key combinations and their actions. They are global hotkeys on windows, and apply to [InputConfig::devices]
+    /// on linux.
+    pub bindings: Vec<BindingConfig>,
+    /// Actions for the keys displays send over the network
+    pub keys: Vec<KeyConfig>,
+    /// Input devices to read key presses from, linux only
+    pub devices: Vec<DeviceConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BindingConfig {
+    /// A key combination such as `ctrl+alt+shift+O`
+    pub key: String,
+    pub action: UserInput,
+}
+
+/// A keyboard-like device under `/dev/input`, see [crate::input::EvdevInput].
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfig {
+    pub path: std::path::PathBuf,
+    /// Keep the key presses from reaching other programs
+    #[serde(default)]
+    pub grab: bool,
+}
+
+/// Maps a key sent by a display to an action, see [crate::input::NetworkInput].
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct KeyConfig {
+    pub code: u16,
+    #[serde(default)]
+    pub press: Press,
+    pub action: UserInput,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct ScreenConfig {
+    #[serde(flatten)]
+    pub kind: ScreenKind,
+    /// Used to jump to the screen. Defaults to the screen's type.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Dither this screen on its own, instead of relying on the output's dithering
+    #[serde(default)]
+    pub dithering: Option<DitheringConfig>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum ScreenKind {
+    Clock {
+        /// Time format, see [chrono::format::strftime]
+        #[serde(default = "default_clock_format")]
+        format: String,
+        /// Date format, see [chrono::format::strftime]. No date is shown if this is missing.
+        date_format: Option<String>,
+    },
+    // Empty braces instead of unit variants, which serde would accept with any extra keys
+    Media {},
+    PerformanceMemory {},
+    PerformanceTemperature {},
+    /// Graphs of the last minute of cpu and gpu usage
+    PerformanceHistory {},
+    Stickfight {
+        #[serde(default = "default_stickfight_fps")]
+        fps: f32,
+    },
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverlaysConfig {
+    pub media: MediaOverlayConfig,
+    pub screensaver: ScreensaverConfig,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct MediaOverlayConfig {
+    pub enabled: bool,
+    /// How long to show the play/pause icon, in milliseconds
+    pub play_pause_ms: u64,
+    /// How long to show the track info when the track changes, in milliseconds
+    pub track_info_ms: u64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScreensaverConfig {
+    pub fps: f32,
+    /// The screensaver animations are grayscale, so they look best dithered on their own
+    pub dithering: Option<DitheringConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            display: Default::default(),
+            output: Default::default(),
+            input: Default::default(),
+            screens: default_screens(),
+            overlays: Default::default(),
+            displays: Vec::new(),
+            control: Default::default(),
+            http: Default::default(),
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self { enabled: false, address: "127.0.0.1:8080".to_string() }
+    }
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self { enabled: true, socket: None }
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self { name: "main".to_string(), width: default_width(), height: default_height() }
+    }
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig::Udp {
+            address: None,
+            device: None,
+            discovery_address: default_discovery_address(),
+            dithering: Default::default(),
+            layout: Default::default(),
+            keyframe_interval: default_keyframe_interval(),
+            protocol: None,
+            compression: Default::default(),
+        }
+    }
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        let binding = |key: &str, action| BindingConfig { key: key.to_string(), action };
+        Self {
+            network_port: 15666,
+            bindings: vec![
+                binding("ctrl+alt+shift+O", UserInput::NextScreen),
+                binding("ctrl+alt+shift+I", UserInput::PrevScreen),
+                binding("ctrl+alt+shift+P", UserInput::Quit),
+            ],
+            keys: Vec::new(),
+            devices: Vec::new(),
+        }
+    }
+}
+
+impl Default for MediaOverlayConfig {
+    fn default() -> Self {
+        Self { enabled: true, play_pause_ms: 1000, track_info_ms: 3000 }
+    }
+}
+
+impl Default for ScreensaverConfig {
+    fn default() -> Self {
+        Self { fps: 20.0, dithering: None }
+    }
+}
+
+fn default_width() -> usize { 128 }
+fn default_height() -> usize { 64 }
+fn default_screens() -> Vec<ScreenConfig> {
+    vec![
+        ScreenKind::Clock { format: default_clock_format(), date_format: None }.into(),
+        ScreenKind::Media {}.into(),
+        ScreenKind::PerformanceMemory {}.into(),
+        ScreenKind::PerformanceTemperature {}.into(),
+        ScreenKind::Stickfight { fps: default_stickfight_fps() }.into(),
+    ]
+}
+fn default_baud_rate() -> u32 { DEFAULT_BAUD_RATE }
+fn default_terminal_style() -> TerminalStyle { TerminalStyle::HalfBlocks }
+fn default_clock_format() -> String { "%H:%M".to_string() }
+fn default_stickfight_fps() -> f32 { 15.0 }
+fn default_threshold() -> u8 { 0x40 }
+fn default_bayer_size() -> usize { 4 }
+fn default_column_offset() -> usize { 2 }
+fn default_keyframe_interval() -> u32 { 40 }
+fn default_discovery_address() -> String { crate::discovery::DEFAULT_BROADCAST_ADDRESS.to_string() }
+
+/// A key combination parsed from [BindingConfig::key].
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct KeyCombination {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub super_key: bool,
+    /// An uppercase letter or a digit
+    pub key: char,
+}
+
+impl KeyCombination {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut combination = KeyCombination::default();
+        let mut parts = text.split('+').map(str::trim).collect::<Vec<&str>>();
+        let key = parts.pop().unwrap_or_default();
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => combination.ctrl = true,
+                "alt" => combination.alt = true,
+                "shift" => combination.shift = true,
+                "super" | "win" => combination.super_key = true,
+                _ => return Err(format!("unknown modifier '{}'", modifier)),
+            }
+        }
+        let mut chars = key.chars();
+        combination.key = match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphanumeric() => c.to_ascii_uppercase(),
+            _ => return Err(format!("unsupported key '{}', expected a letter or a digit", key)),
+        };
+        Ok(combination)
+    }
+}
+
+/// A problem with the configuration file.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: PathBuf, error: std::io::Error },
+    /// The file isn't valid TOML, or doesn't match the expected structure
+    Parse { path: PathBuf, error: toml::de::Error },
+    /// A value is not allowed. `key` is the path to it, e.g. `screens[1].fps`.
+    Invalid { key: String, message: String },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io { path, error } => write!(f, "Failed to read config file {:?}: {}", path, error),
+            ConfigError::Parse { path, error } => write!(f, "Invalid config file {:?}: {}", path, error),
+            ConfigError::Invalid { key, message } => write!(f, "Invalid config value for `{}`: {}", key, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(key: &str, message: &str) -> ConfigError {
+    ConfigError::Invalid { key: key.to_string(), message: message.to_string() }
+}
+
+impl Config {
+    /// Loads the config file at `path`, or from [default_path] if `path` is [None].
+    ///
+    /// A missing file at the default path gives the default configuration.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents).map_err(|e| match e {
+                ConfigError::Parse { error, .. } => ConfigError::Parse { path, error },
+                e => e,
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => Ok(Config::default()),
+            Err(error) => Err(ConfigError::Io { path, error }),
+        }
+    }
+
+    /// Parses and validates a config from the contents of a TOML file.
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        let config: Config = toml::from_str(contents)
+            .map_err(|error| ConfigError::Parse { path: PathBuf::new(), error })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Every display to drive: the main one, made up of `[display]`, `[output]`, `screens` and `input.bindings`,
+    /// followed by the ones in `[[displays]]`.
+    pub fn all_displays(&self) -> Vec<DisplaySetup> {
+        let main = DisplaySetup {
+            name: self.display.name.clone(),
+            width: self.display.width,
+            height: self.display.height,
+            output: self.output.clone(),
+            screens: self.screens.clone(),
+            bindings: self.input.bindings.clone(),
+        };
+        std::iter::once(main).chain(self.displays.iter().cloned()).collect()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let displays = self.all_displays();
+        for (i, display) in displays.iter().enumerate() {
+            let keys = DisplayKeys::of(i);
+            if displays[..i].iter().any(|other| other.name == display.name) {
+                return Err(invalid(&format!("{}.name", keys.size), &format!("another display is called '{}'", display.name)));
+            }
+            let is_terminal = |display: &DisplaySetup| matches!(display.output, OutputConfig::Terminal { .. });
+            if is_terminal(display) && displays[..i].iter().any(is_terminal) {
+                return Err(invalid(&keys.output, "only one display can use the terminal"));
+            }
+            display.validate(&keys)?;
+        }
+
+        for (i, key) in self.input.keys.iter().enumerate() {
+            if self.input.keys[..i].iter().any(|other| (other.code, other.press) == (key.code, key.press)) {
+                return Err(invalid(&format!("input.keys[{}]", i), &format!("key {} already has an action for a {:?} press", key.code, key.press)));
+            }
+        }
+
+        for (i, device) in self.input.devices.iter().enumerate() {
+            if device.path.as_os_str().is_empty() {
+                return Err(invalid(&format!("input.devices[{}].path", i), "must not be empty"));
+            }
+        }
+
+        if !is_host_and_port(&self.http.address) {
+            return Err(invalid("http.address", "must be a host and a port, e.g. \"127.0.0.1:8080\""));
+        }
+
+        if self.overlays.screensaver.fps <= 0.0 {
+            return Err(invalid("overlays.screensaver.fps", "must be larger than 0"));
+        }
+        if let Some(dithering) = &self.overlays.screensaver.dithering {
+            dithering.validate("overlays.screensaver.dithering")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the values of a display are in the config file, for error messages.
+struct DisplayKeys {
+    size: String,
+    output: String,
+    screens: String,
+    bindings: String,
+}
+
+impl DisplayKeys {
+    /// The keys of the display at `index` in [Config::all_displays].
+    fn of(index: usize) -> Self {
+        if index == 0 {
+            DisplayKeys {
+                size: "display".to_string(),
+                output: "output".to_string(),
+                screens: "screens".to_string(),
+                bindings: "input.bindings".to_string(),
+            }
+        } else {
+            let prefix = format!("displays[{}]", index - 1);
+            DisplayKeys {
+                size: prefix.clone(),
+                output: format!("{}.output", prefix),
+                screens: format!("{}.screens", prefix),
+                bindings: format!("{}.bindings", prefix),
+            }
+        }
+    }
+}
+
+impl DisplaySetup {
+    /// Switches to the resolution a discovered display reported, after checking that it works with the rest
+    /// of the setup like a configured one. `index` is the display's position in [Config::all_displays].
+    pub fn use_reported_size(&mut self, index: usize, width: usize, height: usize) -> Result<(), ConfigError> {
+        let configured = (self.width, self.height);
+        (self.width, self.height) = (width, height);
+        let result = self.validate_size(&DisplayKeys::of(index));
+        if result.is_err() {
+            (self.width, self.height) = configured;
+        }
+        result
+    }
+
+    fn validate_size(&self, keys: &DisplayKeys) -> Result<(), ConfigError> {
+        if self.width == 0 {
+            return Err(invalid(&format!("{}.width", keys.size), "must be larger than 0"));
+        }
+        if self.height == 0 {
+            return Err(invalid(&format!("{}.height", keys.size), "must be larger than 0"));
+        }
+        if !(self.width * self.height).is_multiple_of(8) {
+            return Err(invalid(&keys.size, "width * height must be a multiple of 8"));
+        }
+        if let OutputConfig::Udp { layout: LayoutConfig::Sh1106 { column_offset }, .. } | OutputConfig::Serial { layout: LayoutConfig::Sh1106 { column_offset }, .. } = &self.output {
+            if column_offset + self.width > rendering::SH1106_COLUMNS {
+                return Err(invalid(&format!("{}.layout.column_offset", keys.output), &format!("the display doesn't fit in the SH1106's {} columns", rendering::SH1106_COLUMNS)));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self, keys: &DisplayKeys) -> Result<(), ConfigError> {
+        self.validate_size(keys)?;
+
+        match &self.output {
+            OutputConfig::Udp { address, device, discovery_address, keyframe_interval, protocol, compression, .. } => {
+                if *keyframe_interval == 0 {
+                    return Err(invalid(&format!("{}.keyframe_interval", keys.output), "must be at least 1"));
+                }
+                if *protocol == Some(Protocol::V1) && *compression != Compression::None {
+                    return Err(invalid(&format!("{}.compression", keys.output), "requires protocol 2 or later"));
+                }
+                if address.is_some() && device.is_some() {
+                    return Err(invalid(&format!("{}.device", keys.output), &format!("can't be combined with {}.address", keys.output)));
+                }
+                if !address.as_deref().is_none_or(is_host_and_port) {
+                    return Err(invalid(&format!("{}.address", keys.output), "must be a host and a port, e.g. \"192.168.1.6:4435\""));
+                }
+                if !is_host_and_port(discovery_address) {
+                    return Err(invalid(&format!("{}.discovery_address", keys.output), "must be a host and a port, e.g. \"255.255.255.255:4435\""));
+                }
+            },
+            OutputConfig::Serial { port, baud_rate, .. } => {
+                if port.is_empty() {
+                    return Err(invalid(&format!("{}.port", keys.output), "must not be empty"));
+                }
+                if *baud_rate == 0 {
+                    return Err(invalid(&format!("{}.baud_rate", keys.output), "must be larger than 0"));
+                }
+            },
+            OutputConfig::Terminal { .. } => {},
+        }
+        self.output.dithering().validate(&format!("{}.dithering", keys.output))?;
+
+        for (i, binding) in self.bindings.iter().enumerate() {
+            KeyCombination::parse(&binding.key).map_err(|e| invalid(&format!("{}[{}].key", keys.bindings, i), &e))?;
+        }
+
+        if self.screens.is_empty() {
+            return Err(invalid(&keys.screens, "at least one screen is required"));
+        }
+        for (i, screen) in self.screens.iter().enumerate() {
+            if screen.name.as_ref().is_some_and(|name| name.is_empty()) {
+                return Err(invalid(&format!("{}[{}].name", keys.screens, i), "must not be empty"));
+            }
+            if let Some(dithering) = &screen.dithering {
+                dithering.validate(&format!("{}[{}].dithering", keys.screens, i))?;
+            }
+            match &screen.kind {
+                ScreenKind::Clock { format, date_format } => {
+                    validate_time_format(format).map_err(|e| invalid(&format!("{}[{}].format", keys.screens, i), &e))?;
+                    if let Some(date_format) = date_format {
+                        validate_time_format(date_format).map_err(|e| invalid(&format!("{}[{}].date_format", keys.screens, i), &e))?;
+                    }
+                },
+                ScreenKind::Stickfight { fps } => {
+                    if *fps <= 0.0 {
+                        return Err(invalid(&format!("{}[{}].fps", keys.screens, i), "must be larger than 0"));
+                    }
+                },
+                ScreenKind::Media {} | ScreenKind::PerformanceMemory {} | ScreenKind::PerformanceTemperature {} | ScreenKind::PerformanceHistory {} => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_host_and_port(address: &str) -> bool {
+    address.rsplit_once(':')
+        .map(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+        .unwrap_or(false)
+}
+
+fn validate_time_format(format: &str) -> Result<(), String> {
+    let has_error = chrono::format::StrftimeItems::new(format).any(|item| item == chrono::format::Item::Error);
+    if has_error {
+        return Err(format!("'{}' is not a valid time format", format));
+    }
+    Ok(())
+}
+
+/// The config file used when none is given on the command line.
+///
+/// `%APPDATA%\OcuLED\config.toml` on windows, `$XDG_CONFIG_HOME/oculed/config.toml` (or `~/.config/...`) elsewhere.
+pub fn default_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("OcuLED").join("config.toml"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|dir| dir.join("oculed").join("config.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_key(contents: &str) -> String {
+        match Config::parse(contents) {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        let config = Config::parse(include_str!("../config.example.toml")).unwrap();
+        let defaults = Config::default();
+        assert_eq!(config.screens, defaults.screens);
+        assert_eq!(config.output, defaults.output);
+        assert_eq!(config.input.bindings.len(), defaults.input.bindings.len());
+    }
+
+    #[test]
+    fn missing_keys_use_defaults() {
+        let config = Config::parse("[display]\nheight = 32\n[output]\ntype = \"serial\"\nport = \"COM3\"\n").unwrap();
+        assert_eq!(config.display.width, 128);
+        assert_eq!(config.display.height, 32);
+        assert_eq!(config.output, OutputConfig::Serial {
+            port: "COM3".to_string(),
+            baud_rate: 115200,
+            dithering: DitheringConfig::Threshold { level: 64 },
+            layout: LayoutConfig::RowMajorMsbFirst,
+        });
+        assert_eq!(config.screens.len(), 5);
+        assert!(config.overlays.media.enabled);
+        assert!(config.control.enabled && config.control.socket.is_none());
+    }
+
+    #[test]
+    fn screens_have_options() {
+        let config = Config::parse("[[screens]]\ntype = \"clock\"\ndate_format = \"%d %b\"\n[[screens]]\ntype = \"stickfight\"\nfps = 30\ndithering = { type = \"bayer\" }\n").unwrap();
+        assert_eq!(config.screens, vec![
+            ScreenKind::Clock { format: "%H:%M".to_string(), date_format: Some("%d %b".to_string()) }.into(),
+            ScreenConfig { kind: ScreenKind::Stickfight { fps: 30.0 }, name: None, dithering: Some(DitheringConfig::Bayer { size: 4 }) },
+        ]);
+    }
+
+    #[test]
+    fn invalid_values_point_at_their_key() {
+        assert_eq!(invalid_key("[display]\nwidth = 0"), "display.width");
+        assert_eq!(invalid_key("[output]\ntype = \"udp\"\naddress = \"192.168.1.6\""), "output.address");
+        assert_eq!(invalid_key("screens = []"), "screens");
+        assert_eq!(invalid_key("[[screens]]\ntype = \"media\"\n[[screens]]\ntype = \"clock\"\nformat = \"%Q\""), "screens[1].format");
+        assert_eq!(invalid_key("[[screens]]\ntype = \"media\"\ndithering = { type = \"bayer\", size = 3 }"), "screens[0].dithering.size");
+        assert_eq!(invalid_key("[output]\ntype = \"udp\"\ndiscovery_address = \"255.255.255.255\""), "output.discovery_address");
+        assert_eq!(invalid_key("[output]\ntype = \"udp\"\naddress = \"oled:4435\"\ndevice = \"desk\""), "output.device");
+        assert_eq!(invalid_key("[output]\ntype = \"udp\"\naddress = \"oled:4435\"\nkeyframe_interval = 0"), "output.keyframe_interval");
+        assert_eq!(invalid_key("[output]\ntype = \"udp\"\naddress = \"oled:4435\"\nprotocol = 1"), "output.compression");
+        assert_eq!(invalid_key("[output]\ntype = \"udp\"\naddress = \"oled:4435\"\nlayout = { type = \"sh1106\", column_offset = 6 }"), "output.layout.column_offset");
+        assert_eq!(invalid_key("[[input.bindings]]\nkey = \"ctrl+hyper+O\"\naction = \"quit\""), "input.bindings[0].key");
+        assert_eq!(invalid_key("[http]\naddress = \"8080\""), "http.address");
+    }
+
+    #[test]
+    fn reported_sizes_are_checked_like_configured_ones() {
+        let config = Config::parse("[output]\ntype = \"udp\"\nlayout = { type = \"sh1106\", column_offset = 2 }\n[[displays]]\nname = \"shelf\"\n[displays.output]\ntype = \"udp\"\n").unwrap();
+        let mut displays = config.all_displays();
+        displays[0].use_reported_size(0, 128, 32).unwrap();
+        assert_eq!((displays[0].width, displays[0].height), (128, 32));
+        match displays[0].use_reported_size(0, 132, 64) {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "output.layout.column_offset"),
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+        assert_eq!((displays[0].width, displays[0].height), (128, 32));
+        match displays[1].use_reported_size(1, 3, 3) {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "displays[0]"),
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn extra_displays_have_their_own_setup() {
+        let config = Config::parse("[[displays]]\nname = \"shelf\"\nheight = 32\n[displays.output]\ntype = \"serial\"\nport = \"COM4\"\n[[displays.screens]]\ntype = \"media\"\n").unwrap();
+        let displays = config.all_displays();
+        assert_eq!(displays.len(), 2);
+        assert_eq!(displays[0].name, "main");
+        assert_eq!(displays[0].screens, Config::default().screens);
+        assert_eq!((displays[1].name.as_str(), displays[1].width, displays[1].height), ("shelf", 128, 32));
+        assert_eq!(displays[1].screens, vec![ScreenKind::Media {}.into()]);
+        assert!(matches!(&displays[1].output, OutputConfig::Serial { port, .. } if port == "COM4"));
+        assert!(displays[1].bindings.is_empty());
+    }
+
+    #[test]
+    fn extra_displays_are_validated() {
+        let shelf = "[[displays]]\nname = \"shelf\"\n[displays.output]\ntype = \"serial\"\nport = \"COM4\"\n";
+        assert_eq!(invalid_key(&format!("{}[[displays.screens]]\ntype = \"media\"\n[[displays.screens]]\ntype = \"clock\"\nformat = \"%Q\"", shelf)), "displays[0].screens[1].format");
+        assert_eq!(invalid_key(&format!("{}{}", shelf, shelf)), "displays[1].name");
+        assert_eq!(invalid_key("[[displays]]\nname = \"main\"\n[displays.output]\ntype = \"terminal\"\n"), "displays[0].name");
+        assert_eq!(invalid_key("[output]\ntype = \"terminal\"\n[[displays]]\nname = \"shelf\"\n[displays.output]\ntype = \"terminal\"\n"), "displays[0].output");
+        assert_eq!(invalid_key(&format!("{}[[displays.bindings]]\nkey = \"ctrl+hyper+O\"\naction = \"quit\"", shelf)), "displays[0].bindings[0].key");
+    }
+
+    #[test]
+    fn network_keys_map_to_actions() {
+        let config = Config::parse("[[input.keys]]\ncode = 7\naction = \"next-screen\"\n[[input.keys]]\ncode = 7\npress = \"long\"\naction = { show-screen = \"media\" }\n[[input.keys]]\ncode = 8\naction = { show-screen = 0 }\n").unwrap();
+        let keys = config.input.keys.iter().map(|key| (key.code, key.press, key.action.clone())).collect::<Vec<_>>();
+        assert_eq!(keys, vec![
+            (7, Press::Short, UserInput::NextScreen),
+            (7, Press::Long, UserInput::ShowScreen(crate::ScreenRef::Name("media".to_string()))),
+            (8, Press::Short, UserInput::ShowScreen(crate::ScreenRef::Index(0))),
+        ]);
+        assert_eq!(invalid_key("[[input.keys]]\ncode = 7\naction = \"quit\"\n[[input.keys]]\ncode = 7\npress = \"short\"\naction = \"next-screen\""), "input.keys[1]");
+    }
+
+    #[test]
+    fn input_devices_are_read() {
+        let config = Config::parse("[[input.devices]]\npath = \"/dev/input/event3\"\ngrab = true\n[[input.devices]]\npath = \"/dev/input/event4\"\n").unwrap();
+        let devices = config.input.devices.iter().map(|device| (device.path.to_str().unwrap(), device.grab)).collect::<Vec<_>>();
+        assert_eq!(devices, [("/dev/input/event3", true), ("/dev/input/event4", false)]);
+        assert!(Config::default().input.devices.is_empty());
+        assert_eq!(invalid_key("[[input.devices]]\npath = \"\""), "input.devices[0].path");
+    }
+
+    #[test]
+    fn screens_are_named_after_their_type() {
+        let config = Config::parse("[[screens]]\ntype = \"clock\"\n[[screens]]\ntype = \"clock\"\nname = \"utc\"\n").unwrap();
+        assert_eq!(config.screens.iter().map(ScreenConfig::name).collect::<Vec<_>>(), ["clock", "utc"]);
+        assert_eq!(invalid_key("[[screens]]\ntype = \"media\"\nname = \"\""), "screens[0].name");
+    }
+
+    #[test]
+    fn parse_errors_show_the_location() {
+        let Err(ConfigError::Parse { error, .. }) = Config::parse("[display]\nwidth = 128\nheigth = 32\n") else {
+            panic!("expected a parse error");
+        };
+        let message = error.to_string();
+        assert!(message.contains("line 3"), "{}", message);
+        assert!(message.contains("heigth"), "{}", message);
+    }
+
+    #[test]
+    fn unknown_screen_options_are_rejected() {
+        for screen in ["type = \"media\"", "type = \"clock\"\ndithering = { type = \"atkinson\" }"] {
+            let result = Config::parse(&format!("[[screens]]\n{}\nfont_size = 3\n", screen));
+            assert!(matches!(result, Err(ConfigError::Parse { .. })), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn key_combinations_are_parsed() {
+        let combination = KeyCombination::parse("Ctrl+Shift+o").unwrap();
+        assert_eq!(combination, KeyCombination { ctrl: true, shift: true, key: 'O', ..Default::default() });
+        assert!(KeyCombination::parse("ctrl+F1").is_err());
+        assert!(KeyCombination::parse("").is_err());
+    }
+}
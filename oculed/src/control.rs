@@ -0,0 +1,235 @@
+//! Lets scripts on the same machine control OcuLED through a Unix socket.
+//!
+//! Clients send one JSON request per line and get one JSON response per line back. A request has a `command`,
+//! the arguments of that command, and optionally the `display` it is for (the main display if missing):
+//!
+//! ```text
+//! {"command": "next-screen"}
+//! {"command": "show-screen", "screen": "media", "display": "shelf"}
+//! {"command": "notify", "text": "Build passed", "duration_ms": 5000}
+//! {"command": "state"}
+//! ```
+//!
+//! Commands: `next-screen`, `prev-screen`, `move-screens` (`offset`), `show-screen` (`screen`, an index or a
+//! name), `screensaver-on`, `screensaver-off`, `notify` (`text`, `duration_ms`), `state` and `quit`.
+//!
+//! Responses are `{"ok": true}`, or `{"ok": false, "error": "..."}` if the request couldn't be handled. The
+//! response to `state` also has `displays`, a list of [DisplayState]s.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::display::{DisplayState, SharedState};
+use crate::{InputOrigin, ScreenRef, UserInput};
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    display: Option<String>,
+    #[serde(flatten)]
+    command: Command,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+enum Command {
+    NextScreen,
+    PrevScreen,
+    MoveScreens { offset: i32 },
+    ShowScreen { screen: ScreenRef },
+    ScreensaverOn,
+    ScreensaverOff,
+    Notify {
+        text: String,
+        #[serde(default = "default_notify_ms")]
+        duration_ms: u64,
+    },
+    State,
+    Quit,
+}
+
+fn default_notify_ms() -> u64 { 3000 }
+
+#[derive(Serialize, PartialEq, Debug)]
+struct Response {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    displays: Option<Vec<DisplayState>>,
+}
+
+/// Where the socket is unless configured: `$XDG_RUNTIME_DIR/oculed.sock`, or a file in the temp directory
+/// named after the user.
+pub fn default_socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("oculed.sock"),
+        None => std::env::temp_dir().join(format!("oculed-{}.sock", unsafe { libc::getuid() })),
+    }
+}
+
+/// Listens on the socket at `path` and sends the commands it receives to `tx`, tagged with the index of their
+/// display in `state`. The socket is removed when the returned guard is dropped.
+pub fn start(path: &Path, tx: mpsc::Sender<(InputOrigin, UserInput)>, state: SharedState) -> std::io::Result<ControlSocket> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(std::io::Error::new(std::io::ErrorKind::AddrInUse, format!("Another OcuLED is listening on {}", path.display())));
+        }
+        // Left behind by an instance that didn't exit cleanly
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let (tx, state) = (tx.clone(), Arc::clone(&state));
+                    std::thread::spawn(move || serve(stream, tx, state));
+                },
+                Err(e) => println!("Failed to accept control connection: {:?}", e),
+            }
+        }
+    });
+    Ok(ControlSocket { path: path.to_path_buf() })
+}
+
+fn serve(stream: UnixStream, tx: mpsc::Sender<(InputOrigin, UserInput)>, state: SharedState) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            println!("Failed to set up control connection: {:?}", e);
+            return;
+        },
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { return; };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = handle_request(&line, &tx, &state);
+        let mut json = serde_json::to_string(&response).unwrap();
+        json.push('\n');
+        if writer.write_all(json.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+fn handle_request(line: &str, tx: &mpsc::Sender<(InputOrigin, UserInput)>, state: &SharedState) -> Response {
+    let error = |message: String| Response { ok: false, error: Some(message), displays: None };
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return error(format!("Invalid request: {}", e)),
+    };
+    let displays = state.lock().unwrap().clone();
+    let display = match &request.display {
+        Some(name) => match displays.iter().position(|display| &display.name == name) {
+            Some(index) => index,
+            None => return error(format!("There is no display called {}", name)),
+        },
+        None => 0,
+    };
+    let input = match request.command {
+        Command::NextScreen => UserInput::NextScreen,
+        Command::PrevScreen => UserInput::PrevScreen,
+        Command::MoveScreens { offset } => UserInput::MoveScreens(offset),
+        Command::ShowScreen { screen } => UserInput::ShowScreen(screen),
+        Command::ScreensaverOn => UserInput::ScreensaverOn,
+        Command::ScreensaverOff => UserInput::ScreensaverOff,
+        Command::Notify { text, duration_ms } => UserInput::Notify { text, duration_ms },
+        Command::Quit => UserInput::Quit,
+        Command::State => return Response { ok: true, error: None, displays: Some(displays) },
+    };
+    match tx.send((InputOrigin::Control(display), input)) {
+        Ok(()) => Response { ok: true, error: None, displays: None },
+        Err(_) => error("OcuLED is shutting down".to_string()),
+    }
+}
+
+/// Removes the control socket when dropped.
+pub struct ControlSocket {
+    path: PathBuf,
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn displays() -> SharedState {
+        let display = |name: &str| DisplayState {
+            name: name.to_string(),
+            screen: 0,
+            screen_name: "clock".to_string(),
+            screens: vec!["clock".to_string(), "media".to_string()],
+            screensaver: false,
+            connection: None,
+        };
+        Arc::new(Mutex::new(vec![display("main"), display("shelf")]))
+    }
+
+    #[test]
+    fn requests_become_inputs_for_their_display() {
+        let (tx, rx) = mpsc::channel();
+        let state = displays();
+        for request in [
+            r#"{"command": "next-screen"}"#,
+            r#"{"command": "show-screen", "screen": "media", "display": "shelf"}"#,
+            r#"{"command": "move-screens", "offset": -2, "display": "main"}"#,
+            r#"{"command": "notify", "text": "Hi"}"#,
+        ] {
+            assert_eq!(handle_request(request, &tx, &state), Response { ok: true, error: None, displays: None });
+        }
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![
+            (InputOrigin::Control(0), UserInput::NextScreen),
+            (InputOrigin::Control(1), UserInput::ShowScreen(ScreenRef::Name("media".to_string()))),
+            (InputOrigin::Control(0), UserInput::MoveScreens(-2)),
+            (InputOrigin::Control(0), UserInput::Notify { text: "Hi".to_string(), duration_ms: 3000 }),
+        ]);
+    }
+
+    #[test]
+    fn bad_requests_are_answered_with_an_error() {
+        let (tx, rx) = mpsc::channel();
+        let state = displays();
+        for request in [
+            "not json",
+            r#"{"command": "explode"}"#,
+            r#"{"command": "move-screens"}"#,
+            r#"{"command": "next-screen", "display": "kitchen"}"#,
+        ] {
+            let response = handle_request(request, &tx, &state);
+            assert!(!response.ok && response.error.is_some(), "{}: {:?}", request, response);
+        }
+        assert_eq!(rx.try_iter().count(), 0);
+    }
+
+    #[test]
+    fn clients_talk_json_lines_over_the_socket() {
+        let path = std::env::temp_dir().join(format!("oculed-test-{}.sock", std::process::id()));
+        let (tx, rx) = mpsc::channel();
+        let socket = start(&path, tx.clone(), displays()).unwrap();
+        assert!(start(&path, tx, displays()).is_err());
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"{\"command\": \"screensaver-on\"}\n{\"command\": \"state\"}\n").unwrap();
+        let mut lines = BufReader::new(client).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), r#"{"ok":true}"#);
+        let state: serde_json::Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(state["displays"][1]["name"], "shelf");
+        assert_eq!(rx.recv().unwrap(), (InputOrigin::Control(0), UserInput::ScreensaverOn));
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
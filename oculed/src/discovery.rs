@@ -0,0 +1,151 @@
+//! Finding displays on the local network.
+//!
+//! A probe, the single byte `40`, is broadcast to the port displays receive frames on. Every display answers
+//! from that port with:
+//!
+//! | bytes | content                                              |
+//! |-------|------------------------------------------------------|
+//! | 1     | `41`                                                 |
+//! | 2     | width, little endian                                 |
+//! | 2     | height, little endian                                |
+//! | 1     | the newest [Protocol] version the display understands |
+//! | rest  | the display's name, utf-8                            |
+
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::output::Protocol;
+
+const PROBE: u8 = 40;
+const REPLY: u8 = 41;
+
+/// Where probes are sent by default: everyone on the local network, at the displays' frame port.
+pub const DEFAULT_BROADCAST_ADDRESS: &str = "255.255.255.255:4435";
+
+/// A display that answered a probe.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Device {
+    pub name: String,
+    /// Where to send frames
+    pub address: SocketAddr,
+    pub width: usize,
+    pub height: usize,
+    pub protocol: Protocol,
+}
+
+/// Sends a probe to `broadcast_address` and returns the displays that answer within `timeout`, in the order
+/// they answered.
+pub fn discover(broadcast_address: &str, timeout: Duration) -> std::io::Result<Vec<Device>> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&[PROBE], broadcast_address)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut devices = Vec::<Device>::new();
+    let mut buffer = [0u8; 256];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(devices);
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        match socket.recv_from(&mut buffer) {
+            Ok((len, sender)) => {
+                if let Some(device) = parse_reply(&buffer[..len], sender) {
+                    // Displays on several interfaces may answer more than once
+                    if !devices.contains(&device) {
+                        devices.push(device);
+                    }
+                }
+            },
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => return Ok(devices),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Discovers displays and returns the one called `name`, or the first to answer if `name` is `None`.
+pub fn find(broadcast_address: &str, name: Option<&str>, timeout: Duration) -> std::io::Result<Option<Device>> {
+    let devices = discover(broadcast_address, timeout)?;
+    Ok(devices.into_iter().find(|device| name.is_none_or(|name| device.name == name)))
+}
+
+fn parse_reply(reply: &[u8], sender: SocketAddr) -> Option<Device> {
+    let [REPLY, width_low, width_high, height_low, height_high, version, name @ ..] = reply else { return None; };
+    // Newer displays still understand the versions we know of
+    let protocol = Protocol::try_from(*version.min(&3)).ok()?;
+    Some(Device {
+        name: String::from_utf8(name.to_vec()).ok()?,
+        address: sender,
+        width: u16::from_le_bytes([*width_low, *width_high]) as usize,
+        height: u16::from_le_bytes([*height_low, *height_high]) as usize,
+        protocol,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The display's side: answers a probe.
+    fn encode_reply(name: &str, width: u16, height: u16, version: u8) -> Vec<u8> {
+        let mut reply = vec![REPLY];
+        reply.extend(width.to_le_bytes());
+        reply.extend(height.to_le_bytes());
+        reply.push(version);
+        reply.extend(name.as_bytes());
+        reply
+    }
+
+    /// Answers one probe on behalf of two displays, the second replying from its own socket. Returns the
+    /// address to probe and the displays' addresses.
+    fn start_responder() -> (String, SocketAddr, SocketAddr) {
+        let desk = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let shelf = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addresses = (desk.local_addr().unwrap(), shelf.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 16];
+            let (len, sender) = desk.recv_from(&mut buffer).unwrap();
+            if buffer[..len] == [PROBE] {
+                desk.send_to(b"not a reply", sender).unwrap();
+                desk.send_to(&encode_reply("desk", 128, 64, 3), sender).unwrap();
+                desk.send_to(&encode_reply("desk", 128, 64, 3), sender).unwrap();
+                shelf.send_to(&encode_reply("shelf", 128, 32, 1), sender).unwrap();
+            }
+        });
+        (addresses.0.to_string(), addresses.0, addresses.1)
+    }
+
+    #[test]
+    fn displays_answer_the_probe() {
+        let (probe_address, desk, shelf) = start_responder();
+        let devices = discover(&probe_address, Duration::from_millis(300)).unwrap();
+        assert_eq!(devices, vec![
+            Device { name: "desk".to_string(), address: desk, width: 128, height: 64, protocol: Protocol::V3 },
+            Device { name: "shelf".to_string(), address: shelf, width: 128, height: 32, protocol: Protocol::V1 },
+        ]);
+    }
+
+    #[test]
+    fn displays_are_picked_by_name() {
+        let (probe_address, _, shelf) = start_responder();
+        let device = find(&probe_address, Some("shelf"), Duration::from_millis(300)).unwrap().unwrap();
+        assert_eq!(device.address, shelf);
+
+        let (probe_address, desk, _) = start_responder();
+        assert_eq!(find(&probe_address, None, Duration::from_millis(300)).unwrap().unwrap().address, desk);
+
+        let (probe_address, _, _) = start_responder();
+        assert_eq!(find(&probe_address, Some("kitchen"), Duration::from_millis(300)).unwrap(), None);
+    }
+
+    #[test]
+    fn replies_are_validated() {
+        let sender = "127.0.0.1:4435".parse().unwrap();
+        assert_eq!(parse_reply(&encode_reply("", 8, 8, 2), sender).unwrap().protocol, Protocol::V2);
+        assert_eq!(parse_reply(&encode_reply("future", 8, 8, 9), sender).unwrap().protocol, Protocol::V3);
+        assert_eq!(parse_reply(&encode_reply("broken", 8, 8, 0), sender), None);
+        assert_eq!(parse_reply(&[REPLY, 8, 0, 8], sender), None);
+        assert_eq!(parse_reply(&[REPLY, 8, 0, 8, 0, 1, 0xff], sender), None);
+    }
+}
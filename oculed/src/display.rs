@@ -0,0 +1,181 @@
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use graphics::components::{self, Bounds, Drawable};
+use graphics::rendering;
+
+use crate::output::{ConnectionState, RenderTarget};
+use crate::overlays;
+use crate::screens;
+use crate::{ScreenRef, UserInput};
+
+/// What a display is showing, see [Display::state].
+#[derive(serde::Serialize, Clone, PartialEq, Debug)]
+pub struct DisplayState {
+    pub name: String,
+    /// The index of the active screen
+    pub screen: usize,
+    pub screen_name: String,
+    /// The names of all screens, in order
+    pub screens: Vec<String>,
+    pub screensaver: bool,
+    /// Only known for displays that acknowledge frames
+    pub connection: Option<ConnectionState>,
+}
+
+/// The state of every display, kept up to date by the main loop for the control API.
+pub type SharedState = Arc<Mutex<Vec<DisplayState>>>;
+
+/// One physical display: what is drawn on it, and the outputs the frames are sent to.
+pub struct Display<'a> {
+    name: String,
+    screens: screens::ScreenCollection<'a>,
+    screensaver: components::Layer<overlays::ScreensaverOverlay>,
+    media_overlay: Option<Box<dyn Drawable + 'a>>,
+    notification: overlays::NotificationOverlay,
+    outputs: Vec<Output<'a>>,
+    /// For [UserInput::ShowScreen], in the same order as the screens
+    screen_names: Vec<String>,
+    canvas: rendering::Bitmap,
+    /// The address the display sends network input from, if it is on the network
+    ip: Option<IpAddr>,
+}
+
+struct Output<'a> {
+    target: Box<dyn RenderTarget + 'a>,
+    ditherer: rendering::Ditherer,
+    /// The last reported state, to tell when it changes
+    state: Option<ConnectionState>,
+}
+
+impl<'a> Display<'a> {
+    pub fn new(
+        name: String,
+        width: usize,
+        height: usize,
+        screens: screens::ScreenCollection<'a>,
+        screensaver: components::Layer<overlays::ScreensaverOverlay>,
+        media_overlay: Option<Box<dyn Drawable + 'a>>,
+        ip: Option<IpAddr>,
+    ) -> Self {
+        Self {
+            name,
+            screens,
+            screensaver,
+            media_overlay,
+            notification: overlays::NotificationOverlay::new(),
+            outputs: Vec::new(),
+            screen_names: Vec::new(),
+            canvas: rendering::Bitmap::new(width, height),
+            ip,
+        }
+    }
+
+    pub fn with_screen_names(mut self, names: Vec<String>) -> Self {
+        self.screen_names = names;
+        self
+    }
+
+    /// Adds an output that every frame is sent to, dithered with `dithering`.
+    pub fn add_output(&mut self, target: Box<dyn RenderTarget + 'a>, dithering: rendering::Dithering) {
+        let state = target.connection_state();
+        self.outputs.push(Output { target, ditherer: rendering::Ditherer::new(dithering), state });
+    }
+
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.ip
+    }
+
+    /// Draws the next frame and sends it to every output.
+    pub fn update(&mut self, elapsed: &Duration) {
+        self.canvas.clear();
+        let bounds = Bounds::cover_bitmap(&self.canvas);
+        self.screens.draw(&mut self.canvas, bounds, elapsed);
+        // The screensaver hides the screens. It can't clear them itself when it's drawn through a dithering layer.
+        if self.screensaver.inner().is_shown() {
+            self.canvas.clear();
+        }
+        self.screensaver.draw(&mut self.canvas, bounds, elapsed);
+        if let Some(media_overlay) = self.media_overlay.as_mut() {
+            media_overlay.draw(&mut self.canvas, bounds, elapsed);
+        }
+        self.notification.draw(&mut self.canvas, bounds, elapsed);
+        for output in self.outputs.iter_mut() {
+            if let Err(e) = output.target.render_bitmap(output.ditherer.to_binary(&self.canvas)) {
+                println!("Failed to send bitmap to {}: {:?}", self.name, e);
+            }
+            if output.target.connection_state() != output.state {
+                output.state = output.target.connection_state();
+                println!("Display {} connection: {:?}", self.name, output.state.unwrap());
+            }
+        }
+    }
+
+    pub fn state(&self) -> DisplayState {
+        let screen = self.screens.active_screen();
+        DisplayState {
+            name: self.name.clone(),
+            screen,
+            screen_name: self.screen_names.get(screen).cloned().unwrap_or_default(),
+            screens: self.screen_names.clone(),
+            screensaver: self.screensaver.inner().is_shown(),
+            connection: self.outputs.iter().find_map(|output| output.state),
+        }
+    }
+
+    /// Handles input meant for this display. [UserInput::Quit] is left to the caller.
+    pub fn handle_input(&mut self, input: &UserInput) {
+        match input {
+            UserInput::NextScreen => self.screens.next_screen(),
+            UserInput::PrevScreen => self.screens.previous_screen(),
+            UserInput::MoveScreens(offset) => self.screens.move_screens(*offset),
+            UserInput::ShowScreen(ScreenRef::Index(index)) => {
+                if !self.screens.show_screen(*index) {
+                    println!("Display {} has no screen {}", self.name, index);
+                }
+            },
+            UserInput::ShowScreen(ScreenRef::Name(name)) => {
+                match self.screen_names.iter().position(|screen| screen == name) {
+                    Some(index) => { self.screens.show_screen(index); },
+                    None => println!("Display {} has no screen called {}", self.name, name),
+                }
+            },
+            UserInput::ScreensaverOn => self.screensaver.inner_mut().show(),
+            UserInput::ScreensaverOff => self.screensaver.inner_mut().hide(),
+            UserInput::Notify { text, duration_ms } => self.notification.show(text, Duration::from_millis(*duration_ms)),
+            UserInput::Quit => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct LitScreen;
+
+    impl Drawable for LitScreen {
+        fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, _elapsed: &Duration) {
+            canvas.draw_rect(bounds.pos.x, bounds.pos.y, bounds.size.width as usize, bounds.size.height as usize);
+        }
+    }
+
+    impl screens::Screen for LitScreen {
+        fn on_mount(&mut self) {}
+    }
+
+    #[test]
+    fn dithered_screensaver_hides_the_screens() {
+        let mut screen = LitScreen;
+        let screensaver = components::Layer::new(overlays::ScreensaverOverlay::new(30.0))
+            .with_dithering(rendering::Dithering::Bayer(4));
+        let mut display = Display::new("test".to_string(), 128, 64, screens::ScreenCollection::new(vec![&mut screen]), screensaver, None, None);
+        display.handle_input(&UserInput::ScreensaverOn);
+        display.update(&Duration::from_millis(10));
+
+        // The videos are 128x36, centered on the display
+        let outside_video = [0..14 * 128, 50 * 128..64 * 128];
+        assert!(outside_video.into_iter().flatten().all(|i| display.canvas.buffer[i] == 0));
+    }
+}
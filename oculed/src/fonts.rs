@@ -1,14 +1,18 @@
 
+// Fonts without a screen using them are kept around to try out
+#[allow(dead_code)]
 static ROBOTO_BYTES: &[u8] = include_bytes!("../resources/fonts/Roboto-Bold.ttf");
 lazy_static![
     pub static ref ROBOTO: fontdue::Font = fontdue::Font::from_bytes(ROBOTO_BYTES, fontdue::FontSettings::default()).expect("Failed to load font");
 ];
 
+#[allow(dead_code)]
 static ELFBOY_BYTES: &[u8] = include_bytes!("../resources/fonts/Elfboyclassic.ttf");
 lazy_static![
     pub static ref ELFBOY: fontdue::Font = fontdue::Font::from_bytes(ELFBOY_BYTES, fontdue::FontSettings::default()).expect("Failed to load font");
 ];
 
+#[allow(dead_code)]
 static RETROSIGNATURE_BYTES: &[u8] = include_bytes!("../resources/fonts/RetroSignature.otf");
 lazy_static![
     pub static ref RETROSIGNATURE: fontdue::Font = fontdue::Font::from_bytes(RETROSIGNATURE_BYTES, fontdue::FontSettings::default()).expect("Failed to load font");
@@ -38,6 +42,7 @@ lazy_static![
     pub static ref SYMTEXT: fontdue::Font = fontdue::Font::from_bytes(SYMTEXT_BYTES, fontdue::FontSettings::default()).expect("Failed to load font");
 ];
 
+#[allow(dead_code)]
 static SQHEAD_BYTES: &[u8] = include_bytes!("../resources/fonts/SF Square Head.ttf");
 lazy_static![
     /// Use with multiples of 8px
@@ -0,0 +1,478 @@
+//! An HTTP API for watching and controlling the displays from a browser.
+//!
+//! | method | path                                 | does                                                     |
+//! |--------|--------------------------------------|----------------------------------------------------------|
+//! | GET    | `/`                                  | a page with a live preview and buttons                   |
+//! | GET    | `/state`                             | the [DisplayState] of every display, as json             |
+//! | GET    | `/frame.png`                         | the latest frame                                         |
+//! | GET    | `/stream`                            | every new frame, as a `multipart/x-mixed-replace` stream |
+//! | POST   | `/screens/next`, `/screens/prev`     | switches screens                                         |
+//! | POST   | `/screens/<index or name>`           | jumps to a screen                                        |
+//! | POST   | `/screensaver/on`, `/screensaver/off`| toggles the screensaver                                  |
+//! | POST   | `/notify`                            | shows the notification `{"text": "...", "duration_ms": 3000}` |
+//!
+//! Every path takes `?display=<name>` to pick a display, the main one if missing. POST requests are answered like
+//! the control socket's: `{"ok": true}`, or `{"ok": false, "error": "..."}`.
+//!
+//! So that other web pages can't control the displays, POST requests must have `Content-Type: application/json`
+//! (which browsers only send cross-site after asking first) and can't come from another origin. Requests for other
+//! hosts than the configured one, `localhost` or an IP address are refused, which stops DNS rebinding.
+
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::display::SharedState;
+use crate::output::{encode_png, FrameSlot};
+use crate::{InputOrigin, ScreenRef, UserInput};
+
+const PREVIEW_PAGE: &str = include_str!("../resources/http/preview.html");
+const STREAM_BOUNDARY: &str = "frame";
+/// How many requests are handled at the same time, streams included
+const MAX_REQUESTS: usize = 32;
+const MAX_STREAMS: usize = 8;
+/// How often a stream resends its frame when the picture doesn't change, to notice clients that went away
+const KEEP_ALIVE: Duration = Duration::from_secs(2);
+
+/// Serves the API on `address`, e.g. `127.0.0.1:8080`. `frames` holds the frames of the displays in `state`,
+/// in the same order.
+pub fn start(address: &str, tx: mpsc::Sender<(InputOrigin, UserInput)>, state: SharedState, frames: Vec<FrameSlot>) -> std::io::Result<()> {
+    let server = tiny_http::Server::http(address).map_err(std::io::Error::other)?;
+    let context = Arc::new(Context::new(tx, state, frames, host_name(address)));
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let context = Arc::clone(&context);
+            if !context.requests.acquire() {
+                let response = tiny_http::Response::from_string(r#"{"ok":false,"error":"Too many requests"}"#).with_status_code(503);
+                let _ = request.respond(with_type(response, "application/json"));
+                continue;
+            }
+            // Streams never finish, so every request gets its own thread
+            std::thread::spawn(move || {
+                handle(request, &context);
+                context.requests.release();
+            });
+        }
+    });
+    Ok(())
+}
+
+struct Context {
+    tx: mpsc::Sender<(InputOrigin, UserInput)>,
+    state: SharedState,
+    frames: Vec<FrameSlot>,
+    /// The host the server was configured to listen on, e.g. `127.0.0.1`
+    host: String,
+    requests: Limit,
+    streams: Limit,
+}
+
+impl Context {
+    fn new(tx: mpsc::Sender<(InputOrigin, UserInput)>, state: SharedState, frames: Vec<FrameSlot>, host: &str) -> Self {
+        Self {
+            tx,
+            state,
+            frames,
+            host: host.to_string(),
+            requests: Limit::new(MAX_REQUESTS),
+            streams: Limit::new(MAX_STREAMS),
+        }
+    }
+}
+
+/// Counts something there may only be `max` of at a time.
+struct Limit {
+    active: AtomicUsize,
+    max: usize,
+}
+
+impl Limit {
+    fn new(max: usize) -> Self {
+        Self { active: AtomicUsize::new(0), max }
+    }
+
+    /// Counts one more, unless there already are `max`. Returns whether it was counted.
+    fn acquire(&self) -> bool {
+        self.active.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| (active < self.max).then_some(active + 1)).is_ok()
+    }
+
+    fn release(&self) {
+        self.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The request headers that decide whether a request is answered.
+#[derive(Clone, Copy, Default)]
+struct Headers<'a> {
+    host: Option<&'a str>,
+    origin: Option<&'a str>,
+    content_type: Option<&'a str>,
+}
+
+#[derive(PartialEq, Debug)]
+enum Reply {
+    Json(u16, String),
+    Html(&'static str),
+    Png(Vec<u8>),
+    /// The frames of the display with this index
+    Stream(usize),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Notification {
+    text: String,
+    #[serde(default = "default_notify_ms")]
+    duration_ms: u64,
+}
+
+fn default_notify_ms() -> u64 { 3000 }
+
+fn handle(mut request: tiny_http::Request, context: &Context) {
+    let mut body = String::new();
+    if let Err(e) = request.as_reader().take(64 * 1024).read_to_string(&mut body) {
+        println!("Failed to read HTTP request: {:?}", e);
+        return;
+    }
+    let is_post = *request.method() == tiny_http::Method::Post;
+    let reply = {
+        let header = |name: &'static str| request.headers().iter().find(|header| header.field.equiv(name)).map(|header| header.value.as_str());
+        let headers = Headers { host: header("Host"), origin: header("Origin"), content_type: header("Content-Type") };
+        respond(is_post, request.url(), &body, &headers, context)
+    };
+    let json = |status: u16, json: String| with_type(tiny_http::Response::from_string(json).with_status_code(status), "application/json");
+    let result = match reply {
+        Reply::Json(status, body) => request.respond(json(status, body)),
+        Reply::Html(html) => request.respond(with_type(tiny_http::Response::from_string(html), "text/html; charset=utf-8")),
+        Reply::Png(png) => request.respond(with_type(tiny_http::Response::from_data(png), "image/png")),
+        Reply::Stream(display) if context.streams.acquire() => {
+            let result = stream_frames(request.into_writer(), &context.frames[display], KEEP_ALIVE);
+            context.streams.release();
+            result
+        },
+        Reply::Stream(_) => request.respond(json(503, serde_json::json!({ "ok": false, "error": "Too many streams" }).to_string())),
+    };
+    // Clients closing the connection early, e.g. by leaving the preview page, is not worth reporting
+    if let Err(e) = result {
+        if !matches!(e.kind(), std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::ConnectionReset) {
+            println!("Failed to send HTTP response: {:?}", e);
+        }
+    }
+}
+
+fn with_type<R: Read>(response: tiny_http::Response<R>, content_type: &str) -> tiny_http::Response<R> {
+    response.with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap())
+}
+
+fn respond(is_post: bool, url: &str, body: &str, headers: &Headers, context: &Context) -> Reply {
+    let error = |status: u16, message: String| Reply::Json(status, serde_json::json!({ "ok": false, "error": message }).to_string());
+    if let Err((status, message)) = check_headers(is_post, headers, &context.host) {
+        return error(status, message);
+    }
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let display_name = query.split('&').find_map(|pair| pair.strip_prefix("display=")).map(decode_component);
+    let displays = context.state.lock().unwrap().clone();
+    let display = match display_name {
+        Some(name) => match displays.iter().position(|display| display.name == name) {
+            Some(index) => index,
+            None => return error(404, format!("There is no display called {}", name)),
+        },
+        None => 0,
+    };
+
+    if !is_post {
+        return match path {
+            "/" => Reply::Html(PREVIEW_PAGE),
+            "/state" => Reply::Json(200, serde_json::to_string(&displays).unwrap()),
+            "/frame.png" => match context.frames[display].latest() {
+                Some((_, frame)) => Reply::Png(encode_png(&frame)),
+                None => error(503, "No frame has been rendered yet".to_string()),
+            },
+            "/stream" => Reply::Stream(display),
+            _ => error(404, format!("Nothing at {}", path)),
+        };
+    }
+
+    let input = match path {
+        "/screens/next" => UserInput::NextScreen,
+        "/screens/prev" => UserInput::PrevScreen,
+        "/screensaver/on" => UserInput::ScreensaverOn,
+        "/screensaver/off" => UserInput::ScreensaverOff,
+        "/notify" => match serde_json::from_str::<Notification>(body) {
+            Ok(Notification { text, duration_ms }) => UserInput::Notify { text, duration_ms },
+            Err(e) => return error(400, format!("Invalid notification: {}", e)),
+        },
+        _ => match path.strip_prefix("/screens/") {
+            // Numbers are indices, anything else is a name
+            Some(screen) => UserInput::ShowScreen(match screen.parse() {
+                Ok(index) => ScreenRef::Index(index),
+                Err(_) => ScreenRef::Name(decode_component(screen)),
+            }),
+            None => return error(404, format!("Nothing at {}", path)),
+        },
+    };
+    match context.tx.send((InputOrigin::Control(display), input)) {
+        Ok(()) => Reply::Json(200, serde_json::json!({ "ok": true }).to_string()),
+        Err(_) => error(503, "OcuLED is shutting down".to_string()),
+    }
+}
+
+/// Refuses requests for other hosts, and POST requests that a web page on another site could have sent.
+fn check_headers(is_post: bool, headers: &Headers, host: &str) -> Result<(), (u16, String)> {
+    if let Some(requested) = headers.host {
+        let name = host_name(requested);
+        // A page can only rebind a domain name to this computer, not an IP address
+        if !(name.eq_ignore_ascii_case(host) || name.eq_ignore_ascii_case("localhost") || name.parse::<std::net::IpAddr>().is_ok()) {
+            return Err((403, format!("Not serving host {}", requested)));
+        }
+    }
+    if !is_post {
+        return Ok(());
+    }
+    if let Some(origin) = headers.origin {
+        if headers.host.map(|host| format!("http://{}", host)).as_deref() != Some(origin) {
+            return Err((403, format!("Not accepting requests from {}", origin)));
+        }
+    }
+    let media_type = headers.content_type.and_then(|content_type| content_type.split(';').next()).map(str::trim);
+    if !media_type.is_some_and(|media_type| media_type.eq_ignore_ascii_case("application/json")) {
+        return Err((415, "POST requests must have Content-Type: application/json".to_string()));
+    }
+    Ok(())
+}
+
+/// The host of a `host:port` address or `Host` header, without the brackets around IPv6 addresses.
+fn host_name(address: &str) -> &str {
+    match address.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(rest),
+        None => address.rsplit_once(':').map_or(address, |(host, _)| host),
+    }
+}
+
+/// Undoes the percent-encoding of a path segment or query value.
+fn decode_component(component: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = component.as_bytes();
+    while let [first, tail @ ..] = rest {
+        match (first, tail) {
+            (b'%', [high, low, tail @ ..]) if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&[*high, *low]).unwrap(), 16).unwrap());
+                rest = tail;
+                continue;
+            },
+            (b'+', _) => bytes.push(b' '),
+            _ => bytes.push(*first),
+        }
+        rest = tail;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Writes every frame that differs from the previous one as a part of a `multipart/x-mixed-replace` response,
+/// until the client disconnects. The response is written by hand, since tiny_http buffers bodies it sends in
+/// chunks, which would hold back the small frames.
+///
+/// The last frame is sent again when nothing was sent for `keep_alive`, since a disconnected client is only
+/// noticed when writing to it.
+fn stream_frames<W: Write>(mut writer: W, slot: &FrameSlot, keep_alive: Duration) -> std::io::Result<()> {
+    write!(writer, "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary={}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n", STREAM_BOUNDARY)?;
+    writer.flush()?;
+    let mut number = 0;
+    // The last frame sent, and its png
+    let mut last: Option<(Vec<u8>, Vec<u8>)> = None;
+    let mut last_sent = Instant::now();
+    loop {
+        let mut changed = false;
+        if let Some((new_number, frame)) = slot.wait_after(number, keep_alive) {
+            number = new_number;
+            if last.as_ref().map(|(buffer, _)| buffer) != Some(&frame.buffer) {
+                let png = encode_png(&frame);
+                last = Some((frame.buffer, png));
+                changed = true;
+            }
+        }
+        let Some((_, png)) = &last else { continue; };
+        if !changed && last_sent.elapsed() < keep_alive {
+            continue;
+        }
+        write!(writer, "--{}\r\nContent-Type: image/png\r\nContent-Length: {}\r\n\r\n", STREAM_BOUNDARY, png.len())?;
+        writer.write_all(png)?;
+        writer.write_all(b"\r\n")?;
+        writer.flush()?;
+        last_sent = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use crate::display::DisplayState;
+    use crate::output::{HttpOutput, RenderTarget};
+    use graphics::rendering::BinaryBitmap;
+
+    fn context() -> (Context, mpsc::Receiver<(InputOrigin, UserInput)>) {
+        let display = |name: &str| DisplayState {
+            name: name.to_string(),
+            screen: 0,
+            screen_name: "clock".to_string(),
+            screens: vec!["clock".to_string(), "media".to_string()],
+            screensaver: false,
+            connection: None,
+        };
+        let (tx, rx) = mpsc::channel();
+        let state = Arc::new(Mutex::new(vec![display("main"), display("shelf desk")]));
+        (Context::new(tx, state, vec![FrameSlot::default(), FrameSlot::default()], "127.0.0.1"), rx)
+    }
+
+    /// What the preview page sends
+    const JSON: Headers = Headers { host: Some("127.0.0.1:8080"), origin: Some("http://127.0.0.1:8080"), content_type: Some("application/json") };
+
+    #[test]
+    fn posts_become_inputs_for_their_display() {
+        let (context, rx) = context();
+        let ok = Reply::Json(200, r#"{"ok":true}"#.to_string());
+        assert_eq!(respond(true, "/screens/next", "", &JSON, &context), ok);
+        assert_eq!(respond(true, "/screens/2?display=shelf%20desk", "", &JSON, &context), ok);
+        assert_eq!(respond(true, "/screens/now%20playing", "", &JSON, &context), ok);
+        assert_eq!(respond(true, "/screensaver/on?display=shelf+desk", "", &JSON, &context), ok);
+        assert_eq!(respond(true, "/notify", r#"{"text": "Hi"}"#, &JSON, &context), ok);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![
+            (InputOrigin::Control(0), UserInput::NextScreen),
+            (InputOrigin::Control(1), UserInput::ShowScreen(ScreenRef::Index(2))),
+            (InputOrigin::Control(0), UserInput::ShowScreen(ScreenRef::Name("now playing".to_string()))),
+            (InputOrigin::Control(1), UserInput::ScreensaverOn),
+            (InputOrigin::Control(0), UserInput::Notify { text: "Hi".to_string(), duration_ms: 3000 }),
+        ]);
+    }
+
+    #[test]
+    fn bad_requests_get_error_statuses() {
+        let (context, rx) = context();
+        let status = |is_post, url, body| match respond(is_post, url, body, &JSON, &context) {
+            Reply::Json(status, _) => status,
+            other => panic!("expected json, got {:?}", other),
+        };
+        assert_eq!(status(true, "/screens/next?display=kitchen", ""), 404);
+        assert_eq!(status(true, "/explode", ""), 404);
+        assert_eq!(status(true, "/notify", "{}"), 400);
+        assert_eq!(status(false, "/frame.png", ""), 503);
+        assert_eq!(rx.try_iter().count(), 0);
+    }
+
+    #[test]
+    fn requests_other_sites_could_send_are_refused() {
+        let (context, rx) = context();
+        let status = |is_post, headers: Headers| match respond(is_post, "/screensaver/on", "", &headers, &context) {
+            Reply::Json(status, _) => status,
+            other => panic!("expected json, got {:?}", other),
+        };
+        // Forms and no-cors fetches can't set this content type
+        assert_eq!(status(true, Headers { content_type: Some("text/plain"), ..JSON }), 415);
+        assert_eq!(status(true, Headers { content_type: None, ..JSON }), 415);
+        assert_eq!(status(true, Headers { origin: Some("http://example.com"), ..JSON }), 403);
+        assert_eq!(status(true, Headers { origin: Some("null"), ..JSON }), 403);
+        // DNS rebinding
+        assert_eq!(status(true, Headers { host: Some("evil.example.com:8080"), origin: Some("http://evil.example.com:8080"), ..JSON }), 403);
+        assert_eq!(status(false, Headers { host: Some("evil.example.com:8080"), ..JSON }), 403);
+        assert_eq!(rx.try_iter().count(), 0);
+
+        // Clients other than browsers don't send an origin
+        assert_eq!(status(true, Headers { origin: None, content_type: Some("application/json; charset=utf-8"), ..JSON }), 200);
+        assert_eq!(status(true, Headers { host: Some("localhost:8080"), origin: Some("http://localhost:8080"), ..JSON }), 200);
+        assert_eq!(status(true, Headers { host: Some("[::1]:8080"), origin: Some("http://[::1]:8080"), ..JSON }), 200);
+        assert_eq!(rx.try_iter().count(), 3);
+    }
+
+    #[test]
+    fn frames_are_served_over_http() {
+        let (context, _rx) = context();
+        let mut output = HttpOutput::new(context.frames[1].clone());
+        output.render_bitmap(BinaryBitmap { width: 8, height: 2, buffer: vec![0xff, 0x00] }).unwrap();
+        let address = "127.0.0.1:0".parse::<std::net::SocketAddr>().unwrap();
+        let port = std::net::TcpListener::bind(address).unwrap().local_addr().unwrap().port();
+        start(&format!("127.0.0.1:{}", port), context.tx, context.state, context.frames).unwrap();
+
+        let get = |path: &str| {
+            let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).unwrap();
+            let split = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap();
+            (String::from_utf8_lossy(&response[..split]).into_owned(), response[split + 4..].to_vec())
+        };
+        let (head, body) = get("/frame.png?display=shelf%20desk");
+        assert!(head.starts_with("HTTP/1.1 200") && head.contains("image/png"), "{}", head);
+        assert_eq!(image::load_from_memory(&body).unwrap().into_luma8().dimensions(), (8, 2));
+
+        let (head, body) = get("/state");
+        assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+        assert!(String::from_utf8(body).unwrap().contains(r#""name":"shelf desk""#));
+
+        let (head, _) = get("/frame.png");
+        assert!(head.starts_with("HTTP/1.1 503"), "{}", head);
+    }
+
+    /// Collects what is written, and fails like a closed connection once `parts` frames were flushed.
+    struct Client {
+        received: Vec<u8>,
+        parts: usize,
+    }
+
+    impl Write for Client {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.received.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            let received = self.received.windows(7).filter(|window| window == b"--frame").count();
+            if received == self.parts {
+                return Err(std::io::ErrorKind::BrokenPipe.into());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn streams_skip_unchanged_frames() {
+        let slot = FrameSlot::default();
+        let mut output = HttpOutput::new(slot.clone());
+        let frame = |byte| BinaryBitmap { width: 8, height: 1, buffer: vec![byte] };
+        output.render_bitmap(frame(1)).unwrap();
+        let renderer = std::thread::spawn(move || {
+            for byte in [1, 1, 2] {
+                std::thread::sleep(Duration::from_millis(50));
+                output.render_bitmap(frame(byte)).unwrap();
+            }
+        });
+
+        let mut client = Client { received: Vec::new(), parts: 2 };
+        assert!(stream_frames(&mut client, &slot, Duration::from_secs(5)).is_err());
+        renderer.join().unwrap();
+        let received = String::from_utf8_lossy(&client.received).into_owned();
+        assert!(received.starts_with("HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary=frame\r\n"), "{}", received);
+        assert_eq!(received.matches("--frame\r\nContent-Type: image/png\r\n").count(), 2);
+    }
+
+    #[test]
+    fn streams_of_unchanged_frames_notice_disconnects() {
+        let slot = FrameSlot::default();
+        HttpOutput::new(slot.clone()).render_bitmap(BinaryBitmap { width: 8, height: 1, buffer: vec![1] }).unwrap();
+        // Without keep-alives, this would wait for a new frame forever
+        let mut client = Client { received: Vec::new(), parts: 3 };
+        assert!(stream_frames(&mut client, &slot, Duration::from_millis(20)).is_err());
+    }
+
+    #[test]
+    fn limits_are_kept() {
+        let limit = Limit::new(2);
+        assert!(limit.acquire() && limit.acquire());
+        assert!(!limit.acquire());
+        limit.release();
+        assert!(limit.acquire());
+    }
+}
@@ -0,0 +1,165 @@
+use std::io::Read;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+
+use crate::config::KeyCombination;
+use crate::{InputOrigin, UserInput};
+use super::{Bindings, InputSource};
+
+const EV_KEY: u16 = 1;
+/// The `value` of a key event when the key goes down. 0 is a release and 2 an autorepeat.
+const KEY_PRESSED: i32 = 1;
+/// `_IOW('E', 0x90, int)`, see linux/input.h
+const EVIOCGRAB: libc::c_ulong = 0x40044590;
+
+/// Reads key presses from a Linux input device, e.g. `/dev/input/by-id/usb-...-event-kbd` for a macropad, and
+/// sends the actions bound to them.
+pub struct EvdevInput {
+    path: PathBuf,
+    grab: bool,
+    bindings: Bindings,
+}
+
+impl EvdevInput {
+    pub fn new(path: PathBuf, bindings: Bindings) -> Self {
+        Self { path, grab: false, bindings }
+    }
+
+    /// Take the device for ourselves, so its key presses don't also reach other programs.
+    pub fn with_grab(mut self, grab: bool) -> Self {
+        self.grab = grab;
+        self
+    }
+}
+
+impl InputSource for EvdevInput {
+    fn start(&mut self, tx: std::sync::mpsc::Sender<(InputOrigin, UserInput)>) -> std::io::Result<()> {
+        let mut device = std::fs::File::open(&self.path)?;
+        if self.grab && unsafe { libc::ioctl(device.as_raw_fd(), EVIOCGRAB, 1) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let (path, bindings) = (self.path.clone(), self.bindings.clone());
+        std::thread::spawn(move || {
+            let mut modifiers = Modifiers::default();
+            let mut event = [0u8; std::mem::size_of::<libc::input_event>()];
+            loop {
+                // The device is closed when the thread ends, which also releases the grab
+                if let Err(e) = device.read_exact(&mut event) {
+                    if e.kind() != std::io::ErrorKind::UnexpectedEof {
+                        println!("Failed to read from {:?}: {:?}", path, e);
+                    }
+                    return;
+                }
+                if let Some(combination) = modifiers.update(&event) {
+                    if !bindings.send(&combination, &tx) {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Tracks which modifiers are held, to turn key events into [KeyCombination]s.
+#[derive(Default)]
+struct Modifiers {
+    ctrl: [bool; 2],
+    alt: [bool; 2],
+    shift: [bool; 2],
+    super_key: [bool; 2],
+}
+
+impl Modifiers {
+    /// Handles a raw `input_event`, returning the combination if a letter or digit was pressed.
+    fn update(&mut self, event: &[u8]) -> Option<KeyCombination> {
+        // The event ends with type, code and value, after a timestamp whose size depends on the platform
+        let event = &event[event.len() - 8..];
+        if u16::from_ne_bytes([event[0], event[1]]) != EV_KEY {
+            return None;
+        }
+        let code = u16::from_ne_bytes([event[2], event[3]]);
+        let value = i32::from_ne_bytes([event[4], event[5], event[6], event[7]]);
+        // Modifiers are held on autorepeat too
+        let held = value != 0;
+        match code {
+            29 => self.ctrl[0] = held,
+            97 => self.ctrl[1] = held,
+            56 => self.alt[0] = held,
+            100 => self.alt[1] = held,
+            42 => self.shift[0] = held,
+            54 => self.shift[1] = held,
+            125 => self.super_key[0] = held,
+            126 => self.super_key[1] = held,
+            _ if value == KEY_PRESSED => {
+                return key_char(code).map(|key| KeyCombination {
+                    ctrl: self.ctrl.contains(&true),
+                    alt: self.alt.contains(&true),
+                    shift: self.shift.contains(&true),
+                    super_key: self.super_key.contains(&true),
+                    key,
+                });
+            },
+            _ => {},
+        }
+        None
+    }
+}
+
+/// The letter or digit for a key code, see linux/input-event-codes.h.
+fn key_char(code: u16) -> Option<char> {
+    let row = |first: u16, keys: &str| code.checked_sub(first).and_then(|i| keys.chars().nth(i as usize));
+    match code {
+        2..=11 => row(2, "1234567890"),
+        16..=25 => row(16, "QWERTYUIOP"),
+        30..=38 => row(30, "ASDFGHJKL"),
+        44..=50 => row(44, "ZXCVBNM"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: u16, code: u16, value: i32) -> Vec<u8> {
+        let mut event = vec![0u8; std::mem::size_of::<libc::input_event>() - 8];
+        event.extend(kind.to_ne_bytes());
+        event.extend(code.to_ne_bytes());
+        event.extend(value.to_ne_bytes());
+        event
+    }
+
+    #[test]
+    fn modifiers_are_tracked() {
+        let mut modifiers = Modifiers::default();
+        let combination = |ctrl, shift, key| Some(KeyCombination { ctrl, shift, key, ..Default::default() });
+        assert_eq!(modifiers.update(&event(EV_KEY, 24, 1)), combination(false, false, 'O'));
+        assert_eq!(modifiers.update(&event(EV_KEY, 29, 1)), None);
+        assert_eq!(modifiers.update(&event(EV_KEY, 54, 1)), None);
+        assert_eq!(modifiers.update(&event(EV_KEY, 11, 1)), combination(true, true, '0'));
+        // Autorepeats and releases don't trigger bindings
+        assert_eq!(modifiers.update(&event(EV_KEY, 11, 2)), None);
+        assert_eq!(modifiers.update(&event(EV_KEY, 11, 0)), None);
+        assert_eq!(modifiers.update(&event(EV_KEY, 29, 0)), None);
+        assert_eq!(modifiers.update(&event(EV_KEY, 50, 1)), combination(false, true, 'M'));
+        // Other event types, e.g. EV_SYN, and keys without a letter are ignored
+        assert_eq!(modifiers.update(&event(0, 50, 1)), None);
+        assert_eq!(modifiers.update(&event(EV_KEY, 59, 1)), None);
+    }
+
+    #[test]
+    fn bound_keys_are_sent_for_their_display() {
+        let path = std::env::temp_dir().join(format!("oculed-evdev-{}", std::process::id()));
+        let events = [event(EV_KEY, 29, 1), event(EV_KEY, 24, 1), event(EV_KEY, 24, 0), event(EV_KEY, 29, 0), event(EV_KEY, 24, 1)];
+        std::fs::write(&path, events.concat()).unwrap();
+
+        let ctrl_o = KeyCombination { ctrl: true, key: 'O', ..Default::default() };
+        let bindings = Bindings::new(vec![(0, ctrl_o, UserInput::NextScreen), (1, ctrl_o, UserInput::Quit)]);
+        let (tx, rx) = std::sync::mpsc::channel();
+        EvdevInput::new(path.clone(), bindings).start(tx).unwrap();
+        let received = rx.iter().collect::<Vec<_>>();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(received, vec![(InputOrigin::Binding(0), UserInput::NextScreen), (InputOrigin::Binding(1), UserInput::Quit)]);
+    }
+}
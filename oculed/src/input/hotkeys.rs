@@ -0,0 +1,54 @@
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Input::KeyboardAndMouse::{RegisterHotKey, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN};
+use windows::Win32::UI::WindowsAndMessaging::{GetMessageW, MSG, WM_HOTKEY};
+
+use crate::{InputOrigin, UserInput};
+use super::{Bindings, InputSource};
+
+/// Global hotkeys, which work whichever window has focus.
+pub struct HotkeyInput {
+    bindings: Bindings,
+}
+
+impl HotkeyInput {
+    pub fn new(bindings: Bindings) -> Self {
+        Self { bindings }
+    }
+}
+
+impl InputSource for HotkeyInput {
+    fn start(&mut self, tx: std::sync::mpsc::Sender<(InputOrigin, UserInput)>) -> std::io::Result<()> {
+        let bindings = self.bindings.clone();
+        // Hotkeys are posted to the thread that registered them, so both happen on the listening thread
+        std::thread::spawn(move || {
+            let combinations = bindings.combinations().copied().collect::<Vec<_>>();
+            for (id, combination) in combinations.iter().enumerate() {
+                let modifiers = [
+                    (combination.ctrl, MOD_CONTROL),
+                    (combination.alt, MOD_ALT),
+                    (combination.shift, MOD_SHIFT),
+                    (combination.super_key, MOD_WIN),
+                ].into_iter().filter(|(enabled, _)| *enabled).fold(MOD_NOREPEAT, |mods, (_, modifier)| mods | modifier);
+                // A combination bound on several displays is registered once, and sends to all of them
+                if combinations[..id].contains(combination) {
+                    continue;
+                }
+                if !unsafe { RegisterHotKey(HWND(0), id as i32, modifiers, combination.key as u32) }.as_bool() {
+                    println!("Failed to register hotkey: {:?}", std::io::Error::last_os_error());
+                }
+            }
+            let mut msg = MSG::default();
+            while unsafe { GetMessageW(&mut msg, HWND(0), 0, 0) }.as_bool() {
+                if msg.message != WM_HOTKEY {
+                    continue;
+                }
+                if let Some(combination) = combinations.get(msg.wParam.0) {
+                    if !bindings.send(combination, &tx) {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+//! The places [UserInput] comes from.
+
+mod network;
+mod terminal;
+#[cfg(target_os = "linux")]
+mod evdev;
+#[cfg(windows)]
+mod hotkeys;
+
+pub use network::{NetworkInput, Press};
+pub use terminal::TerminalInput;
+#[cfg(target_os = "linux")]
+pub use evdev::EvdevInput;
+#[cfg(windows)]
+pub use hotkeys::HotkeyInput;
+
+use crate::config::KeyCombination;
+use crate::{InputOrigin, UserInput};
+
+/// Something that produces [UserInput]s, like a keyboard or the displays on the network.
+pub trait InputSource {
+    /// Starts reading input on a thread of its own, sending it to `tx`. Sources that change global state, like
+    /// the terminal's mode, restore it when dropped.
+    fn start(&mut self, tx: std::sync::mpsc::Sender<(InputOrigin, UserInput)>) -> std::io::Result<()>;
+}
+
+/// Key combinations and what they do, for keyboard-like sources.
+#[derive(Clone, Default, Debug)]
+pub struct Bindings {
+    /// The display each binding is for, by index
+    bindings: Vec<(usize, KeyCombination, UserInput)>,
+}
+
+impl Bindings {
+    pub fn new(bindings: Vec<(usize, KeyCombination, UserInput)>) -> Self {
+        Self { bindings }
+    }
+
+    #[cfg(windows)]
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    #[cfg(windows)]
+    pub fn combinations(&self) -> impl Iterator<Item = &KeyCombination> {
+        self.bindings.iter().map(|(_, combination, _)| combination)
+    }
+
+    /// Sends the inputs bound to `combination`, one for every display it is bound on. Returns false if the
+    /// receiver is gone.
+    pub fn send(&self, combination: &KeyCombination, tx: &std::sync::mpsc::Sender<(InputOrigin, UserInput)>) -> bool {
+        self.bindings.iter()
+            .filter(|(_, bound, _)| bound == combination)
+            .all(|(display, _, action)| tx.send((InputOrigin::Binding(*display), action.clone())).is_ok())
+    }
+}
@@ -0,0 +1,227 @@
+//! Input sent by displays over the network.
+//!
+//! Every packet starts with its version. Version 1 packets are the two bytes `0x33, action`, where action is
+//! 0 - next screen, 1 - previous screen, 2 - screensaver on, 3 - screensaver off or 4 - quit. Version 2 packets
+//! are `0x34, event, payload`:
+//!
+//! | event | payload                                  | meaning                                   |
+//! |-------|------------------------------------------|-------------------------------------------|
+//! | 0     | action u8                                | an action, as in version 1                |
+//! | 1     | delta i8                                 | a rotary encoder turned `delta` steps     |
+//! | 2     | code u16 little endian, [Press] u8       | a key, mapped to an action by the config  |
+//! | 3     | index u8                                 | jump to the screen at `index`             |
+//! | 4     | name, utf-8                              | jump to the screen called `name`          |
+//!
+//! Packets that can't be parsed are reported, together with the reason.
+
+use std::net::UdpSocket;
+
+use serde::Deserialize;
+
+use crate::config::KeyConfig;
+use crate::{InputOrigin, ScreenRef, UserInput};
+use super::InputSource;
+
+const VERSION_1: u8 = 0x33;
+const VERSION_2: u8 = 0x34;
+
+const EVENT_ACTION: u8 = 0;
+const EVENT_ROTARY: u8 = 1;
+const EVENT_KEY: u8 = 2;
+const EVENT_SCREEN_INDEX: u8 = 3;
+const EVENT_SCREEN_NAME: u8 = 4;
+
+/// How a key was pressed.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Press {
+    #[default]
+    Short,
+    Long,
+    Double,
+}
+
+/// Something a display sent.
+#[derive(PartialEq, Debug)]
+pub enum Event {
+    Input(UserInput),
+    Key { code: u16, press: Press },
+}
+
+/// Why a packet couldn't be parsed.
+#[derive(PartialEq, Debug)]
+pub enum PacketError {
+    Empty,
+    UnknownVersion(u8),
+    UnknownEvent(u8),
+    UnknownAction(u8),
+    UnknownPress(u8),
+    /// The packet is shorter than its event needs
+    Truncated,
+    /// The packet is longer than its event needs
+    TrailingBytes,
+    InvalidName,
+}
+
+/// Receives button presses from displays, tagged with the address of the display that sent them.
+pub struct NetworkInput {
+    port: u16,
+    /// Maps the keys displays send to actions
+    keys: Vec<KeyConfig>,
+}
+
+impl NetworkInput {
+    pub fn new(port: u16, keys: Vec<KeyConfig>) -> Self {
+        Self { port, keys }
+    }
+}
+
+impl InputSource for NetworkInput {
+    fn start(&mut self, tx: std::sync::mpsc::Sender<(InputOrigin, UserInput)>) -> std::io::Result<()> {
+        let socket = UdpSocket::bind(("0.0.0.0", self.port))?;
+        let keys = self.keys.clone();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 255];
+            loop {
+                let Ok((len, sender)) = socket.recv_from(&mut buffer) else { continue; };
+                let input = match parse_packet(&buffer[..len]) {
+                    Ok(Event::Input(input)) => input,
+                    Ok(Event::Key { code, press }) => {
+                        match keys.iter().find(|key| key.code == code && key.press == press) {
+                            Some(key) => key.action.clone(),
+                            None => {
+                                println!("No action for key {} ({:?} press) from {}", code, press, sender);
+                                continue;
+                            },
+                        }
+                    },
+                    Err(e) => {
+                        println!("Malformed input packet from {}: {:?}", sender, e);
+                        continue;
+                    },
+                };
+                if tx.send((InputOrigin::Network(sender.ip()), input)).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Parses a packet of either version.
+pub fn parse_packet(packet: &[u8]) -> Result<Event, PacketError> {
+    match packet {
+        [] => Err(PacketError::Empty),
+        [VERSION_1, action] => Ok(Event::Input(parse_action(*action)?)),
+        [VERSION_1] => Err(PacketError::Truncated),
+        [VERSION_1, ..] => Err(PacketError::TrailingBytes),
+        [VERSION_2, event, payload @ ..] => parse_event(*event, payload),
+        [VERSION_2] => Err(PacketError::Truncated),
+        [version, ..] => Err(PacketError::UnknownVersion(*version)),
+    }
+}
+
+fn parse_event(event: u8, payload: &[u8]) -> Result<Event, PacketError> {
+    let input = match event {
+        EVENT_ACTION => parse_action(exactly::<1>(payload)?[0])?,
+        EVENT_ROTARY => UserInput::MoveScreens(exactly::<1>(payload)?[0] as i8 as i32),
+        EVENT_KEY => {
+            let [code_low, code_high, press] = *exactly::<3>(payload)?;
+            let press = match press {
+                0 => Press::Short,
+                1 => Press::Long,
+                2 => Press::Double,
+                _ => return Err(PacketError::UnknownPress(press)),
+            };
+            return Ok(Event::Key { code: u16::from_le_bytes([code_low, code_high]), press });
+        },
+        EVENT_SCREEN_INDEX => UserInput::ShowScreen(ScreenRef::Index(exactly::<1>(payload)?[0] as usize)),
+        EVENT_SCREEN_NAME => {
+            if payload.is_empty() {
+                return Err(PacketError::Truncated);
+            }
+            let name = std::str::from_utf8(payload).map_err(|_| PacketError::InvalidName)?;
+            UserInput::ShowScreen(ScreenRef::Name(name.to_string()))
+        },
+        _ => return Err(PacketError::UnknownEvent(event)),
+    };
+    Ok(Event::Input(input))
+}
+
+/// Maps a version 1 action code to its input.
+fn parse_action(action: u8) -> Result<UserInput, PacketError> {
+    Ok(match action {
+        0 => UserInput::NextScreen,
+        1 => UserInput::PrevScreen,
+        2 => UserInput::ScreensaverOn,
+        3 => UserInput::ScreensaverOff,
+        4 => UserInput::Quit,
+        _ => return Err(PacketError::UnknownAction(action)),
+    })
+}
+
+fn exactly<const N: usize>(payload: &[u8]) -> Result<&[u8; N], PacketError> {
+    match payload.len().cmp(&N) {
+        std::cmp::Ordering::Less => Err(PacketError::Truncated),
+        std::cmp::Ordering::Greater => Err(PacketError::TrailingBytes),
+        std::cmp::Ordering::Equal => Ok(payload.try_into().unwrap()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_packets_are_actions() {
+        assert_eq!(parse_packet(&[0x33, 0]), Ok(Event::Input(UserInput::NextScreen)));
+        assert_eq!(parse_packet(&[0x33, 4]), Ok(Event::Input(UserInput::Quit)));
+        assert_eq!(parse_packet(&[0x33, 5]), Err(PacketError::UnknownAction(5)));
+    }
+
+    #[test]
+    fn version_2_events_are_parsed() {
+        assert_eq!(parse_packet(&[0x34, 0, 2]), Ok(Event::Input(UserInput::ScreensaverOn)));
+        assert_eq!(parse_packet(&[0x34, 1, 3]), Ok(Event::Input(UserInput::MoveScreens(3))));
+        assert_eq!(parse_packet(&[0x34, 1, 0xfe]), Ok(Event::Input(UserInput::MoveScreens(-2))));
+        assert_eq!(parse_packet(&[0x34, 2, 0x01, 0x02, 1]), Ok(Event::Key { code: 0x0201, press: Press::Long }));
+        assert_eq!(parse_packet(&[0x34, 2, 7, 0, 2]), Ok(Event::Key { code: 7, press: Press::Double }));
+        assert_eq!(parse_packet(&[0x34, 3, 2]), Ok(Event::Input(UserInput::ShowScreen(ScreenRef::Index(2)))));
+        assert_eq!(parse_packet(b"\x34\x04media"), Ok(Event::Input(UserInput::ShowScreen(ScreenRef::Name("media".to_string())))));
+    }
+
+    #[test]
+    fn malformed_packets_are_reported() {
+        assert_eq!(parse_packet(&[]), Err(PacketError::Empty));
+        assert_eq!(parse_packet(&[0x12, 0]), Err(PacketError::UnknownVersion(0x12)));
+        assert_eq!(parse_packet(&[0x33]), Err(PacketError::Truncated));
+        assert_eq!(parse_packet(&[0x33, 0, 0]), Err(PacketError::TrailingBytes));
+        assert_eq!(parse_packet(&[0x34]), Err(PacketError::Truncated));
+        assert_eq!(parse_packet(&[0x34, 9]), Err(PacketError::UnknownEvent(9)));
+        assert_eq!(parse_packet(&[0x34, 1]), Err(PacketError::Truncated));
+        assert_eq!(parse_packet(&[0x34, 2, 7, 0]), Err(PacketError::Truncated));
+        assert_eq!(parse_packet(&[0x34, 2, 7, 0, 3]), Err(PacketError::UnknownPress(3)));
+        assert_eq!(parse_packet(&[0x34, 3, 1, 1]), Err(PacketError::TrailingBytes));
+        assert_eq!(parse_packet(&[0x34, 4]), Err(PacketError::Truncated));
+        assert_eq!(parse_packet(&[0x34, 4, 0xff]), Err(PacketError::InvalidName));
+    }
+
+    #[test]
+    fn packets_are_received_from_the_network() {
+        let port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let keys = vec![KeyConfig { code: 7, press: Press::Long, action: UserInput::ScreensaverOn }];
+        NetworkInput::new(port, keys).start(tx).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for packet in [&[0x34, 2, 7, 0, 0][..], &[0x34, 0], &[0x34, 2, 7, 0, 1], &[0x33, 1]] {
+            socket.send_to(packet, ("127.0.0.1", port)).unwrap();
+        }
+        let source = InputOrigin::Network("127.0.0.1".parse().unwrap());
+        let timeout = std::time::Duration::from_secs(1);
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), (source, UserInput::ScreensaverOn));
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), (source, UserInput::PrevScreen));
+    }
+}
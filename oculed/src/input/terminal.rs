@@ -0,0 +1,122 @@
+use std::io::Read;
+use crate::{InputOrigin, UserInput};
+use super::InputSource;
+
+/// Reads key presses from stdin. Intended to be used with [crate::output::TerminalOutput], for developing
+/// without a display attached.
+///
+/// Keys: `n`/right arrow - next screen, `p`/left arrow - previous screen, `s` - screensaver on,
+/// `w` - screensaver off, `q`/ctrl+c - quit.
+///
+/// The terminal is switched to unbuffered input once started, until this is dropped.
+#[derive(Default)]
+pub struct TerminalInput {
+    raw_mode: Option<RawModeGuard>,
+}
+
+impl InputSource for TerminalInput {
+    fn start(&mut self, tx: std::sync::mpsc::Sender<(InputOrigin, UserInput)>) -> std::io::Result<()> {
+        self.raw_mode = Some(RawModeGuard::enable()?);
+        std::thread::spawn(move || read_keys(tx));
+        Ok(())
+    }
+}
+
+fn read_keys(tx: std::sync::mpsc::Sender<(InputOrigin, UserInput)>) {
+    let mut stdin = std::io::stdin();
+    let mut buffer = [0u8; 64];
+    loop {
+        match stdin.read(&mut buffer) {
+            Ok(0) => return,
+            Ok(len) => {
+                for input in parse_keys(&buffer[..len]) {
+                    if tx.send((InputOrigin::Terminal, input)).is_err() {
+                        return;
+                    }
+                }
+            },
+            Err(e) => {
+                println!("Failed to read from stdin: {:?}", e);
+                return;
+            },
+        }
+    }
+}
+
+/// Maps the bytes read from a terminal to inputs, ignoring unknown keys.
+fn parse_keys(bytes: &[u8]) -> Vec<UserInput> {
+    let mut inputs = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let input = match &bytes[i..] {
+            [0x1b, b'[', b'C', ..] => { i += 2; Some(UserInput::NextScreen) },
+            [0x1b, b'[', b'D', ..] => { i += 2; Some(UserInput::PrevScreen) },
+            [b'n', ..] => Some(UserInput::NextScreen),
+            [b'p', ..] => Some(UserInput::PrevScreen),
+            [b's', ..] => Some(UserInput::ScreensaverOn),
+            [b'w', ..] => Some(UserInput::ScreensaverOff),
+            [b'q', ..] | [0x03, ..] => Some(UserInput::Quit),
+            _ => None,
+        };
+        inputs.extend(input);
+        i += 1;
+    }
+    inputs
+}
+
+/// Disables line buffering and echo on the terminal while alive.
+struct RawModeGuard {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl RawModeGuard {
+    #[cfg(unix)]
+    fn enable() -> std::io::Result<Self> {
+        // Not a terminal (e.g. piped input), there is nothing to change
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } == 0 {
+            return Ok(Self { original: None });
+        }
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut raw = original;
+        // ISIG is cleared so ctrl+c reaches us as a key, letting us quit cleanly and restore the terminal
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self { original: Some(original) })
+    }
+
+    /// Windows consoles are left line buffered, so keys must be followed by enter.
+    #[cfg(not(unix))]
+    fn enable() -> std::io::Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = self.original {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &original) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_and_arrows_are_mapped() {
+        let inputs = parse_keys(b"n\x1b[Cp\x1b[Dxsw\x1b[Aq\x03");
+        let expected = [
+            UserInput::NextScreen, UserInput::NextScreen, UserInput::PrevScreen, UserInput::PrevScreen,
+            UserInput::ScreensaverOn, UserInput::ScreensaverOff, UserInput::Quit, UserInput::Quit,
+        ];
+        assert_eq!(inputs, expected);
+    }
+}
@@ -10,107 +10,330 @@ mod overlays;
 mod fonts;
 mod media_provider;
 
-mod network_receiver;
+mod input;
+#[cfg(unix)]
+mod control;
+#[cfg(feature = "http")]
+mod http_server;
+mod discovery;
+mod display;
+mod config;
 
+use std::net::{IpAddr, ToSocketAddrs};
 use std::rc::Rc;
 use std::sync::{mpsc, Mutex};
+use std::time::Duration;
 
-use graphics::components::{self, Drawable, Bounds};
+use anyhow::Context;
+
+use graphics::components::{self, Drawable};
 use graphics::rendering;
 use output::RenderTarget;
+use config::{Config, OutputConfig, ScreenKind};
 
-mod config {
-    pub const ADDRESS: &str = "192.168.1.6:4435";
-    pub const DISPLAY_WIDTH: usize = 128;
-    pub const DISPLAY_HEIGHT: usize = 64;
-}
+/// How many times to probe for a display without an address before giving up, a second apart
+const DISCOVERY_ATTEMPTS: u32 = 10;
+
+fn main() -> anyhow::Result<()> {
+    let args = parse_args(std::env::args().skip(1))?;
+    let config = Config::load(args.config_path.as_deref())?;
+    let mut setups = config.all_displays();
 
-fn main() -> std::io::Result<()> {
-    let (tx, rx) = mpsc::channel::<UserInput>();
-
-    let tx2 = tx.clone();
-    std::thread::spawn(move || {
-        let mut hk = hotkey::Listener::new();
-        {
-            let tx = tx.clone();
-            hk.register_hotkey(hotkey::modifiers::CONTROL | hotkey::modifiers::ALT | hotkey::modifiers::SHIFT,
-                'O' as u32,
-                move || { tx.send(UserInput::NextScreen).unwrap(); }).unwrap();
+    // Command line options apply to the main display
+    let main_output = &mut setups[0].output;
+    let dithering = main_output.dithering();
+    if let Some(style) = args.terminal {
+        *main_output = OutputConfig::Terminal { style, dithering };
+    } else if let Some(port) = args.serial_port {
+        let layout = match main_output {
+            OutputConfig::Udp { layout, .. } | OutputConfig::Serial { layout, .. } => *layout,
+            OutputConfig::Terminal { .. } => Default::default(),
+        };
+        *main_output = OutputConfig::Serial { port, baud_rate: args.baud_rate.unwrap_or(config::DEFAULT_BAUD_RATE), dithering, layout };
+    } else if let (Some(baud), OutputConfig::Serial { baud_rate, .. }) = (args.baud_rate, &mut *main_output) {
+        *baud_rate = baud;
+    } else if let Some(name) = args.device {
+        if !matches!(main_output, OutputConfig::Udp { .. }) {
+            *main_output = OutputConfig::default();
         }
-        {
-            let tx = tx.clone();
-            hk.register_hotkey(hotkey::modifiers::CONTROL | hotkey::modifiers::ALT | hotkey::modifiers::SHIFT,
-                'I' as u32,
-                move || { tx.send(UserInput::PrevScreen).unwrap(); }).unwrap();
+        if let OutputConfig::Udp { address, device, dithering: udp_dithering, .. } = main_output {
+            *address = None;
+            *device = Some(name);
+            *udp_dithering = dithering;
         }
-        {
-            let tx = tx.clone();
-            hk.register_hotkey(hotkey::modifiers::CONTROL | hotkey::modifiers::ALT | hotkey::modifiers::SHIFT,
-                'P' as u32,
-                move || { tx.send(UserInput::Quit).unwrap(); }).unwrap();
+    }
+
+    // Find the displays before creating the screens, since they decide the resolution
+    for (i, setup) in setups.iter_mut().enumerate() {
+        let OutputConfig::Udp { address: address @ None, device, discovery_address, protocol, .. } = &mut setup.output else { continue; };
+        let found = find_display(discovery_address, device.as_deref())?;
+        *address = Some(found.address.to_string());
+        protocol.get_or_insert(found.protocol);
+        // Displays that don't know their size report 0x0
+        let size = (found.width, found.height);
+        if size != (setup.width, setup.height) && size != (0, 0) {
+            setup.use_reported_size(i, size.0, size.1)
+                .with_context(|| format!("{} reported a resolution of {}x{} that can't be used", found.name, size.0, size.1))?;
+            println!("Using the resolution of {}, {}x{}, instead of the configured one", setup.name, size.0, size.1);
         }
-        hk.listen();
-    });
+    }
 
-    network_receiver::start(tx2);
+    let (tx, rx) = mpsc::channel::<(InputOrigin, UserInput)>();
 
-    let media_provider = Rc::new(Mutex::new(media_provider::PollingMediaProvider::new()));
-    media_provider.lock().unwrap().update_media_info();
+    let terminal_display = setups.iter().position(|setup| matches!(setup.output, OutputConfig::Terminal { .. }));
+    // Kept until the end, so the terminal's mode is restored when quitting
+    let mut sources: Vec<Box<dyn input::InputSource>> = vec![Box::new(input::NetworkInput::new(config.input.network_port, config.input.keys.clone()))];
+    if terminal_display.is_some() {
+        sources.push(Box::<input::TerminalInput>::default());
+    }
+    // The bindings were validated when loading the config
+    let bindings = |include_terminal: bool| input::Bindings::new(setups.iter().enumerate()
+        .filter(|(i, _)| include_terminal || Some(*i) != terminal_display)
+        .flat_map(|(i, setup)| setup.bindings.iter()
+            .map(move |binding| (i, config::KeyCombination::parse(&binding.key).unwrap(), binding.action.clone())))
+        .collect());
+    #[cfg(target_os = "linux")]
+    for device in &config.input.devices {
+        sources.push(Box::new(input::EvdevInput::new(device.path.clone(), bindings(true)).with_grab(device.grab)));
+    }
+    #[cfg(not(target_os = "linux"))]
+    if !config.input.devices.is_empty() {
+        println!("Input devices are only supported on linux, ignoring them");
+    }
+    // Hotkeys need a desktop session, which a terminal-only setup may not have
+    #[cfg(windows)]
+    if !bindings(false).is_empty() {
+        sources.push(Box::new(input::HotkeyInput::new(bindings(false))));
+    }
+    for source in sources.iter_mut() {
+        source.start(tx.clone())?;
+    }
 
-    let mut clock = screens::clock::ClockScreen::new();
-    let mut media = screens::media::MediaScreen::new(Rc::clone(&media_provider));
+    let media_provider = Rc::new(Mutex::new(media_provider::SystemMediaProvider::new()));
+    media_provider.lock().unwrap().update_media_info();
     let stats_monitor = performance_monitor::PerformanceMonitor::new();
-    let mut perf_mem = screens::performance::PerformanceWithMemoryScreen::new(stats_monitor.statistics());
-    let mut perf_temp = screens::performance::PerformanceWithTemperatureScreen::new(stats_monitor.statistics());
-    let mut stickfight = screens::stickfight::StickFightScreen::new(config::DISPLAY_WIDTH, config::DISPLAY_HEIGHT).unwrap();
-
-    let mut media_overlay = overlays::MediaOverlay::new(Rc::clone(&media_provider));
-    let mut screensaver = overlays::ScreensaverOverlay::new();
-
-    let mut screens = screens::ScreenCollection::new(
-        vec![
-            &mut clock,
-            &mut media,
-            &mut perf_mem,
-            &mut perf_temp,
-            &mut stickfight
-        ],
-    );
-    let mut output = output::UdpOutput{ address: config::ADDRESS, previous: rendering::BinaryBitmap{ width: 0, height: 0, buffer: Vec::new() } };
+
+    let mut screen_lists = setups.iter()
+        .map(|setup| create_screens(setup, &media_provider, &stats_monitor))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let overlay_config = &config.overlays;
+    let mut recordings = Some(args.recordings);
+    let mut displays = Vec::new();
+    for (setup, screen_list) in setups.iter().zip(screen_lists.iter_mut()) {
+        let screens = screens::ScreenCollection::new(
+            screen_list.iter_mut().map(|screen| screen.as_mut() as &mut dyn screens::Screen).collect(),
+        );
+        let media_overlay = overlay_config.media.enabled.then(|| Box::new(overlays::MediaOverlay::new(
+            Rc::clone(&media_provider),
+            Duration::from_millis(overlay_config.media.play_pause_ms),
+            Duration::from_millis(overlay_config.media.track_info_ms),
+        )) as Box<dyn Drawable>);
+        let mut screensaver = components::Layer::new(overlays::ScreensaverOverlay::new(overlay_config.screensaver.fps));
+        if let Some(dithering) = overlay_config.screensaver.dithering {
+            screensaver = screensaver.with_dithering(dithering.into());
+        }
+
+        let (target, ip): (Box<dyn RenderTarget + '_>, _) = match &setup.output {
+            OutputConfig::Terminal { style, .. } => (Box::new(output::TerminalOutput::stdout(*style)), None),
+            OutputConfig::Serial { port, baud_rate, layout, .. } => {
+                (Box::new(output::SerialOutput::open(port, *baud_rate)?.with_layout((*layout).into())), None)
+            },
+            OutputConfig::Udp { address, layout, keyframe_interval, protocol, compression, .. } => {
+                let address = address.as_deref().expect("The display was discovered above");
+                let output = output::UdpOutput::open(address)?
+                    .with_layout((*layout).into())
+                    .with_protocol(protocol.unwrap_or_default())
+                    .with_compression(*compression)
+                    .with_keyframe_interval(*keyframe_interval);
+                let ip = address.to_socket_addrs().ok().and_then(|mut addresses| addresses.next()).map(|address| address.ip());
+                (Box::new(output), ip)
+            },
+        };
+        let mut display = display::Display::new(setup.name.clone(), setup.width, setup.height, screens, screensaver, media_overlay, ip)
+            .with_screen_names(setup.screens.iter().map(|screen| screen.name().to_string()).collect());
+        let dithering = setup.output.dithering().into();
+        display.add_output(target, dithering);
+        // Recordings show the same pixels as the main display
+        for recording in recordings.take().into_iter().flatten() {
+            display.add_output(recording, dithering);
+        }
+        displays.push(display);
+    }
+
+    #[cfg(feature = "http")]
+    let frames = match config.http.enabled {
+        // Dithered like the display's own output, so the preview shows what the display shows
+        true => displays.iter_mut().zip(setups.iter()).map(|(display, setup)| {
+            let slot = output::FrameSlot::default();
+            display.add_output(Box::new(output::HttpOutput::new(slot.clone())), setup.output.dithering().into());
+            slot
+        }).collect(),
+        false => Vec::new(),
+    };
+
+    let display_state = display::SharedState::default();
+    *display_state.lock().unwrap() = displays.iter().map(display::Display::state).collect();
+    #[cfg(feature = "http")]
+    if config.http.enabled {
+        http_server::start(&config.http.address, tx.clone(), std::sync::Arc::clone(&display_state), frames)?;
+        println!("Serving the HTTP API on http://{}", config.http.address);
+    }
+    #[cfg(not(feature = "http"))]
+    if config.http.enabled {
+        println!("The HTTP API is enabled, but OcuLED was built without the http feature");
+    }
+    #[cfg(unix)]
+    let _control_socket = match config.control.enabled {
+        true => {
+            let path = config.control.socket.clone().unwrap_or_else(control::default_socket_path);
+            Some(control::start(&path, tx, std::sync::Arc::clone(&display_state))?)
+        },
+        false => None,
+    };
+    #[cfg(not(unix))]
+    drop(tx);
 
     let mut last_time = std::time::Instant::now();
-    let mut canvas = rendering::Bitmap::new(config::DISPLAY_WIDTH, config::DISPLAY_HEIGHT);
-    let canvas_bounds = Bounds::cover_bitmap(&canvas);
     loop {
         let elapsed = last_time.elapsed();
         last_time = std::time::Instant::now();
 
         media_provider.lock().unwrap().update_media_info();
-        canvas.clear();
-        let drawables: [&mut dyn Drawable; 3] = [&mut screens, &mut screensaver, &mut media_overlay];
-        for drawable in drawables {
-            drawable.draw(&mut canvas, canvas_bounds, &elapsed);
+        for display in displays.iter_mut() {
+            display.update(&elapsed);
+        }
+        *display_state.lock().unwrap() = displays.iter().map(display::Display::state).collect();
+
+        let Ok((source, input)) = rx.recv_timeout(Duration::from_millis(50)) else { continue; };
+        if input == UserInput::Quit {
+            break Ok(());
+        }
+        let target = match source {
+            InputOrigin::Binding(display) => display,
+            InputOrigin::Terminal => terminal_display.unwrap_or(0),
+            // Input from unknown addresses goes to the main display, like when there was only one
+            InputOrigin::Network(ip) => displays.iter().position(|display| display.ip() == Some(ip)).unwrap_or(0),
+            InputOrigin::Control(display) => display,
+        };
+        displays[target].handle_input(&input);
+    }
+}
+
+/// Creates the screens to show on a display, in order.
+fn create_screens(
+    setup: &config::DisplaySetup,
+    media_provider: &Rc<Mutex<media_provider::SystemMediaProvider>>,
+    stats_monitor: &performance_monitor::PerformanceMonitor,
+) -> anyhow::Result<Vec<Box<dyn screens::Screen>>> {
+    setup.screens.iter().map(|screen_config| -> anyhow::Result<Box<dyn screens::Screen>> {
+        let screen: Box<dyn screens::Screen> = match &screen_config.kind {
+            ScreenKind::Clock { format, date_format } => Box::new(screens::clock::ClockScreen::new(format.clone(), date_format.clone())),
+            ScreenKind::Media {} => Box::new(screens::media::MediaScreen::new(Rc::clone(media_provider))),
+            ScreenKind::PerformanceMemory {} => Box::new(screens::performance::PerformanceWithMemoryScreen::new(stats_monitor.statistics())),
+            ScreenKind::PerformanceTemperature {} => Box::new(screens::performance::PerformanceWithTemperatureScreen::new(stats_monitor.statistics())),
+            ScreenKind::PerformanceHistory {} => Box::new(screens::performance::PerformanceHistoryScreen::new(stats_monitor.history())),
+            ScreenKind::Stickfight { fps } => Box::new(screens::stickfight::StickFightScreen::new(setup.width, setup.height, *fps)?),
+        };
+        Ok(match screen_config.dithering {
+            Some(dithering) => Box::new(components::Layer::new(screen).with_dithering(dithering.into())),
+            None => screen,
+        })
+    }).collect()
+}
+
+/// Discovers the first display to answer, or the one called `name` if set. Gives up after
+/// [DISCOVERY_ATTEMPTS] probes, since displays with older firmware never answer.
+fn find_display(discovery_address: &str, name: Option<&str>) -> anyhow::Result<discovery::Device> {
+    for _ in 0..DISCOVERY_ATTEMPTS {
+        if let Some(device) = discovery::find(discovery_address, name, Duration::from_secs(1))? {
+            println!("Found display {} at {}", device.name, device.address);
+            return Ok(device);
         }
-        if let Err(e) = output.render_bitmap((&canvas).into()) {
-            println!("Failed to send bitmap: {:?}", e);
+        match name {
+            Some(name) => println!("No display called {} answered, retrying", name),
+            None => println!("No display answered, retrying"),
         }
+    }
+    match name {
+        Some(name) => anyhow::bail!("No display called {} answered. Check that it is on, or set output.address in the config file", name),
+        None => anyhow::bail!("No display answered. Displays with older firmware aren't discoverable, set output.address in the config file for those"),
+    }
+}
+
+/// Options given on the command line. These override the values in the config file.
+struct Args {
+    /// Read the config from this file instead of [config::default_path]
+    config_path: Option<std::path::PathBuf>,
+    /// Draw to the terminal instead of the display, and read input from stdin
+    terminal: Option<output::TerminalStyle>,
+    /// Send to a display attached to this serial port instead of over wifi
+    serial_port: Option<String>,
+    baud_rate: Option<u32>,
+    /// Discover the display with this name instead of using the configured address
+    device: Option<String>,
+    /// Additional outputs recording the display
+    recordings: Vec<Box<dyn RenderTarget>>,
+}
 
-        let event = rx.recv_timeout(std::time::Duration::from_millis(50));
-        match event {
-            Ok(UserInput::NextScreen) => screens.next_screen(),
-            Ok(UserInput::PrevScreen) => screens.previous_screen(),
-            Ok(UserInput::ScreensaverOn) => screensaver.show(),
-            Ok(UserInput::ScreensaverOff) => screensaver.hide(),
-            Ok(UserInput::Quit) => break Ok(()),
-            Err(_) => {},
+/// Parses command line arguments.
+///
+/// `--config <file>` reads the config from `file`.
+/// `--terminal` draws to the terminal using half blocks, `--terminal-braille` using braille characters.
+/// `--serial <port>` sends to a display attached to a serial port, at the baud rate given by `--baud <rate>`.
+/// `--device <name>` sends to the display called `name`, found on the local network.
+/// `--record-gif <file>` records an animated gif, `--record-png <directory>` writes each frame to a png file.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> std::io::Result<Args> {
+    let mut parsed = Args { config_path: None, terminal: None, serial_port: None, baud_rate: None, device: None, recordings: Vec::new() };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Missing value after {}", arg)));
+        match arg.as_str() {
+            "--config" => parsed.config_path = Some(value()?.into()),
+            "--terminal" => parsed.terminal = Some(output::TerminalStyle::HalfBlocks),
+            "--terminal-braille" => parsed.terminal = Some(output::TerminalStyle::Braille),
+            "--serial" => parsed.serial_port = Some(value()?),
+            "--baud" => parsed.baud_rate = Some(value()?.parse().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid baud rate: {}", e)))?),
+            "--device" => parsed.device = Some(value()?),
+            "--record-gif" => parsed.recordings.push(Box::new(output::GifOutput::new(value()?)?)),
+            "--record-png" => parsed.recordings.push(Box::new(output::PngSequenceOutput::new(value()?)?)),
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Unknown argument {}", arg))),
         }
     }
+    Ok(parsed)
 }
 
+#[derive(PartialEq, Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum UserInput {
     NextScreen,
     PrevScreen,
+    /// Move this many screens forward, or back if negative, e.g. when a rotary encoder is turned
+    MoveScreens(i32),
+    ShowScreen(ScreenRef),
     ScreensaverOn,
     ScreensaverOff,
+    /// Show `text` over the screens for a while
+    Notify { text: String, duration_ms: u64 },
     Quit,
 }
+
+/// A screen on a display, by its position or its name (see [config::ScreenConfig::name]).
+#[derive(PartialEq, Debug, Clone, serde::Deserialize)]
+#[serde(untagged)]
+pub enum ScreenRef {
+    Index(usize),
+    Name(String),
+}
+
+/// Where a [UserInput] came from, which decides the display it is for.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InputOrigin {
+    /// A key combination bound for the display with this index, see [input::Bindings]
+    Binding(usize),
+    /// A key pressed in the terminal, see [input::TerminalInput]
+    Terminal,
+    /// A display on the network, see [input::NetworkInput]
+    Network(IpAddr),
+    /// The control socket or the HTTP API, for the display with this index
+    Control(usize),
+}
@@ -0,0 +1,80 @@
+use std::rc::Rc;
+use std::sync::Mutex;
+
+#[cfg(windows)]
+mod smtc;
+#[cfg(target_os = "linux")]
+mod mpris;
+
+#[cfg(windows)]
+pub use smtc::PollingMediaProvider;
+#[cfg(target_os = "linux")]
+pub use mpris::MprisMediaProvider;
+
+/// The media provider to use on the current platform.
+#[cfg(windows)]
+pub type SystemMediaProvider = PollingMediaProvider;
+/// The media provider to use on the current platform.
+#[cfg(target_os = "linux")]
+pub type SystemMediaProvider = MprisMediaProvider;
+
+/// Provides information about media being played on the computer.
+pub trait MediaProvider {
+    /// The name of the current track, if available.
+    fn track_name(&self) -> Option<String>;
+    /// Checks whether the current track name matches `other`.
+    ///
+    /// This is useful to check whether the value has changed,
+    /// and avoid unnecessarily allocating [String]s.
+    fn track_name_is(&self, other: &Option<String>) -> bool;
+    /// The artist of the current track, if available.
+    fn artist(&self) -> Option<String>;
+    /// Checks whether the current artist matches `other`.
+    ///
+    /// This is useful to check whether the value has changed,
+    /// and avoid unnecessarily allocating [String]s.
+    fn artist_is(&self, other: &Option<String>) -> bool;
+    /// Whether media is currently playing ([false]) or paused ([true]).
+    fn paused(&self) -> Option<bool>;
+}
+
+impl<M: MediaProvider> MediaProvider for Rc<Mutex<M>> {
+    fn track_name(&self) -> Option<String> {
+        let provider = self.lock().unwrap();
+        provider.track_name()
+    }
+    fn track_name_is(&self, other: &Option<String>) -> bool {
+        let provider = self.lock().unwrap();
+        provider.track_name_is(other)
+    }
+    fn artist(&self) -> Option<String> {
+        let provider = self.lock().unwrap();
+        provider.artist()
+    }
+    fn artist_is(&self, other: &Option<String>) -> bool {
+        let provider = self.lock().unwrap();
+        provider.artist_is(other)
+    }
+    fn paused(&self) -> Option<bool> {
+        let provider = self.lock().unwrap();
+        provider.paused()
+    }
+}
+
+/// A provider returning fixed values, for tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeMediaProvider {
+    pub track_name: Option<String>,
+    pub artist: Option<String>,
+    pub paused: Option<bool>,
+}
+
+#[cfg(test)]
+impl MediaProvider for FakeMediaProvider {
+    fn track_name(&self) -> Option<String> { self.track_name.clone() }
+    fn track_name_is(&self, other: &Option<String>) -> bool { self.track_name.eq(other) }
+    fn artist(&self) -> Option<String> { self.artist.clone() }
+    fn artist_is(&self, other: &Option<String>) -> bool { self.artist.eq(other) }
+    fn paused(&self) -> Option<bool> { self.paused }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use zbus::blocking::{self, Connection};
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedValue;
+
+use super::MediaProvider;
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+const BUS_NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// A media provider reading from MPRIS players on a D-Bus session bus.
+///
+/// The players are polled on a background thread, [MprisMediaProvider::update_media_info] makes the latest
+/// values visible.
+pub struct MprisMediaProvider {
+    latest: Arc<Mutex<Option<PlayerState>>>,
+    track_name: Option<String>,
+    artist: Option<String>,
+    paused: Option<bool>,
+}
+
+impl MprisMediaProvider {
+    /// Creates a provider for the players on the user's session bus.
+    pub fn new() -> Self {
+        Self::spawn(Connection::session)
+    }
+
+    /// Creates a provider for the players on the bus at `address`, e.g. `unix:path=/tmp/bus`.
+    #[cfg(test)]
+    pub fn with_address(address: &str) -> Self {
+        let address = address.to_string();
+        Self::spawn(move || blocking::connection::Builder::address(address.as_str())?.build())
+    }
+
+    fn spawn<F>(connect: F) -> Self
+        where F: FnOnce() -> zbus::Result<Connection> + Send + 'static {
+        let latest = Arc::new(Mutex::new(None));
+        let weak_latest = Arc::downgrade(&latest);
+        std::thread::spawn(move || {
+            match connect() {
+                Ok(connection) => poll_players(&connection, weak_latest),
+                Err(e) => println!("Failed to connect to D-Bus, media info will be unavailable: {:?}", e),
+            }
+        });
+        Self {
+            latest,
+            track_name: None,
+            artist: None,
+            paused: None,
+        }
+    }
+
+    pub fn update_media_info(&mut self) {
+        let latest = self.latest.lock().unwrap();
+        if let Some(player) = latest.as_ref() {
+            if !self.track_name.eq(&player.title) {
+                self.track_name = player.title.clone();
+            }
+            if !self.artist.eq(&player.artist) {
+                self.artist = player.artist.clone();
+            }
+            self.paused = Some(player.status != PlaybackStatus::Playing);
+        } else {
+            self.track_name = None;
+            self.artist = None;
+            self.paused = None;
+        }
+    }
+}
+
+impl MediaProvider for MprisMediaProvider {
+    fn track_name(&self) -> Option<String> {
+        self.track_name.clone()
+    }
+    fn track_name_is(&self, other: &Option<String>) -> bool {
+        self.track_name.eq(other)
+    }
+    fn artist(&self) -> Option<String> {
+        self.artist.clone()
+    }
+    fn artist_is(&self, other: &Option<String>) -> bool {
+        self.artist.eq(other)
+    }
+    fn paused(&self) -> Option<bool> {
+        self.paused
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct PlayerState {
+    bus_name: String,
+    title: Option<String>,
+    artist: Option<String>,
+    status: PlaybackStatus,
+}
+
+/// Polls all players on the bus until the provider owning `latest` is dropped.
+fn poll_players(connection: &Connection, latest: Weak<Mutex<Option<PlayerState>>>) {
+    let mut active: Option<String> = None;
+    loop {
+        let players = match read_players(connection) {
+            Ok(players) => players,
+            Err(e) => {
+                println!("Failed to read MPRIS players: {:?}", e);
+                Vec::new()
+            },
+        };
+        let selected = pick_active_player(&players, active.as_deref()).cloned();
+        active = selected.as_ref().map(|player| player.bus_name.clone());
+
+        match latest.upgrade() {
+            Some(latest) => *latest.lock().unwrap() = selected,
+            None => return,
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn read_players(connection: &Connection) -> zbus::Result<Vec<PlayerState>> {
+    let dbus = blocking::fdo::DBusProxy::new(connection)?;
+    let mut names = dbus.list_names()?.into_iter()
+        .map(|name| name.to_string())
+        .filter(|name| name.starts_with(BUS_NAME_PREFIX))
+        .collect::<Vec<String>>();
+    names.sort();
+    // Players may disappear between listing and reading them, so errors for single players are ignored
+    Ok(names.into_iter().filter_map(|name| read_player(connection, name).ok()).collect())
+}
+
+fn read_player(connection: &Connection, bus_name: String) -> zbus::Result<PlayerState> {
+    let proxy = blocking::proxy::Builder::<blocking::Proxy>::new(connection)
+        .destination(bus_name.clone())?
+        .path(OBJECT_PATH)?
+        .interface(PLAYER_INTERFACE)?
+        .cache_properties(CacheProperties::No)
+        .build()?;
+    let status = match proxy.get_property::<String>("PlaybackStatus")?.as_str() {
+        "Playing" => PlaybackStatus::Playing,
+        "Paused" => PlaybackStatus::Paused,
+        _ => PlaybackStatus::Stopped,
+    };
+    let metadata = proxy.get_property::<HashMap<String, OwnedValue>>("Metadata").unwrap_or_default();
+    let title = metadata.get("xesam:title")
+        .and_then(|value| String::try_from(value.try_clone().ok()?).ok())
+        .filter(|title| !title.is_empty());
+    let artist = metadata.get("xesam:artist")
+        .and_then(|value| Vec::<String>::try_from(value.try_clone().ok()?).ok())
+        .map(|artists| artists.join(", "))
+        .filter(|artist| !artist.is_empty());
+    Ok(PlayerState { bus_name, title, artist, status })
+}
+
+/// Picks the player to show information from.
+///
+/// Playing players are preferred over paused ones, and paused ones over stopped ones. We stick with the
+/// previously picked player (`previous`) until another one has a better status, so that the display doesn't
+/// jump between several playing players.
+fn pick_active_player<'a>(players: &'a [PlayerState], previous: Option<&str>) -> Option<&'a PlayerState> {
+    let rank = |player: &PlayerState| match player.status {
+        PlaybackStatus::Playing => 0,
+        PlaybackStatus::Paused => 1,
+        PlaybackStatus::Stopped => 2,
+    };
+    let best_rank = players.iter().map(rank).min()?;
+    let candidates = players.iter().filter(|player| rank(player) == best_rank);
+    candidates.clone()
+        .find(|player| Some(player.bus_name.as_str()) == previous)
+        .or_else(|| candidates.clone().next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::Value;
+
+    /// A private dbus-daemon, killed when dropped.
+    struct TestBus {
+        daemon: std::process::Child,
+        address: String,
+        socket_dir: std::path::PathBuf,
+    }
+
+    impl TestBus {
+        /// Starts a bus, or returns [None] if dbus-daemon isn't installed.
+        fn start(name: &str) -> Option<Self> {
+            let socket_dir = std::env::temp_dir().join(format!("oculed-mpris-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&socket_dir).unwrap();
+            let address = format!("unix:path={}", socket_dir.join("bus").display());
+            let daemon = std::process::Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--nopidfile", "--address", &address])
+                .stderr(std::process::Stdio::null())
+                .spawn();
+            let Ok(daemon) = daemon else {
+                eprintln!("dbus-daemon not found, skipping test");
+                return None;
+            };
+            let bus = TestBus { daemon, address, socket_dir };
+            // Wait for the daemon to start listening
+            for _ in 0..100 {
+                if bus.connect().is_ok() {
+                    return Some(bus);
+                }
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            panic!("dbus-daemon did not start");
+        }
+
+        fn connect(&self) -> zbus::Result<Connection> {
+            blocking::connection::Builder::address(self.address.as_str())?.build()
+        }
+
+        /// Registers a fake player on the bus. The player disappears when the returned connection is dropped.
+        fn add_player(&self, name: &str, player: FakePlayer) -> Connection {
+            blocking::connection::Builder::address(self.address.as_str()).unwrap()
+                .name(format!("{}{}", BUS_NAME_PREFIX, name)).unwrap()
+                .serve_at(OBJECT_PATH, player).unwrap()
+                .build().unwrap()
+        }
+    }
+
+    impl Drop for TestBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+            let _ = std::fs::remove_dir_all(&self.socket_dir);
+        }
+    }
+
+    struct FakePlayer {
+        status: &'static str,
+        title: &'static str,
+        artists: Vec<&'static str>,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.status.to_string()
+        }
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let mut metadata = HashMap::new();
+            metadata.insert("xesam:title".to_string(), Value::from(self.title).try_to_owned().unwrap());
+            metadata.insert("xesam:artist".to_string(), Value::from(self.artists.clone()).try_to_owned().unwrap());
+            metadata
+        }
+    }
+
+    fn set_status(player: &Connection, status: &'static str) {
+        let iface = player.object_server().interface::<_, FakePlayer>(OBJECT_PATH).unwrap();
+        iface.get_mut().status = status;
+    }
+
+    /// Updates `provider` until `predicate` holds, panicking if it doesn't within a few seconds.
+    fn wait_for<F: Fn(&MprisMediaProvider) -> bool>(provider: &mut MprisMediaProvider, predicate: F) {
+        for _ in 0..100 {
+            provider.update_media_info();
+            if predicate(provider) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("timed out, provider has track {:?} by {:?}, paused: {:?}", provider.track_name, provider.artist, provider.paused);
+    }
+
+    fn shows(provider: &MprisMediaProvider, title: &str, paused: bool) -> bool {
+        provider.track_name.as_deref() == Some(title) && provider.paused == Some(paused)
+    }
+
+    #[test]
+    fn reads_player_metadata() {
+        let Some(bus) = TestBus::start("metadata") else { return };
+        let mut provider = MprisMediaProvider::with_address(&bus.address);
+        std::thread::sleep(POLL_INTERVAL);
+        provider.update_media_info();
+        assert_eq!(provider.track_name(), None);
+        assert_eq!(provider.paused(), None);
+
+        let _player = bus.add_player("fake", FakePlayer { status: "Paused", title: "Song", artists: vec!["A", "B"] });
+        wait_for(&mut provider, |p| shows(p, "Song", true));
+        assert_eq!(provider.artist(), Some("A, B".to_string()));
+    }
+
+    #[test]
+    fn picks_the_active_player() {
+        let Some(bus) = TestBus::start("active") else { return };
+        let mut provider = MprisMediaProvider::with_address(&bus.address);
+
+        let first = bus.add_player("first", FakePlayer { status: "Paused", title: "First", artists: vec!["A"] });
+        wait_for(&mut provider, |p| shows(p, "First", true));
+        let second = bus.add_player("second", FakePlayer { status: "Playing", title: "Second", artists: vec!["B"] });
+        wait_for(&mut provider, |p| shows(p, "Second", false));
+
+        // Both playing, we should stay with the one we picked
+        set_status(&first, "Playing");
+        std::thread::sleep(POLL_INTERVAL * 2);
+        wait_for(&mut provider, |p| shows(p, "Second", false));
+
+        set_status(&second, "Paused");
+        wait_for(&mut provider, |p| shows(p, "First", false));
+
+        drop(first);
+        wait_for(&mut provider, |p| shows(p, "Second", true));
+        drop(second);
+        wait_for(&mut provider, |p| p.track_name.is_none() && p.paused.is_none());
+    }
+
+    #[test]
+    fn picking_prefers_playing_players() {
+        let player = |name: &str, status| PlayerState { bus_name: name.to_string(), title: None, artist: None, status };
+        let players = [
+            player("a", PlaybackStatus::Stopped),
+            player("b", PlaybackStatus::Paused),
+            player("c", PlaybackStatus::Playing),
+            player("d", PlaybackStatus::Playing),
+        ];
+        assert_eq!(pick_active_player(&players, None).unwrap().bus_name, "c");
+        assert_eq!(pick_active_player(&players, Some("d")).unwrap().bus_name, "d");
+        assert_eq!(pick_active_player(&players, Some("b")).unwrap().bus_name, "c");
+        assert_eq!(pick_active_player(&players[..2], Some("a")).unwrap().bus_name, "b");
+        assert_eq!(pick_active_player(&[], Some("a")), None);
+    }
+}
@@ -1,27 +1,6 @@
-use std::rc::Rc;
-use std::sync::Mutex;
-
 use windows::Media::Control;
 
-/// Provides information about media being played on the computer.
-pub trait MediaProvider {
-    /// The name of the current track, if available.
-    fn track_name(&self) -> Option<String>;
-    /// Checks whether the current track name matches `other`.
-    ///
-    /// This is useful to check whether the value has changed,
-    /// and avoid unnecessarily allocating [String]s.
-    fn track_name_is(&self, other: &Option<String>) -> bool;
-    /// The artist of the current track, if available.
-    fn artist(&self) -> Option<String>;
-    /// Checks whether the current artist matches `other`.
-    ///
-    /// This is useful to check whether the value has changed,
-    /// and avoid unnecessarily allocating [String]s.
-    fn artist_is(&self, other: &Option<String>) -> bool;
-    /// Whether media is currently playing ([false]) or paused ([true]).
-    fn paused(&self) -> Option<bool>;
-}
+use super::MediaProvider;
 
 /// A media provider that must be explicitly told to update its data.
 pub struct PollingMediaProvider {
@@ -74,25 +53,3 @@ impl MediaProvider for PollingMediaProvider {
         self.paused
     }
 }
-impl<M: MediaProvider> MediaProvider for Rc<Mutex<M>> {
-    fn track_name(&self) -> Option<String> {
-        let provider = self.lock().unwrap();
-        provider.track_name()
-    }
-    fn track_name_is(&self, other: &Option<String>) -> bool {
-        let provider = self.lock().unwrap();
-        provider.track_name_is(other)
-    }
-    fn artist(&self) -> Option<String> {
-        let provider = self.lock().unwrap();
-        provider.artist()
-    }
-    fn artist_is(&self, other: &Option<String>) -> bool {
-        let provider = self.lock().unwrap();
-        provider.artist_is(other)
-    }
-    fn paused(&self) -> Option<bool> {
-        let provider = self.lock().unwrap();
-        provider.paused()
-    }
-}
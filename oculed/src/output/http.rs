@@ -0,0 +1,95 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::rendering;
+use super::RenderTarget;
+
+/// The latest frame of a display, shared between its [HttpOutput] and the HTTP server that serves it.
+#[derive(Clone, Default)]
+pub struct FrameSlot {
+    frame: Arc<(Mutex<NumberedFrame>, Condvar)>,
+}
+
+#[derive(Default)]
+struct NumberedFrame {
+    /// How many frames have been rendered so far
+    number: u64,
+    bitmap: Option<rendering::BinaryBitmap>,
+}
+
+impl FrameSlot {
+    /// The latest frame and its number, if one has been rendered.
+    pub fn latest(&self) -> Option<(u64, rendering::BinaryBitmap)> {
+        let frame = self.frame.0.lock().unwrap();
+        frame.bitmap.clone().map(|bitmap| (frame.number, bitmap))
+    }
+
+    /// Waits up to `timeout` for a frame later than frame number `after`.
+    pub fn wait_after(&self, after: u64, timeout: Duration) -> Option<(u64, rendering::BinaryBitmap)> {
+        let (lock, new_frame) = &*self.frame;
+        let guard = lock.lock().unwrap();
+        let (frame, _) = new_frame.wait_timeout_while(guard, timeout, |frame| frame.number <= after).unwrap();
+        frame.bitmap.clone().filter(|_| frame.number > after).map(|bitmap| (frame.number, bitmap))
+    }
+
+    fn store(&self, bitmap: rendering::BinaryBitmap) {
+        let (lock, new_frame) = &*self.frame;
+        let mut frame = lock.lock().unwrap();
+        frame.number += 1;
+        frame.bitmap = Some(bitmap);
+        new_frame.notify_all();
+    }
+}
+
+/// Keeps the latest rendered bitmap in a [FrameSlot], for serving over HTTP.
+pub struct HttpOutput {
+    slot: FrameSlot,
+}
+
+impl HttpOutput {
+    pub fn new(slot: FrameSlot) -> Self {
+        Self { slot }
+    }
+}
+
+impl RenderTarget for HttpOutput {
+    fn render_bitmap(&mut self, bitmap: rendering::BinaryBitmap) -> std::io::Result<()> {
+        self.slot.store(bitmap);
+        Ok(())
+    }
+}
+
+/// Encodes a bitmap as a grayscale png.
+pub fn encode_png(bitmap: &rendering::BinaryBitmap) -> Vec<u8> {
+    let mut png = std::io::Cursor::new(Vec::new());
+    super::recording::to_gray_image(bitmap).write_to(&mut png, image::ImageOutputFormat::Png)
+        .expect("Encoding a png in memory can't fail");
+    png.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waiting_returns_newer_frames() {
+        let slot = FrameSlot::default();
+        assert!(slot.latest().is_none());
+        assert!(slot.wait_after(0, Duration::from_millis(10)).is_none());
+
+        let mut output = HttpOutput::new(slot.clone());
+        let waiter = {
+            let slot = slot.clone();
+            std::thread::spawn(move || slot.wait_after(0, Duration::from_secs(5)).map(|(number, frame)| (number, frame.buffer)))
+        };
+        output.render_bitmap(rendering::BinaryBitmap { width: 8, height: 1, buffer: vec![0xa5] }).unwrap();
+        assert_eq!(waiter.join().unwrap(), Some((1, vec![0xa5])));
+        assert_eq!(slot.latest().unwrap().0, 1);
+        assert!(slot.wait_after(1, Duration::from_millis(10)).is_none());
+
+        let png = image::load_from_memory(&encode_png(&slot.latest().unwrap().1)).unwrap().into_luma8();
+        assert_eq!(png.dimensions(), (8, 1));
+        assert_eq!(png.get_pixel(0, 0).0, [255]);
+        assert_eq!(png.get_pixel(1, 0).0, [0]);
+    }
+}
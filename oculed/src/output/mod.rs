@@ -0,0 +1,40 @@
+use super::rendering;
+
+mod udp;
+mod protocol;
+mod recording;
+mod terminal;
+mod serial;
+#[cfg(feature = "http")]
+mod http;
+
+pub use udp::UdpOutput;
+pub use protocol::{Protocol, Compression};
+pub use recording::{PngSequenceOutput, GifOutput};
+pub use terminal::{TerminalOutput, TerminalStyle};
+pub use serial::SerialOutput;
+#[cfg(feature = "http")]
+pub use http::{HttpOutput, FrameSlot, encode_png};
+
+/**
+ * Something we can output canvases to. Typically this would be a microcontroller connected via USB or wifi.
+ */
+pub trait RenderTarget {
+    fn render_bitmap(&mut self, bitmap: rendering::BinaryBitmap) -> std::io::Result<()>;
+
+    /// Whether the display is receiving frames, for outputs that can tell.
+    fn connection_state(&self) -> Option<ConnectionState> {
+        None
+    }
+}
+
+/// Whether a display acknowledges the frames sent to it.
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConnectionState {
+    /// Nothing has been acknowledged yet
+    Connecting,
+    Connected,
+    /// Frames stopped being acknowledged
+    Disconnected,
+}
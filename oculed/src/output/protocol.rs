@@ -0,0 +1,522 @@
+//! The datagrams [super::UdpOutput] sends to the display.
+//!
+//! The first byte is the protocol version, so the firmware can tell what follows:
+//!
+//! | first byte | content                                                                              |
+//! |------------|--------------------------------------------------------------------------------------|
+//! | `10`       | version 1: the whole bitmap, encoded in the output's [crate::rendering::PixelLayout]  |
+//! | `20`       | version 2: a header byte, followed by the body, compressed as the header says         |
+//! | `30`       | version 3: a header byte, a sequence number (u16, little endian), and the body          |
+//!
+//! The low nibble of the header is the [MessageKind], the high nibble the [Compression] of the body.
+//! A keyframe's body is the whole encoded bitmap. A partial update's body is a list of spans, each being
+//! `offset: u16, length: u16` (little endian) followed by `length` bytes to copy into the previous frame's
+//! encoded bytes at `offset`. Bodies are never longer than the encoded bitmap, so the firmware can decompress
+//! them into a buffer of that size.
+//!
+//! Version 3 makes delivery reliable. Every datagram has the next sequence number, and the display answers
+//! each one it applied with an ack: `30`, a header with [MessageKind::Ack], and the same sequence number. A
+//! partial update only applies on top of the frame with the sequence number before it, so after a lost
+//! datagram the display ignores updates until the next keyframe, which is sent when acks stop arriving.
+
+use serde::Deserialize;
+
+/// The first byte of a version 1 datagram. Understood by all firmware.
+const VERSION_1: u8 = 10;
+/// The first byte of a version 2 datagram.
+const VERSION_2: u8 = 20;
+/// The first byte of a version 3 datagram.
+const VERSION_3: u8 = 30;
+
+/// Compressed runs of repeated bytes are at least this long; shorter runs are cheaper as literals.
+const RLE_MIN_RUN: usize = 3;
+const RLE_MAX_RUN: usize = 0x7f + RLE_MIN_RUN;
+const RLE_MAX_LITERALS: usize = 0x80;
+
+const LZ_MIN_MATCH: usize = 3;
+const LZ_MAX_MATCH: usize = 0xf + LZ_MIN_MATCH;
+/// How far back a match can start, limited by the 12 bits for its offset.
+const LZ_WINDOW: usize = 0x1000;
+
+/// The protocol version the display's firmware understands.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(try_from = "u8")]
+pub enum Protocol {
+    /// Uncompressed keyframes only
+    #[default]
+    V1,
+    /// Partial updates and compression
+    V2,
+    /// Like version 2, with acks
+    V3,
+}
+
+impl Protocol {
+    /// Whether the display acknowledges datagrams.
+    pub fn has_acks(&self) -> bool {
+        *self == Protocol::V3
+    }
+}
+
+impl TryFrom<u8> for Protocol {
+    type Error = String;
+
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        match version {
+            1 => Ok(Protocol::V1),
+            2 => Ok(Protocol::V2),
+            3 => Ok(Protocol::V3),
+            _ => Err(format!("unknown protocol version {}, expected 1, 2 or 3", version)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MessageKind {
+    Keyframe = 0,
+    PartialUpdate = 1,
+    /// Sent by the display, see [Protocol::V3]
+    Ack = 2,
+}
+
+/// How version 2 and 3 bodies are compressed.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compression {
+    None = 0,
+    /// Run-length encoding. Each chunk starts with a control byte: `0..=127` means the next `control + 1`
+    /// bytes are literals, `128..=255` that the next byte is repeated `control - 125` times.
+    #[default]
+    Rle = 1,
+    /// A small LZ77 variant. A flag byte tells whether each of the next 8 items (least significant bit first)
+    /// is a literal byte or a match. A match is a little endian u16 holding `(offset - 1) << 4 | (length - 3)`,
+    /// and copies `length` bytes starting `offset` bytes back in the output.
+    Lz = 2,
+}
+
+/// Builds a datagram. Bodies that compression doesn't make smaller are sent uncompressed. The sequence number
+/// is only sent with version 3.
+pub fn encode_message(protocol: Protocol, kind: MessageKind, compression: Compression, sequence: u16, body: &[u8]) -> Vec<u8> {
+    match protocol {
+        Protocol::V1 => {
+            assert_eq!(kind, MessageKind::Keyframe, "Protocol version 1 only has keyframes");
+            let mut message = Vec::with_capacity(1 + body.len());
+            message.push(VERSION_1);
+            message.extend(body);
+            message
+        },
+        Protocol::V2 | Protocol::V3 => {
+            let compressed = match compression {
+                Compression::None => None,
+                Compression::Rle => Some(rle_compress(body)),
+                Compression::Lz => Some(lz_compress(body)),
+            }.filter(|compressed| compressed.len() < body.len());
+            let (compression, body) = match &compressed {
+                Some(compressed) => (compression, compressed.as_slice()),
+                None => (Compression::None, body),
+            };
+            let mut message = Vec::with_capacity(4 + body.len());
+            if protocol == Protocol::V2 {
+                message.push(VERSION_2);
+                message.push(kind as u8 | (compression as u8) << 4);
+            } else {
+                message.push(VERSION_3);
+                message.push(kind as u8 | (compression as u8) << 4);
+                message.extend(sequence.to_le_bytes());
+            }
+            message.extend(body);
+            message
+        },
+    }
+}
+
+/// The sequence number of an ack from the display, or `None` if `datagram` isn't one.
+pub fn parse_ack(datagram: &[u8]) -> Option<u16> {
+    match datagram {
+        [VERSION_3, header, low, high] if *header == MessageKind::Ack as u8 => Some(u16::from_le_bytes([*low, *high])),
+        _ => None,
+    }
+}
+
+fn rle_compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut literals_start = 0;
+    let mut i = 0;
+    while i < input.len() {
+        let run = input[i..].iter().take(RLE_MAX_RUN).take_while(|byte| **byte == input[i]).count();
+        if run >= RLE_MIN_RUN {
+            push_rle_literals(&mut output, &input[literals_start..i]);
+            output.push(0x80 | (run - RLE_MIN_RUN) as u8);
+            output.push(input[i]);
+            i += run;
+            literals_start = i;
+        } else {
+            i += 1;
+        }
+    }
+    push_rle_literals(&mut output, &input[literals_start..]);
+    output
+}
+
+fn push_rle_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(RLE_MAX_LITERALS) {
+        output.push((chunk.len() - 1) as u8);
+        output.extend(chunk);
+    }
+}
+
+fn lz_compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut flags_index = 0;
+    let mut item = 8;
+    let mut i = 0;
+    while i < input.len() {
+        if item == 8 {
+            flags_index = output.len();
+            output.push(0);
+            item = 0;
+        }
+        let (offset, length) = longest_match(input, i);
+        if length >= LZ_MIN_MATCH {
+            output[flags_index] |= 1 << item;
+            output.extend((((offset - 1) << 4 | (length - LZ_MIN_MATCH)) as u16).to_le_bytes());
+            i += length;
+        } else {
+            output.push(input[i]);
+            i += 1;
+        }
+        item += 1;
+    }
+    output
+}
+
+/// The offset and length of the longest earlier match for the bytes at `position`. Matches may overlap
+/// `position`, which is how runs are encoded.
+fn longest_match(input: &[u8], position: usize) -> (usize, usize) {
+    let max_length = LZ_MAX_MATCH.min(input.len() - position);
+    let mut best = (0, 0);
+    for start in position.saturating_sub(LZ_WINDOW)..position {
+        let length = (0..max_length).take_while(|k| input[start + k] == input[position + k]).count();
+        // Prefer the closest match, for no reason other than being deterministic
+        if length >= best.1 {
+            best = (position - start, length);
+        }
+    }
+    best
+}
+
+/// The receiving end of the protocol, written the way the firmware does it: every buffer is allocated up
+/// front, and malformed datagrams are rejected without touching the displayed frame.
+#[cfg(test)]
+pub(crate) struct Receiver {
+    frame: Vec<u8>,
+    has_keyframe: bool,
+    body: Vec<u8>,
+    /// The sequence number of the last applied version 3 datagram
+    sequence: Option<u16>,
+}
+
+#[cfg(test)]
+#[derive(PartialEq, Debug)]
+pub(crate) enum DecodeError {
+    UnknownVersion(u8),
+    UnknownHeader(u8),
+    /// The datagram or compressed data ended early
+    Truncated,
+    /// The data doesn't fit in the frame
+    Overflow,
+    /// A compressed match points before the start of the data
+    BadMatch,
+    /// A partial update arrived before any keyframe
+    NoKeyframe,
+    /// A version 3 partial update doesn't follow the last applied datagram
+    MissedUpdate,
+}
+
+#[cfg(test)]
+impl Receiver {
+    /// Creates a receiver for bitmaps that take up `frame_len` bytes when encoded.
+    pub fn new(frame_len: usize) -> Self {
+        Self { frame: vec![0; frame_len], has_keyframe: false, body: vec![0; frame_len], sequence: None }
+    }
+
+    /// The encoded bitmap to show, once a keyframe has arrived.
+    pub fn frame(&self) -> Option<&[u8]> {
+        self.has_keyframe.then_some(&self.frame[..])
+    }
+
+    /// Applies a datagram, returning the ack to send back, if any.
+    pub fn receive(&mut self, datagram: &[u8]) -> Result<Option<[u8; 4]>, DecodeError> {
+        match datagram.first() {
+            Some(&VERSION_1) => {
+                if datagram.len() - 1 != self.frame.len() {
+                    return Err(DecodeError::Truncated);
+                }
+                self.frame.copy_from_slice(&datagram[1..]);
+                self.has_keyframe = true;
+                Ok(None)
+            },
+            Some(&VERSION_2) => {
+                let header = *datagram.get(1).ok_or(DecodeError::Truncated)?;
+                self.apply(header, &datagram[2..])?;
+                Ok(None)
+            },
+            Some(&VERSION_3) => {
+                if datagram.len() < 4 {
+                    return Err(DecodeError::Truncated);
+                }
+                let header = datagram[1];
+                let sequence = u16::from_le_bytes([datagram[2], datagram[3]]);
+                let follows_previous = self.sequence.map(|previous| previous.wrapping_add(1)) == Some(sequence);
+                if header & 0xf == MessageKind::PartialUpdate as u8 && self.has_keyframe && !follows_previous {
+                    return Err(DecodeError::MissedUpdate);
+                }
+                self.apply(header, &datagram[4..])?;
+                self.sequence = Some(sequence);
+                let [low, high] = sequence.to_le_bytes();
+                Ok(Some([VERSION_3, MessageKind::Ack as u8, low, high]))
+            },
+            Some(version) => Err(DecodeError::UnknownVersion(*version)),
+            None => Err(DecodeError::Truncated),
+        }
+    }
+
+    /// Applies the body of a version 2 or 3 datagram.
+    fn apply(&mut self, header: u8, body: &[u8]) -> Result<(), DecodeError> {
+        let body_len = match header >> 4 {
+            0 => {
+                if body.len() > self.body.len() {
+                    return Err(DecodeError::Overflow);
+                }
+                self.body[..body.len()].copy_from_slice(body);
+                body.len()
+            },
+            1 => rle_decompress(body, &mut self.body)?,
+            2 => lz_decompress(body, &mut self.body)?,
+            _ => return Err(DecodeError::UnknownHeader(header)),
+        };
+        match header & 0xf {
+            0 => {
+                if body_len != self.frame.len() {
+                    return Err(DecodeError::Truncated);
+                }
+                self.frame.copy_from_slice(&self.body);
+                self.has_keyframe = true;
+            },
+            1 => {
+                if !self.has_keyframe {
+                    return Err(DecodeError::NoKeyframe);
+                }
+                // Check every span before applying any
+                let spans = &self.body[..body_len];
+                let mut i = 0;
+                while i < spans.len() {
+                    let (offset, length) = span_at(spans, i)?;
+                    if offset + length > self.frame.len() {
+                        return Err(DecodeError::Overflow);
+                    }
+                    i += 4 + length;
+                }
+                let mut i = 0;
+                while i < spans.len() {
+                    let (offset, length) = span_at(spans, i)?;
+                    self.frame[offset..offset + length].copy_from_slice(&spans[i + 4..i + 4 + length]);
+                    i += 4 + length;
+                }
+            },
+            _ => return Err(DecodeError::UnknownHeader(header)),
+        }
+        Ok(())
+    }
+}
+
+/// The offset and length of the span starting at `i`, checking that its bytes are there.
+#[cfg(test)]
+fn span_at(spans: &[u8], i: usize) -> Result<(usize, usize), DecodeError> {
+    if i + 4 > spans.len() {
+        return Err(DecodeError::Truncated);
+    }
+    let offset = u16::from_le_bytes([spans[i], spans[i + 1]]) as usize;
+    let length = u16::from_le_bytes([spans[i + 2], spans[i + 3]]) as usize;
+    if i + 4 + length > spans.len() {
+        return Err(DecodeError::Truncated);
+    }
+    Ok((offset, length))
+}
+
+/// Decompresses [Compression::Rle] data into `output`, returning the number of bytes written.
+#[cfg(test)]
+fn rle_decompress(input: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+    let mut i = 0;
+    let mut written = 0;
+    while i < input.len() {
+        let control = input[i] as usize;
+        i += 1;
+        if control < 0x80 {
+            let count = control + 1;
+            if i + count > input.len() {
+                return Err(DecodeError::Truncated);
+            }
+            if written + count > output.len() {
+                return Err(DecodeError::Overflow);
+            }
+            output[written..written + count].copy_from_slice(&input[i..i + count]);
+            i += count;
+            written += count;
+        } else {
+            let count = control - 0x80 + RLE_MIN_RUN;
+            let byte = *input.get(i).ok_or(DecodeError::Truncated)?;
+            if written + count > output.len() {
+                return Err(DecodeError::Overflow);
+            }
+            output[written..written + count].fill(byte);
+            i += 1;
+            written += count;
+        }
+    }
+    Ok(written)
+}
+
+/// Decompresses [Compression::Lz] data into `output`, returning the number of bytes written.
+#[cfg(test)]
+fn lz_decompress(input: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+    let mut i = 0;
+    let mut written = 0;
+    while i < input.len() {
+        let flags = input[i];
+        i += 1;
+        for item in 0..8 {
+            if i == input.len() {
+                break;
+            }
+            if flags & (1 << item) != 0 {
+                if i + 2 > input.len() {
+                    return Err(DecodeError::Truncated);
+                }
+                let code = u16::from_le_bytes([input[i], input[i + 1]]) as usize;
+                i += 2;
+                let (offset, length) = ((code >> 4) + 1, (code & 0xf) + LZ_MIN_MATCH);
+                if offset > written {
+                    return Err(DecodeError::BadMatch);
+                }
+                if written + length > output.len() {
+                    return Err(DecodeError::Overflow);
+                }
+                // Byte by byte, since the match may overlap the bytes being written
+                for _ in 0..length {
+                    output[written] = output[written - offset];
+                    written += 1;
+                }
+            } else {
+                if written == output.len() {
+                    return Err(DecodeError::Overflow);
+                }
+                output[written] = input[i];
+                i += 1;
+                written += 1;
+            }
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Data with a bit of everything: runs, repeats, and noise.
+    fn sample_data() -> Vec<u8> {
+        let mut data = vec![0u8; 300];
+        data.extend((0..200u32).map(|i| (i * 7 % 13) as u8));
+        data.extend([0xff; 3]);
+        data.extend([1, 2, 1, 2, 1, 2]);
+        let mut seed = 1u32;
+        data.extend((0..400).map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as u8
+        }));
+        data.extend([0xaa; 200]);
+        data
+    }
+
+    #[test]
+    fn compression_round_trips() {
+        let inputs = [vec![], vec![5], vec![5, 5], vec![5; 3], vec![5; 1000], (0..=255).collect(), sample_data()];
+        for input in inputs {
+            let mut output = vec![0u8; input.len()];
+            let rle = rle_compress(&input);
+            assert_eq!(rle_decompress(&rle, &mut output), Ok(input.len()));
+            assert_eq!(output, input);
+            let lz = lz_compress(&input);
+            assert_eq!(lz_decompress(&lz, &mut output), Ok(input.len()));
+            assert_eq!(output, input);
+        }
+    }
+
+    #[test]
+    fn frames_get_smaller() {
+        let mut bitmap = crate::rendering::Bitmap::new(128, 64);
+        bitmap.draw_rect(10, 10, 40, 20);
+        bitmap.draw_circle(90, 32, 20);
+        let frame = crate::rendering::BinaryBitmap::from(&bitmap).buffer;
+        assert!(rle_compress(&frame).len() < frame.len() / 2, "rle: {}", rle_compress(&frame).len());
+        assert!(lz_compress(&frame).len() < frame.len() / 2, "lz: {}", lz_compress(&frame).len());
+    }
+
+    #[test]
+    fn incompressible_bodies_are_sent_as_is() {
+        let body = (0..=255).collect::<Vec<u8>>();
+        let message = encode_message(Protocol::V2, MessageKind::Keyframe, Compression::Rle, 0, &body);
+        assert_eq!(message[..2], [VERSION_2, 0x00]);
+        assert_eq!(message[2..], body);
+
+        let message = encode_message(Protocol::V2, MessageKind::Keyframe, Compression::Lz, 0, &[0; 256]);
+        assert_eq!(message[1], 0x20);
+        let mut receiver = Receiver::new(256);
+        receiver.receive(&message).unwrap();
+        assert_eq!(receiver.frame(), Some(&[0; 256][..]));
+    }
+
+    #[test]
+    fn malformed_datagrams_are_rejected() {
+        let mut receiver = Receiver::new(6);
+        let partial = encode_message(Protocol::V2, MessageKind::PartialUpdate, Compression::None, 0, &[0, 0, 1, 0, 9]);
+        assert_eq!(receiver.receive(&partial), Err(DecodeError::NoKeyframe));
+        receiver.receive(&encode_message(Protocol::V1, MessageKind::Keyframe, Compression::None, 0, &[1, 2, 3, 4, 5, 6])).unwrap();
+        receiver.receive(&partial).unwrap();
+        assert_eq!(receiver.frame(), Some(&[9, 2, 3, 4, 5, 6][..]));
+
+        assert_eq!(receiver.receive(&[]), Err(DecodeError::Truncated));
+        assert_eq!(receiver.receive(&[11, 0]), Err(DecodeError::UnknownVersion(11)));
+        assert_eq!(receiver.receive(&[VERSION_1, 1, 2]), Err(DecodeError::Truncated));
+        assert_eq!(receiver.receive(&[VERSION_2, 0x30]), Err(DecodeError::UnknownHeader(0x30)));
+        // A run longer than the frame
+        assert_eq!(receiver.receive(&[VERSION_2, 0x10, 0x85, 7]), Err(DecodeError::Overflow));
+        // A match before the first byte
+        assert_eq!(receiver.receive(&[VERSION_2, 0x20, 0x02, 1, 0x10, 0x00]), Err(DecodeError::BadMatch));
+        // A span past the end of the frame
+        assert_eq!(receiver.receive(&[VERSION_2, 0x01, 5, 0, 2, 0, 5, 5]), Err(DecodeError::Overflow));
+        assert_eq!(receiver.receive(&[VERSION_2, 0x01, 0, 0, 1]), Err(DecodeError::Truncated));
+        assert_eq!(receiver.frame(), Some(&[9, 2, 3, 4, 5, 6][..]));
+    }
+
+    #[test]
+    fn version_3_acks_and_skips_updates_after_a_gap() {
+        let mut receiver = Receiver::new(6);
+        let message = |kind, sequence, body: &[u8]| encode_message(Protocol::V3, kind, Compression::None, sequence, body);
+
+        let ack = receiver.receive(&message(MessageKind::Keyframe, 0xffff, &[1, 2, 3, 4, 5, 6])).unwrap().unwrap();
+        assert_eq!(parse_ack(&ack), Some(0xffff));
+        let ack = receiver.receive(&message(MessageKind::PartialUpdate, 0, &[0, 0, 1, 0, 9])).unwrap().unwrap();
+        assert_eq!(parse_ack(&ack), Some(0));
+
+        // Update 1 was lost
+        assert_eq!(receiver.receive(&message(MessageKind::PartialUpdate, 2, &[1, 0, 1, 0, 9])), Err(DecodeError::MissedUpdate));
+        assert_eq!(receiver.frame(), Some(&[9, 2, 3, 4, 5, 6][..]));
+        let ack = receiver.receive(&message(MessageKind::Keyframe, 3, &[5, 6, 7, 8, 9, 10])).unwrap().unwrap();
+        assert_eq!(parse_ack(&ack), Some(3));
+        assert_eq!(receiver.frame(), Some(&[5, 6, 7, 8, 9, 10][..]));
+
+        assert_eq!(parse_ack(&message(MessageKind::Keyframe, 3, &[])), None);
+    }
+}
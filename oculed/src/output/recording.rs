@@ -0,0 +1,153 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+
+use crate::rendering;
+use super::RenderTarget;
+
+/// The delay used for the last frame of a gif, since no later frame tells us how long it was shown.
+const LAST_FRAME_DELAY: Duration = Duration::from_millis(500);
+
+/// Writes every rendered bitmap to a numbered png file (`frame_00000.png`, `frame_00001.png`, ...) in a directory.
+pub struct PngSequenceOutput {
+    directory: PathBuf,
+    next_index: usize,
+}
+
+impl PngSequenceOutput {
+    /// Creates an output writing to `directory`, creating it if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> std::io::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        Ok(Self {
+            directory,
+            next_index: 0,
+        })
+    }
+}
+
+impl RenderTarget for PngSequenceOutput {
+    fn render_bitmap(&mut self, bitmap: rendering::BinaryBitmap) -> std::io::Result<()> {
+        let path = self.directory.join(format!("frame_{:05}.png", self.next_index));
+        to_gray_image(&bitmap).save(path).map_err(std::io::Error::other)?;
+        self.next_index += 1;
+        Ok(())
+    }
+}
+
+/// Records rendered bitmaps to an animated gif.
+///
+/// Each frame is shown for as long as it was actually displayed, i.e. the time until the next call to
+/// [RenderTarget::render_bitmap]. Consecutive identical frames are merged into one.
+/// The file is completed when the output is dropped.
+pub struct GifOutput {
+    encoder: GifEncoder<BufWriter<File>>,
+    /// The frame waiting to be written, and when it was rendered
+    pending: Option<(rendering::BinaryBitmap, Instant)>,
+}
+
+impl GifOutput {
+    pub fn new<P: Into<PathBuf>>(path: P) -> std::io::Result<Self> {
+        let file = File::create(path.into())?;
+        let mut encoder = GifEncoder::new(BufWriter::new(file));
+        encoder.set_repeat(Repeat::Infinite).map_err(std::io::Error::other)?;
+        Ok(Self {
+            encoder,
+            pending: None,
+        })
+    }
+
+    fn push_frame(&mut self, bitmap: rendering::BinaryBitmap, time: Instant) -> std::io::Result<()> {
+        if let Some((pending, since)) = self.pending.take() {
+            if pending.buffer == bitmap.buffer && pending.width == bitmap.width {
+                self.pending = Some((pending, since));
+                return Ok(());
+            }
+            self.write_frame(&pending, time.saturating_duration_since(since))?;
+        }
+        self.pending = Some((bitmap, time));
+        Ok(())
+    }
+
+    fn write_frame(&mut self, bitmap: &rendering::BinaryBitmap, duration: Duration) -> std::io::Result<()> {
+        let rgba = image::DynamicImage::ImageLuma8(to_gray_image(bitmap)).into_rgba8();
+        let frame = image::Frame::from_parts(rgba, 0, 0, image::Delay::from_saturating_duration(duration));
+        self.encoder.encode_frame(frame).map_err(std::io::Error::other)
+    }
+}
+
+impl RenderTarget for GifOutput {
+    fn render_bitmap(&mut self, bitmap: rendering::BinaryBitmap) -> std::io::Result<()> {
+        self.push_frame(bitmap, Instant::now())
+    }
+}
+
+impl Drop for GifOutput {
+    fn drop(&mut self) {
+        if let Some((pending, _)) = self.pending.take() {
+            if let Err(e) = self.write_frame(&pending, LAST_FRAME_DELAY) {
+                println!("Failed to write last gif frame: {:?}", e);
+            }
+        }
+    }
+}
+
+pub(super) fn to_gray_image(bitmap: &rendering::BinaryBitmap) -> image::GrayImage {
+    let unpacked = rendering::Bitmap::from(bitmap);
+    image::GrayImage::from_raw(unpacked.width as u32, unpacked.height as u32, unpacked.buffer)
+        .expect("Bitmap buffer does not match its size")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::AnimationDecoder;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("oculed-recording-{}-{}", name, std::process::id()))
+    }
+
+    fn bitmap_with_first_byte(byte: u8) -> rendering::BinaryBitmap {
+        let mut buffer = vec![0u8; 16 * 4 / 8];
+        buffer[0] = byte;
+        rendering::BinaryBitmap { width: 16, height: 4, buffer }
+    }
+
+    #[test]
+    fn png_frames_are_numbered() {
+        let dir = temp_path("png");
+        let mut output = PngSequenceOutput::new(&dir).unwrap();
+        output.render_bitmap(bitmap_with_first_byte(0b1000_0000)).unwrap();
+        output.render_bitmap(bitmap_with_first_byte(0)).unwrap();
+
+        let first = image::open(dir.join("frame_00000.png")).unwrap().into_luma8();
+        assert_eq!(first.dimensions(), (16, 4));
+        assert_eq!(first.get_pixel(0, 0).0, [0xff]);
+        assert_eq!(first.get_pixel(1, 0).0, [0]);
+        assert!(dir.join("frame_00001.png").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn gif_frames_use_real_timings() {
+        let path = temp_path("gif.gif");
+        {
+            let start = Instant::now();
+            let mut output = GifOutput::new(&path).unwrap();
+            output.push_frame(bitmap_with_first_byte(1), start).unwrap();
+            output.push_frame(bitmap_with_first_byte(2), start + Duration::from_millis(100)).unwrap();
+            // identical frames are merged
+            output.push_frame(bitmap_with_first_byte(2), start + Duration::from_millis(150)).unwrap();
+            output.push_frame(bitmap_with_first_byte(3), start + Duration::from_millis(400)).unwrap();
+        }
+
+        let decoder = image::codecs::gif::GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        let delays = frames.iter().map(|f| Duration::from(f.delay())).collect::<Vec<Duration>>();
+        assert_eq!(delays, vec![Duration::from_millis(100), Duration::from_millis(300), LAST_FRAME_DELAY]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
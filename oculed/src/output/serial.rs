@@ -0,0 +1,205 @@
+use std::io::Write;
+
+use crate::rendering;
+use super::RenderTarget;
+
+/// Marks the start of a frame, so the receiver can find frame boundaries in the byte stream.
+const SYNC: [u8; 2] = [0xaa, 0x55];
+const WRITE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Sends bitmaps to a microcontroller attached to a serial port (typically USB).
+///
+/// The payload is a version 1 [super::protocol] datagram (`10u8` followed by the encoded bitmap), but since a serial
+/// port is a byte stream each payload is wrapped in a frame:
+///
+/// | bytes       | content                                                   |
+/// |-------------|-----------------------------------------------------------|
+/// | 2           | sync bytes `0xaa 0x55`                                     |
+/// | 2           | payload length, little endian                              |
+/// | length      | payload                                                    |
+/// | 2           | CRC-16/CCITT-FALSE of the length and payload, little endian |
+///
+/// Receivers should resynchronize on the next sync bytes when the checksum doesn't match.
+pub struct SerialOutput {
+    port: Box<dyn serialport::SerialPort>,
+    previous: rendering::BinaryBitmap,
+    layout: rendering::PixelLayout,
+}
+
+impl SerialOutput {
+    /// Opens the serial port at `path` (e.g. `/dev/ttyUSB0` or `COM3`).
+    pub fn open(path: &str, baud_rate: u32) -> std::io::Result<Self> {
+        let port = serialport::new(path, baud_rate)
+            .timeout(WRITE_TIMEOUT)
+            .open()?;
+        Ok(Self {
+            port,
+            previous: rendering::BinaryBitmap{ width: 0, height: 0, buffer: Vec::new() },
+            layout: rendering::PixelLayout::default(),
+        })
+    }
+
+    /// Sets how pixels are packed into the sent bytes, to match what the display expects.
+    pub fn with_layout(mut self, layout: rendering::PixelLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+impl RenderTarget for SerialOutput {
+    fn render_bitmap(&mut self, bitmap: rendering::BinaryBitmap) -> std::io::Result<()> {
+        let changed = !bitmap.buffer.eq(&self.previous.buffer);
+        if !changed { return Ok(()); }
+
+        let encoded = bitmap.encode(self.layout);
+        let mut payload = Vec::with_capacity(1 + encoded.len());
+        payload.push(10u8);
+        payload.extend(encoded);
+
+        self.port.write_all(&encode_frame(&payload))?;
+        self.port.flush()?;
+        self.previous = bitmap;
+        Ok(())
+    }
+}
+
+/// Wraps `payload` in a frame, see [SerialOutput].
+fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    let length = u16::try_from(payload.len()).expect("Payload too large for a frame").to_le_bytes();
+    let mut frame = Vec::with_capacity(SYNC.len() + length.len() + payload.len() + 2);
+    frame.extend(SYNC);
+    frame.extend(length);
+    frame.extend(payload);
+    let crc = crc16(&frame[SYNC.len()..]);
+    frame.extend(crc.to_le_bytes());
+    frame
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xffff). Small enough to compute bitwise on the
+/// microcontroller.
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for byte in bytes {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use serialport::SerialPort;
+
+    /// A frame parser like the one running on the microcontroller.
+    struct FrameDecoder {
+        buffer: Vec<u8>,
+    }
+
+    impl FrameDecoder {
+        fn new() -> Self {
+            Self { buffer: Vec::new() }
+        }
+
+        /// Adds received bytes, returning the payloads of all complete and valid frames.
+        fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+            self.buffer.extend(bytes);
+            let mut payloads = Vec::new();
+            loop {
+                let Some(start) = self.buffer.windows(2).position(|w| w == SYNC) else {
+                    // keep a trailing partial sync byte
+                    let keep = if self.buffer.last() == Some(&SYNC[0]) { 1 } else { 0 };
+                    self.buffer.drain(..self.buffer.len() - keep);
+                    return payloads;
+                };
+                self.buffer.drain(..start);
+                if self.buffer.len() < 4 {
+                    return payloads;
+                }
+                let length = u16::from_le_bytes([self.buffer[2], self.buffer[3]]) as usize;
+                if self.buffer.len() < 4 + length + 2 {
+                    return payloads;
+                }
+                let crc = u16::from_le_bytes([self.buffer[4 + length], self.buffer[5 + length]]);
+                if crc == crc16(&self.buffer[2..4 + length]) {
+                    payloads.push(self.buffer[4..4 + length].to_vec());
+                    self.buffer.drain(..6 + length);
+                } else {
+                    // Not a real frame start, look for the next one
+                    self.buffer.drain(..1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn crc_matches_reference() {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+    }
+
+    #[test]
+    fn decoder_resynchronizes_after_garbage() {
+        let mut stream = vec![0x01, 0xaa, 0x02];
+        stream.extend(encode_frame(&[10, 1, 2, 3]));
+        let mut corrupted = encode_frame(&[10, 4]);
+        corrupted[5] ^= 0xff;
+        stream.extend(corrupted);
+        stream.extend(encode_frame(&[10, 0xaa, 0x55]));
+
+        let mut decoder = FrameDecoder::new();
+        let mut payloads = Vec::new();
+        // Feed byte by byte to exercise partial frames
+        for byte in stream {
+            payloads.extend(decoder.push(&[byte]));
+        }
+        assert_eq!(payloads, vec![vec![10, 1, 2, 3], vec![10, 0xaa, 0x55]]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn frames_are_written_to_the_port() {
+        let (mut master, slave) = serialport::TTYPort::pair().unwrap();
+        let mut output = SerialOutput::open(slave.name().as_deref().unwrap(), 115200).unwrap();
+
+        let bitmap = rendering::BinaryBitmap { width: 16, height: 2, buffer: vec![0x0a, 0x0d, 0xaa, 0x55] };
+        output.render_bitmap(bitmap.clone()).unwrap();
+        // unchanged bitmaps aren't resent
+        output.render_bitmap(bitmap).unwrap();
+        output.render_bitmap(rendering::BinaryBitmap { width: 16, height: 2, buffer: vec![0, 0, 0, 1] }).unwrap();
+
+        let mut decoder = FrameDecoder::new();
+        let mut payloads = Vec::new();
+        let mut buffer = [0u8; 64];
+        while payloads.len() < 2 {
+            let len = master.read(&mut buffer).unwrap();
+            payloads.extend(decoder.push(&buffer[..len]));
+        }
+        assert_eq!(payloads, vec![vec![10, 0x0a, 0x0d, 0xaa, 0x55], vec![10, 0, 0, 0, 1]]);
+        master.set_timeout(std::time::Duration::from_millis(100)).unwrap();
+        assert!(master.read(&mut buffer).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn frames_use_the_pixel_layout() {
+        let (mut master, slave) = serialport::TTYPort::pair().unwrap();
+        let mut output = SerialOutput::open(slave.name().as_deref().unwrap(), 115200).unwrap()
+            .with_layout(rendering::PixelLayout::Ssd1306Pages);
+
+        // The top left pixel and the one below it
+        let bitmap = rendering::BinaryBitmap { width: 8, height: 8, buffer: vec![0x80, 0x80, 0, 0, 0, 0, 0, 0] };
+        output.render_bitmap(bitmap).unwrap();
+
+        let mut decoder = FrameDecoder::new();
+        let mut payloads = Vec::new();
+        let mut buffer = [0u8; 64];
+        while payloads.is_empty() {
+            let len = master.read(&mut buffer).unwrap();
+            payloads.extend(decoder.push(&buffer[..len]));
+        }
+        assert_eq!(payloads, vec![vec![10, 0b0000_0011, 0, 0, 0, 0, 0, 0, 0]]);
+    }
+}
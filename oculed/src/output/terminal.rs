@@ -0,0 +1,151 @@
+use std::io::Write;
+
+use crate::rendering;
+use super::RenderTarget;
+
+/// How pixels are mapped to characters by [TerminalOutput].
+#[derive(Clone, Copy, PartialEq, Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TerminalStyle {
+    /// Each character shows 1x2 pixels using half block characters (`▀`, `▄`, `█`).
+    HalfBlocks,
+    /// Each character shows 2x4 pixels using braille characters. Smaller, but less legible.
+    Braille,
+}
+
+impl TerminalStyle {
+    /// The number of pixels (horizontally, vertically) covered by one character.
+    fn cell_size(&self) -> (usize, usize) {
+        match self {
+            TerminalStyle::HalfBlocks => (1, 2),
+            TerminalStyle::Braille => (2, 4),
+        }
+    }
+}
+
+/// Draws bitmaps as text to a terminal, for developing without a display attached.
+///
+/// Only rows that changed since the previous bitmap are redrawn.
+pub struct TerminalOutput<W: Write> {
+    writer: W,
+    style: TerminalStyle,
+    previous_rows: Vec<String>,
+}
+
+impl TerminalOutput<std::io::Stdout> {
+    pub fn stdout(style: TerminalStyle) -> Self {
+        Self::new(std::io::stdout(), style)
+    }
+}
+
+impl<W: Write> TerminalOutput<W> {
+    pub fn new(writer: W, style: TerminalStyle) -> Self {
+        Self {
+            writer,
+            style,
+            previous_rows: Vec::new(),
+        }
+    }
+
+    fn to_rows(&self, bitmap: &rendering::BinaryBitmap) -> Vec<String> {
+        let pixels = rendering::Bitmap::from(bitmap);
+        let is_set = |x: usize, y: usize| x < pixels.width && y < pixels.height && pixels.buffer[y * pixels.width + x] > 0;
+        let (cell_width, cell_height) = self.style.cell_size();
+
+        (0..pixels.height.div_ceil(cell_height)).map(|row| {
+            let y = row * cell_height;
+            (0..pixels.width.div_ceil(cell_width)).map(|col| {
+                let x = col * cell_width;
+                match self.style {
+                    TerminalStyle::HalfBlocks => match (is_set(x, y), is_set(x, y + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    },
+                    TerminalStyle::Braille => {
+                        // Dot numbering of the unicode braille patterns, by (x, y) within the cell
+                        const DOTS: [(usize, usize, u32); 8] = [
+                            (0, 0, 0x01), (0, 1, 0x02), (0, 2, 0x04), (1, 0, 0x08),
+                            (1, 1, 0x10), (1, 2, 0x20), (0, 3, 0x40), (1, 3, 0x80),
+                        ];
+                        let bits = DOTS.iter()
+                            .filter(|(dx, dy, _)| is_set(x + dx, y + dy))
+                            .fold(0, |bits, (_, _, bit)| bits | bit);
+                        char::from_u32(0x2800 + bits).unwrap()
+                    },
+                }
+            }).collect()
+        }).collect()
+    }
+}
+
+impl<W: Write> RenderTarget for TerminalOutput<W> {
+    fn render_bitmap(&mut self, bitmap: rendering::BinaryBitmap) -> std::io::Result<()> {
+        let rows = self.to_rows(&bitmap);
+        if rows.len() != self.previous_rows.len() {
+            // First frame or the size changed; clear the screen and hide the cursor
+            write!(self.writer, "\x1b[2J\x1b[?25l")?;
+            self.previous_rows.clear();
+        }
+        for (i, row) in rows.iter().enumerate() {
+            if self.previous_rows.get(i) != Some(row) {
+                write!(self.writer, "\x1b[{};1H{}", i + 1, row)?;
+            }
+        }
+        self.writer.flush()?;
+        self.previous_rows = rows;
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for TerminalOutput<W> {
+    fn drop(&mut self) {
+        // Show the cursor again and leave it below the image
+        let _ = write!(self.writer, "\x1b[{};1H\x1b[?25h", self.previous_rows.len() + 1);
+        let _ = self.writer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitmap(width: usize, height: usize, pixels: &[(usize, usize)]) -> rendering::BinaryBitmap {
+        let mut bmp = rendering::Bitmap::new(width, height);
+        for (x, y) in pixels {
+            bmp.buffer[y * width + x] = 0xff;
+        }
+        (&bmp).into()
+    }
+
+    #[test]
+    fn half_blocks_cover_two_rows() {
+        let output = TerminalOutput::new(Vec::new(), TerminalStyle::HalfBlocks);
+        let rows = output.to_rows(&bitmap(8, 4, &[(0, 0), (1, 1), (2, 0), (2, 1), (3, 3)]));
+        assert_eq!(rows, vec!["▀▄█     ", "   ▄    "]);
+    }
+
+    #[test]
+    fn braille_covers_two_by_four() {
+        let output = TerminalOutput::new(Vec::new(), TerminalStyle::Braille);
+        let rows = output.to_rows(&bitmap(8, 4, &[(0, 0), (1, 3), (2, 0), (3, 0), (2, 1), (3, 1), (2, 2), (3, 2), (2, 3), (3, 3)]));
+        assert_eq!(rows, vec!["⢁⣿⠀⠀"]);
+    }
+
+    #[test]
+    fn only_changed_rows_are_redrawn() {
+        let mut output = TerminalOutput::new(Vec::new(), TerminalStyle::HalfBlocks);
+        output.render_bitmap(bitmap(8, 4, &[(0, 0)])).unwrap();
+        let first = String::from_utf8(std::mem::take(&mut output.writer)).unwrap();
+        assert!(first.starts_with("\x1b[2J"));
+        assert!(first.contains("\x1b[1;1H") && first.contains("\x1b[2;1H"));
+
+        output.render_bitmap(bitmap(8, 4, &[(0, 0)])).unwrap();
+        assert!(output.writer.is_empty());
+
+        output.render_bitmap(bitmap(8, 4, &[(0, 0), (0, 2)])).unwrap();
+        let third = String::from_utf8(std::mem::take(&mut output.writer)).unwrap();
+        assert_eq!(third, "\x1b[2;1H▀       ");
+    }
+}
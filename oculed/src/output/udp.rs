@@ -0,0 +1,420 @@
+use std::net;
+use std::time::{Duration, Instant};
+
+use crate::rendering;
+use super::{ConnectionState, RenderTarget};
+use super::protocol::{self, Compression, MessageKind, Protocol};
+
+/// The size of a span's offset and length. Unchanged runs shorter than this are sent rather than starting a
+/// new span.
+const SPAN_HEADER_LEN: usize = 4;
+
+/// Sends bitmaps to a display over wifi, one datagram per frame. See [protocol] for the format.
+///
+/// From protocol version 2, frames are sent as the bytes that changed since the previous frame. Since datagrams
+/// can be lost, a keyframe with the whole bitmap is sent every `keyframe_interval` frames even when nothing
+/// changed. With version 3 the display acks every datagram, and a keyframe is sent as soon as an ack is late.
+pub struct UdpOutput<'a> {
+    address: &'a str,
+    socket: net::UdpSocket,
+    layout: rendering::PixelLayout,
+    protocol: Protocol,
+    compression: Compression,
+    keyframe_interval: u32,
+    /// How long to wait for an ack before sending a keyframe
+    ack_timeout: Duration,
+    /// How long acks can be missing before the display counts as disconnected
+    disconnect_timeout: Duration,
+    /// The encoded bytes the display has, if everything arrived
+    previous: Vec<u8>,
+    frames_since_keyframe: u32,
+    sequence: u16,
+    /// The sequence number of the oldest datagram that hasn't been acked
+    oldest_unacked: Option<u16>,
+    /// Since when datagrams have been waiting for an ack, restarted whenever an ack arrives
+    unacked_since: Option<Instant>,
+    /// When to send a keyframe if the last datagram still hasn't been acked
+    resend_at: Option<Instant>,
+    ever_acked: bool,
+}
+
+impl<'a> UdpOutput<'a> {
+    /// Creates an output sending to `address` (`host:port`), from a socket bound to any free port.
+    pub fn open(address: &'a str) -> std::io::Result<Self> {
+        let socket = net::UdpSocket::bind(("0.0.0.0", 0))?;
+        // Acks are polled for when rendering
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            address,
+            socket,
+            layout: rendering::PixelLayout::default(),
+            protocol: Protocol::default(),
+            compression: Compression::default(),
+            keyframe_interval: 40,
+            ack_timeout: Duration::from_millis(250),
+            disconnect_timeout: Duration::from_secs(3),
+            previous: Vec::new(),
+            frames_since_keyframe: 0,
+            sequence: 0,
+            oldest_unacked: None,
+            unacked_since: None,
+            resend_at: None,
+            ever_acked: false,
+        })
+    }
+
+    /// Sets how pixels are packed into the sent bytes, to match what the display expects.
+    pub fn with_layout(mut self, layout: rendering::PixelLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets the protocol version the display understands.
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Sets how frames are compressed. Not used with [Protocol::V1].
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets how many frames may pass between keyframes. 1 sends every frame in full.
+    pub fn with_keyframe_interval(mut self, frames: u32) -> Self {
+        self.keyframe_interval = frames.max(1);
+        self
+    }
+
+    /// Picks the message to send for `encoded`, or `None` if the display is already up to date.
+    fn next_message(&mut self, encoded: Vec<u8>, force_keyframe: bool) -> Option<Vec<u8>> {
+        self.frames_since_keyframe += 1;
+        let keyframe_due = force_keyframe
+            || self.frames_since_keyframe >= self.keyframe_interval
+            || encoded.len() != self.previous.len();
+        if !keyframe_due && encoded == self.previous {
+            return None;
+        }
+        let spans = if keyframe_due || self.protocol == Protocol::V1 {
+            None
+        } else {
+            // Large changes are cheaper to send in full
+            Some(changed_spans(&self.previous, &encoded)).filter(|spans| spans.len() < encoded.len())
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+        let message = match spans {
+            Some(spans) => protocol::encode_message(self.protocol, MessageKind::PartialUpdate, self.compression, self.sequence, &spans),
+            None => {
+                self.frames_since_keyframe = 0;
+                protocol::encode_message(self.protocol, MessageKind::Keyframe, self.compression, self.sequence, &encoded)
+            },
+        };
+        self.previous = encoded;
+        Some(message)
+    }
+
+    /// Handles the acks that arrived since the last frame. An ack covers every datagram up to its own, since
+    /// frames often go out before the previous one's ack is back.
+    fn receive_acks(&mut self) -> std::io::Result<()> {
+        let mut buffer = [0u8; 16];
+        loop {
+            match self.socket.recv(&mut buffer) {
+                Ok(len) => {
+                    let Some(sequence) = protocol::parse_ack(&buffer[..len]) else { continue; };
+                    self.ever_acked = true;
+                    let Some(oldest) = self.oldest_unacked else { continue; };
+                    // Sequence numbers wrap, so they are compared by how far past the oldest unacked one they are.
+                    // Acks for datagrams before that are late duplicates.
+                    if sequence.wrapping_sub(oldest) > self.sequence.wrapping_sub(oldest) {
+                        continue;
+                    }
+                    if sequence == self.sequence {
+                        self.oldest_unacked = None;
+                        self.unacked_since = None;
+                        self.resend_at = None;
+                    } else {
+                        let now = Instant::now();
+                        self.oldest_unacked = Some(sequence.wrapping_add(1));
+                        self.unacked_since = Some(now);
+                        self.resend_at = Some(now + self.ack_timeout);
+                    }
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(()),
+                // Some platforms report ICMP errors from earlier sends here, e.g. while the display is off
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::ConnectionReset) => {},
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<'a> RenderTarget for UdpOutput<'a> {
+    fn render_bitmap(&mut self, bitmap: rendering::BinaryBitmap) -> std::io::Result<()> {
+        let mut resend = false;
+        if self.protocol.has_acks() {
+            self.receive_acks()?;
+            resend = self.resend_at.is_some_and(|time| Instant::now() >= time);
+        }
+        let Some(buf) = self.next_message(bitmap.encode(self.layout), resend) else { return Ok(()); };
+
+        self.socket.send_to(&buf, self.address)?;
+        if self.protocol.has_acks() {
+            let now = Instant::now();
+            self.oldest_unacked.get_or_insert(self.sequence);
+            self.unacked_since.get_or_insert(now);
+            if resend || self.resend_at.is_none() {
+                self.resend_at = Some(now + self.ack_timeout);
+            }
+        }
+        Ok(())
+    }
+
+    fn connection_state(&self) -> Option<ConnectionState> {
+        if !self.protocol.has_acks() {
+            return None;
+        }
+        Some(match self.unacked_since {
+            Some(time) if time.elapsed() >= self.disconnect_timeout => ConnectionState::Disconnected,
+            _ if !self.ever_acked => ConnectionState::Connecting,
+            _ => ConnectionState::Connected,
+        })
+    }
+}
+
+/// Builds the spans of a partial update turning `previous` into `current`, which must have the same length.
+fn changed_spans(previous: &[u8], current: &[u8]) -> Vec<u8> {
+    let mut message = Vec::new();
+    let mut i = 0;
+    while i < current.len() {
+        if current[i] == previous[i] {
+            i += 1;
+            continue;
+        }
+        // Extend the span until it's followed by enough unchanged bytes to be worth a new span header
+        let start = i;
+        let mut end = i + 1;
+        while end < current.len() {
+            let unchanged = current[end..].iter().zip(&previous[end..])
+                .take(SPAN_HEADER_LEN)
+                .take_while(|(c, p)| c == p)
+                .count();
+            if unchanged == SPAN_HEADER_LEN || end + unchanged == current.len() {
+                break;
+            }
+            end += unchanged + 1;
+        }
+        message.extend((start as u16).to_le_bytes());
+        message.extend(((end - start) as u16).to_le_bytes());
+        message.extend(&current[start..end]);
+        i = end;
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::protocol::Receiver;
+    use std::sync::{Arc, Mutex};
+
+    fn bitmap_with_square(x: usize, y: usize) -> rendering::BinaryBitmap {
+        let mut bitmap = rendering::Bitmap::new(128, 64);
+        bitmap.draw_rect(x as i32, y as i32, 6, 6);
+        rendering::BinaryBitmap::from(&bitmap)
+    }
+
+    /// A display on a local socket, which drops the datagrams `drop` picks and acks the rest. Acks are held back
+    /// until `ack_delay` more datagrams arrived.
+    struct FakeDevice {
+        address: String,
+        frame: Arc<Mutex<Option<Vec<u8>>>>,
+        stop: Arc<std::sync::atomic::AtomicBool>,
+        thread: Option<std::thread::JoinHandle<()>>,
+    }
+
+    impl FakeDevice {
+        fn start(frame_len: usize, drop: impl FnMut(usize) -> bool + Send + 'static) -> Self {
+            Self::start_with_ack_delay(frame_len, drop, 0)
+        }
+
+        fn start_with_ack_delay(frame_len: usize, mut drop: impl FnMut(usize) -> bool + Send + 'static, ack_delay: usize) -> Self {
+            let socket = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+            let address = socket.local_addr().unwrap().to_string();
+            let frame = Arc::new(Mutex::new(None));
+            let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+            let thread = {
+                let (frame, stop) = (Arc::clone(&frame), Arc::clone(&stop));
+                std::thread::spawn(move || {
+                    let mut receiver = Receiver::new(frame_len);
+                    let mut buffer = [0u8; 2048];
+                    let mut count = 0;
+                    let mut acks = std::collections::VecDeque::new();
+                    while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                        let Ok((len, sender)) = socket.recv_from(&mut buffer) else { continue; };
+                        count += 1;
+                        if drop(count) {
+                            continue;
+                        }
+                        if let Ok(Some(ack)) = receiver.receive(&buffer[..len]) {
+                            acks.push_back(ack);
+                        }
+                        if acks.len() > ack_delay {
+                            socket.send_to(&acks.pop_front().unwrap(), sender).unwrap();
+                        }
+                        *frame.lock().unwrap() = receiver.frame().map(|f| f.to_vec());
+                    }
+                })
+            };
+            Self { address, frame, stop, thread: Some(thread) }
+        }
+
+        fn frame(&self) -> Option<Vec<u8>> {
+            self.frame.lock().unwrap().clone()
+        }
+
+        fn stop(&mut self) {
+            self.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+            self.thread.take().unwrap().join().unwrap();
+        }
+    }
+
+    #[test]
+    fn spans_cover_changes_and_merge_small_gaps() {
+        let previous = [0u8; 16];
+        let mut current = previous;
+        current[1] = 1;
+        current[3] = 2; // less than a span header away, so part of the first span
+        current[12] = 3;
+        let spans = changed_spans(&previous, &current);
+        assert_eq!(spans, vec![1, 0, 3, 0, 1, 0, 2, 12, 0, 1, 0, 3]);
+
+        let mut receiver = Receiver::new(16);
+        receiver.receive(&protocol::encode_message(Protocol::V1, MessageKind::Keyframe, Compression::None, 0, &previous)).unwrap();
+        receiver.receive(&protocol::encode_message(Protocol::V2, MessageKind::PartialUpdate, Compression::None, 0, &spans)).unwrap();
+        assert_eq!(receiver.frame(), Some(&current[..]));
+    }
+
+    #[test]
+    fn version_1_only_sends_whole_frames() {
+        let mut output = UdpOutput::open("").unwrap().with_protocol(Protocol::V1);
+        for x in [10, 11, 11, 12] {
+            let bitmap = bitmap_with_square(x, 20);
+            if let Some(message) = output.next_message(bitmap.encode(output.layout), false) {
+                assert_eq!(message[0], 10);
+                assert_eq!(message[1..], bitmap.buffer);
+            }
+        }
+        assert_eq!(output.frames_since_keyframe, 0);
+        assert_eq!(output.connection_state(), None);
+    }
+
+    #[test]
+    fn listener_reassembles_frames() {
+        let listener = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let layout = rendering::PixelLayout::Ssd1306Pages;
+        let mut receiver = Receiver::new(layout.encoded_len(128, 64));
+        let mut buffer = [0u8; 2048];
+
+        for compression in [Compression::None, Compression::Rle, Compression::Lz] {
+            let mut output = UdpOutput::open(&address).unwrap()
+                .with_layout(layout)
+                .with_protocol(Protocol::V2)
+                .with_compression(compression)
+                .with_keyframe_interval(5);
+            let mut kinds = Vec::new();
+            for i in 0..6 {
+                let bitmap = bitmap_with_square(10 + i, 20);
+                output.render_bitmap(bitmap.clone()).unwrap();
+                let len = listener.recv(&mut buffer).unwrap();
+                kinds.push(buffer[1] & 0xf);
+                receiver.receive(&buffer[..len]).unwrap();
+                assert_eq!(rendering::BinaryBitmap::decode(receiver.frame().unwrap(), 128, 64, layout).buffer, bitmap.buffer);
+                if i > 0 && i < 5 {
+                    assert!(len < 32, "{:?}: a moving square took {} bytes", compression, len);
+                } else if compression != Compression::None {
+                    assert!(len < 256, "{:?}: a keyframe took {} bytes", compression, len);
+                }
+            }
+            assert_eq!(kinds, vec![0, 1, 1, 1, 1, 0]);
+
+            // Unchanged frames are skipped until the next keyframe is due
+            listener.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+            for _ in 0..3 {
+                output.render_bitmap(bitmap_with_square(15, 20)).unwrap();
+            }
+            assert!(listener.recv(&mut buffer).is_err());
+            listener.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        }
+    }
+
+    #[test]
+    fn lost_datagrams_are_recovered() {
+        // Drops every third datagram
+        let mut device = FakeDevice::start(1024, |count| count % 3 == 0);
+        let address = device.address.clone();
+        let mut output = UdpOutput::open(&address).unwrap().with_protocol(Protocol::V3).with_keyframe_interval(1000);
+        output.ack_timeout = Duration::from_millis(30);
+        assert_eq!(output.connection_state(), Some(ConnectionState::Connecting));
+
+        for x in 0..20 {
+            output.render_bitmap(bitmap_with_square(x, 20)).unwrap();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        // Keep showing the last frame until the display catches up, like the main loop does
+        let last = bitmap_with_square(19, 20);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while device.frame().as_ref() != Some(&last.buffer) || output.unacked_since.is_some() {
+            assert!(Instant::now() < deadline, "the display never got the last frame");
+            output.render_bitmap(last.clone()).unwrap();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(output.connection_state(), Some(ConnectionState::Connected));
+        device.stop();
+    }
+
+    #[test]
+    fn late_acks_cover_earlier_datagrams() {
+        // Every ack arrives after the next frame was sent, like while an animation is shown
+        let mut device = FakeDevice::start_with_ack_delay(1024, |_| false, 1);
+        let address = device.address.clone();
+        let mut output = UdpOutput::open(&address).unwrap().with_protocol(Protocol::V3).with_keyframe_interval(1000);
+        output.ack_timeout = Duration::from_millis(100);
+        output.disconnect_timeout = Duration::from_millis(300);
+
+        for x in 0..80 {
+            output.render_bitmap(bitmap_with_square(x, 20)).unwrap();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(output.connection_state(), Some(ConnectionState::Connected));
+        // Only the first frame was a keyframe
+        assert_eq!(output.frames_since_keyframe, 79);
+        device.stop();
+    }
+
+    #[test]
+    fn missing_acks_mean_disconnected() {
+        let mut device = FakeDevice::start(1024, |_| false);
+        let address = device.address.clone();
+        let mut output = UdpOutput::open(&address).unwrap().with_protocol(Protocol::V3);
+        output.ack_timeout = Duration::from_millis(10);
+        output.disconnect_timeout = Duration::from_millis(100);
+
+        let bitmap = bitmap_with_square(0, 0);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while output.connection_state() != Some(ConnectionState::Connected) {
+            assert!(Instant::now() < deadline, "never connected");
+            output.render_bitmap(bitmap.clone()).unwrap();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        device.stop();
+        output.render_bitmap(bitmap_with_square(1, 0)).unwrap();
+        std::thread::sleep(Duration::from_millis(150));
+        output.render_bitmap(bitmap_with_square(1, 0)).unwrap();
+        assert_eq!(output.connection_state(), Some(ConnectionState::Disconnected));
+    }
+}
@@ -3,9 +3,6 @@ use graphics::components::Widget;
 use graphics::rendering;
 use crate::media_provider::MediaProvider;
 
-const PLAY_PAUSE_DURATION: std::time::Duration = std::time::Duration::from_millis(1000);
-const TRACK_INFO_DURATION: std::time::Duration = std::time::Duration::from_millis(3000);
-
 /// Temporarily shows media information when the song changes or it is paused/unpaused.
 pub struct MediaOverlay<M: MediaProvider> {
     media_provider: M,
@@ -17,14 +14,16 @@ pub struct MediaOverlay<M: MediaProvider> {
 }
 
 impl<M: MediaProvider> MediaOverlay<M> {
-    pub fn new(provider: M) -> Self {
+    /// `play_pause_duration` is how long to show the play/pause icon, `track_info_duration` how long to show
+    /// the new track when it changes.
+    pub fn new(provider: M, play_pause_duration: std::time::Duration, track_info_duration: std::time::Duration) -> Self {
         let last_track_name = provider.track_name();
         let last_artist = provider.artist();
         let last_paused = provider.paused();
         Self {
             media_provider: provider,
-            play_pause: PlayPauseOverlay::new(PLAY_PAUSE_DURATION),
-            track_info: TrackInfoOverlay::new(TRACK_INFO_DURATION),
+            play_pause: PlayPauseOverlay::new(play_pause_duration),
+            track_info: TrackInfoOverlay::new(track_info_duration),
             last_track_name,
             last_artist,
             last_paused,
@@ -34,10 +33,9 @@ impl<M: MediaProvider> MediaOverlay<M> {
 
 impl<M: MediaProvider> components::Drawable for MediaOverlay<M> {
     fn draw(&mut self, canvas: &mut crate::rendering::Bitmap, bounds: components::Bounds, elapsed: &std::time::Duration) {
-        if !self.media_provider.track_name_is(&None) && !self.media_provider.artist_is(&None) {
-            if !self.media_provider.track_name_is(&self.last_track_name) || !self.media_provider.artist_is(&self.last_artist) {
-                self.track_info.show(self.media_provider.track_name().as_ref().unwrap(), self.media_provider.artist().as_ref().unwrap());
-            }
+        if !self.media_provider.track_name_is(&None) && !self.media_provider.artist_is(&None)
+            && (!self.media_provider.track_name_is(&self.last_track_name) || !self.media_provider.artist_is(&self.last_artist)) {
+            self.track_info.show(self.media_provider.track_name().as_ref().unwrap(), self.media_provider.artist().as_ref().unwrap());
         }
         if !self.media_provider.track_name_is(&self.last_track_name) {
             self.last_track_name = self.media_provider.track_name();
@@ -78,8 +76,8 @@ impl TrackInfoOverlay {
     }
 
     pub fn show(&mut self, title: &str, artist: &str) {
-        self.title.set_text(&title);
-        self.artist.set_text(&artist);
+        self.title.set_text(title);
+        self.artist.set_text(artist);
         self.time = self.duration;
     }
 }
@@ -149,3 +147,33 @@ impl components::Drawable for PlayPauseOverlay {
         widget.draw(canvas, widget_bounds, elapsed);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use crate::media_provider::FakeMediaProvider;
+
+    #[test]
+    fn changes_are_shown_temporarily() {
+        let provider = Rc::new(Mutex::new(FakeMediaProvider {
+            track_name: Some("First".to_string()),
+            artist: Some("Artist".to_string()),
+            paused: Some(false),
+        }));
+        let mut overlay = MediaOverlay::new(Rc::clone(&provider), Duration::from_millis(1000), Duration::from_millis(3000));
+        graphics::snapshot!("media_overlay_unchanged", 128, 64).assert_matches(&mut overlay);
+
+        provider.lock().unwrap().paused = Some(true);
+        graphics::snapshot!("media_overlay_paused", 128, 64)
+            .with_interval(Duration::from_millis(600), 3)
+            .assert_matches(&mut overlay);
+
+        provider.lock().unwrap().track_name = Some("Second".to_string());
+        graphics::snapshot!("media_overlay_track_changed", 128, 64)
+            .with_interval(Duration::from_millis(1000), 5)
+            .assert_matches(&mut overlay);
+    }
+}
@@ -1,5 +1,7 @@
 mod screensaver;
 mod media;
+mod notification;
 
 pub use media::*;
+pub use notification::*;
 pub use screensaver::*;
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use graphics::components::{self, Alignment, ScrollingTextWidget, TextWidget, Widget};
+use graphics::rendering;
+
+/// Temporarily shows a line of text over the screens, scrolling it if it doesn't fit.
+pub struct NotificationOverlay {
+    text: ScrollingTextWidget<'static>,
+    time: Duration,
+}
+
+impl NotificationOverlay {
+    pub fn new() -> Self {
+        Self {
+            text: ScrollingTextWidget::new("".to_string(), &crate::fonts::PIXELLARI, 16.0, Alignment::Center, Duration::from_millis(500), Duration::from_millis(500)),
+            time: Duration::ZERO,
+        }
+    }
+
+    /// Shows `text` for `duration`, replacing the current notification if there is one.
+    pub fn show(&mut self, text: &str, duration: Duration) {
+        self.text.set_text(text);
+        self.time = duration;
+    }
+}
+
+impl components::Drawable for NotificationOverlay {
+    fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: components::Bounds, elapsed: &Duration) {
+        self.time = self.time.saturating_sub(*elapsed);
+        if self.time.is_zero() {
+            return;
+        }
+        canvas.clear();
+        let text_bounds = components::EmptyBounds::new()
+            .with_width(bounds.size.width).with_height(self.text.size().height)
+            .center_in(&bounds);
+        self.text.draw(canvas, text_bounds, elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_are_shown_temporarily() {
+        let mut overlay = NotificationOverlay::new();
+        overlay.show("Build passed", Duration::from_millis(1500));
+        graphics::snapshot!("notification_overlay", 64, 32)
+            .with_frames(&[Duration::ZERO, Duration::from_millis(600), Duration::from_millis(300), Duration::from_millis(700)])
+            .assert_matches(&mut overlay);
+    }
+}
@@ -9,7 +9,7 @@ pub struct ScreensaverOverlay {
 }
 
 impl ScreensaverOverlay {
-    pub fn new() -> Self {
+    pub fn new(fps: f32) -> Self {
         let raw_bytes: Vec<(&[u8], bool)> = vec![
             (include_bytes!("../../resources/gifs/fishy.gif"), false),
             (include_bytes!("../../resources/gifs/ghost.gif"), false),
@@ -20,7 +20,7 @@ impl ScreensaverOverlay {
             (include_bytes!("../../resources/gifs/cyclist.gif"), true),
         ];
         let videos = raw_bytes.into_iter().map(|(bytes, invert)| Video::from_gif(bytes, invert).unwrap());
-        let widgets = videos.map(|vid| VideoWidget::new(vid, 20.0));
+        let widgets = videos.map(|vid| VideoWidget::new(vid, fps));
         Self {
             videos: widgets.collect(),
             current_video: None,
@@ -36,12 +36,14 @@ impl ScreensaverOverlay {
     pub fn hide(&mut self) {
         self.current_video = None;
     }
+    pub fn is_shown(&self) -> bool {
+        self.current_video.is_some()
+    }
 }
 
 impl Drawable for ScreensaverOverlay {
     fn draw(&mut self, canvas: &mut crate::rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
         if let Some(current_video) = self.current_video {
-            canvas.clear();
             let active_video = self.videos.get_mut(current_video).unwrap();
             let video_bounds = EmptyBounds::new().with_size(active_video.size()).center_in(&bounds);
             active_video.draw(canvas, video_bounds, elapsed);
@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+#[cfg(windows)]
+mod openhardwaremonitor;
+#[cfg(target_os = "linux")]
+mod procfs;
+
+#[cfg(windows)]
+pub use self::openhardwaremonitor::WmiSource;
+#[cfg(target_os = "linux")]
+pub use self::procfs::ProcfsSource;
+
+const UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// How many updates [PerformanceHistory] keeps, i.e. a minute's worth
+pub const HISTORY_LENGTH: usize = 60;
+
+#[derive(Default, Clone, Copy)]
+pub struct PerformanceStatistics {
+   pub memory_usage: f32,
+   pub cpu_usage_group_1: f32,
+   pub cpu_usage_group_2: f32,
+   pub cpu_temperature: f32,
+   pub gpu_usage: f32,
+   pub vram_usage: f32,
+   pub gpu_temperature: f32,
+}
+
+/// The statistics from the last few updates, oldest first. Once full, each update replaces the oldest one.
+pub struct PerformanceHistory {
+    samples: VecDeque<PerformanceStatistics>,
+    capacity: usize,
+}
+
+impl PerformanceHistory {
+    pub fn new(capacity: usize) -> Self {
+        PerformanceHistory { samples: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    pub fn push(&mut self, stats: PerformanceStatistics) {
+        if self.capacity == 0 { return; }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(stats);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// One of the statistics over time, e.g. `history.series(|stats| stats.gpu_usage)`.
+    pub fn series<'a, F: Fn(&PerformanceStatistics) -> f32 + 'a>(&'a self, value: F) -> impl Iterator<Item = f32> + 'a {
+        self.samples.iter().map(value)
+    }
+}
+
+/// Somewhere we can read performance statistics from, e.g. an operating system API.
+pub trait StatisticsSource {
+    /// Reads the current values from the source and writes them to `stats`.
+    ///
+    /// Values the source can't provide should be left untouched.
+    fn update(&mut self, stats: &mut PerformanceStatistics) -> anyhow::Result<()>;
+}
+
+// Periodically updates performance statistics from a [StatisticsSource] on a background thread.
+pub struct PerformanceMonitor {
+    // We could avoid the mutex if there were atomic floats
+    stats: Arc<Mutex<PerformanceStatistics>>,
+    history: Arc<Mutex<PerformanceHistory>>,
+    _update_thread: std::thread::JoinHandle<()>,
+}
+
+impl PerformanceMonitor {
+    /// Creates a monitor using the default source for the current platform.
+    ///
+    /// On windows this uses openhardwaremonitor and wmi. Openhardwaremonitor must be running.
+    #[cfg(windows)]
+    pub fn new() -> Self {
+        Self::with_source(|| WmiSource::new().unwrap())
+    }
+    /// Creates a monitor using the default source for the current platform.
+    ///
+    /// On linux this reads from /proc and /sys.
+    #[cfg(target_os = "linux")]
+    pub fn new() -> Self {
+        Self::with_source(ProcfsSource::new)
+    }
+
+    /// Creates a monitor reading from the source returned by `create_source`.
+    ///
+    /// The source is created on the update thread, so it doesn't need to be [Send] itself.
+    pub fn with_source<S, F>(create_source: F) -> Self
+        where S: StatisticsSource,
+              F: FnOnce() -> S + Send + 'static {
+        let stats = Arc::new(Mutex::new(Default::default()));
+        let history = Arc::new(Mutex::new(PerformanceHistory::new(HISTORY_LENGTH)));
+        PerformanceMonitor {
+            stats: stats.clone(),
+            history: history.clone(),
+            _update_thread: std::thread::spawn(move || {
+                let mut source = create_source();
+                loop {
+                    update_stats(&stats, &history, &mut source);
+                    std::thread::sleep(UPDATE_INTERVAL);
+                }
+            }),
+        }
+    }
+
+    pub fn statistics(&self) -> Arc<Mutex<PerformanceStatistics>> {
+        self.stats.clone()
+    }
+
+    /// The statistics from the last [HISTORY_LENGTH] updates.
+    pub fn history(&self) -> Arc<Mutex<PerformanceHistory>> {
+        self.history.clone()
+    }
+}
+
+fn update_stats<S: StatisticsSource>(stats: &Arc<Mutex<PerformanceStatistics>>, history: &Arc<Mutex<PerformanceHistory>>, source: &mut S) {
+    // Read into a copy so the lock isn't held while the source does I/O
+    let mut new_stats = *stats.lock().unwrap();
+    if let Err(e) = source.update(&mut new_stats) {
+        println!("Failed to update performance statistics: {:?}", e);
+        return;
+    }
+    *stats.lock().unwrap() = new_stats;
+    history.lock().unwrap().push(new_stats);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingSource(f32);
+
+    impl StatisticsSource for CountingSource {
+        fn update(&mut self, stats: &mut PerformanceStatistics) -> anyhow::Result<()> {
+            self.0 += 1.0;
+            stats.cpu_usage_group_1 = self.0;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn history_keeps_the_latest_updates() {
+        let stats = Arc::new(Mutex::new(PerformanceStatistics::default()));
+        let history = Arc::new(Mutex::new(PerformanceHistory::new(3)));
+        let mut source = CountingSource(0.0);
+        for _ in 0..5 {
+            update_stats(&stats, &history, &mut source);
+        }
+        let history = history.lock().unwrap();
+        assert_eq!(history.series(|stats| stats.cpu_usage_group_1).collect::<Vec<_>>(), [3.0, 4.0, 5.0]);
+        assert_eq!(history.series(|stats| stats.cpu_usage_group_1).last(), Some(stats.lock().unwrap().cpu_usage_group_1));
+    }
+}
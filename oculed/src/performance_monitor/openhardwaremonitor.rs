@@ -0,0 +1,73 @@
+use windows::Win32::System::SystemInformation;
+use std::ops::Range;
+use wmi::{COMLibrary, WMIConnection, Variant};
+use std::collections::HashMap;
+
+use super::{PerformanceStatistics, StatisticsSource};
+
+// i7-12700k. We could probably fetch this information from the system.
+const P_CORES: u32 = 8;
+
+/// Reads statistics using openhardwaremonitor and wmi. Openhardwaremonitor must be running.
+pub struct WmiSource {
+    wmi_con: WMIConnection,
+}
+
+impl WmiSource {
+    pub fn new() -> anyhow::Result<Self> {
+        let com_con = COMLibrary::without_security()?;
+        let wmi_con = WMIConnection::with_namespace_path(r"root\OpenHardwareMonitor", com_con.into())?;
+        Ok(Self { wmi_con })
+    }
+}
+
+impl StatisticsSource for WmiSource {
+    fn update(&mut self, stats: &mut PerformanceStatistics) -> anyhow::Result<()> {
+        let wmi_con = &self.wmi_con;
+        let (cpu_1, cpu_2) = get_cpu_usage(wmi_con)?;
+        stats.memory_usage = get_ram_usage();
+        stats.cpu_usage_group_1 = cpu_1;
+        stats.cpu_usage_group_2 = cpu_2;
+        stats.cpu_temperature = get_openhardwaremonitor_sensor_value(wmi_con, "CPU Package", "Temperature").unwrap_or_default();
+        stats.gpu_usage = get_openhardwaremonitor_sensor_value(wmi_con, "GPU Core", "Load").unwrap_or_default() / 100.0;
+        stats.vram_usage = get_openhardwaremonitor_sensor_value(wmi_con, "GPU Memory", "Load").unwrap_or_default() / 100.0;
+        stats.gpu_temperature = get_openhardwaremonitor_sensor_value(wmi_con, "GPU Core", "Temperature").unwrap_or_default();
+        Ok(())
+    }
+}
+
+fn get_openhardwaremonitor_sensor_value(wmi_con: &WMIConnection, sensor_name: &str, sensor_type: &str) -> anyhow::Result<f32> {
+    let results: Vec<HashMap<String, Variant>> = wmi_con.raw_query(format!("SELECT Value FROM Sensor WHERE Name='{}' AND SensorType='{}'", sensor_name, sensor_type))?;
+    if results.len() == 0 {
+        return Err(anyhow::anyhow!(format!("No sensor '{}' of type '{}'.", sensor_name, sensor_type)));
+    }
+    let val = results[0].get("Value").ok_or(anyhow::anyhow!(format!("Found no value for sensor '{}'", sensor_name)))?;
+    return match val {
+        wmi::Variant::R4(fval) => Ok(*fval),
+        x => Err(anyhow::anyhow!("Invalid WMI value. Expected float, found {:?}", x)),
+    };
+}
+
+/// Returns CPU usage separated by P-cores and E-cores
+fn get_cpu_usage(wmi_con: &WMIConnection) -> anyhow::Result<(f32, f32)> {
+    let get_core_usage = |cores: Range<u32>| {
+        let (core_count, usage_sum) = cores.map(|core| {
+            get_openhardwaremonitor_sensor_value(wmi_con, &format!("CPU Core #{}", core + 1), "Load").map(|val| val / 100.0)
+        })
+        .take_while(Result::is_ok).map(Result::unwrap)
+        .fold((0.0, 0.0), |(count, sum), val| (count + 1.0, sum + val));
+        usage_sum / core_count
+    };
+    let usage_p = get_core_usage(0..P_CORES);
+    let usage_e = get_core_usage(P_CORES..(u32::MAX));
+    Ok((usage_p, usage_e))
+}
+
+fn get_ram_usage() -> f32 {
+    let mut mem_info: SystemInformation::MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+    mem_info.dwLength = std::mem::size_of::<SystemInformation::MEMORYSTATUSEX>() as u32;
+    unsafe {
+        SystemInformation::GlobalMemoryStatusEx(&mut mem_info);
+    }
+    return mem_info.dwMemoryLoad as f32 / 100.0;
+}
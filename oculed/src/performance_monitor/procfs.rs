@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use super::{PerformanceStatistics, StatisticsSource};
+
+/// hwmon drivers reporting cpu temperatures, with the sensor label to prefer for each.
+const CPU_HWMON_DRIVERS: [(&str, &str); 4] = [
+    ("coretemp", "Package id 0"),
+    ("k10temp", "Tctl"),
+    ("zenpower", "Tdie"),
+    ("cpu_thermal", ""),
+];
+/// hwmon drivers reporting gpu temperatures, with the sensor label to prefer for each.
+const GPU_HWMON_DRIVERS: [(&str, &str); 3] = [
+    ("amdgpu", "edge"),
+    ("nouveau", ""),
+    ("radeon", ""),
+];
+
+/// Reads statistics from the linux /proc and /sys filesystems.
+///
+/// CPU usage is split into P-cores and E-cores on hybrid intel cpus. On other cpus both groups report the total usage.
+/// GPU usage is only available for drivers exposing it through sysfs (i.e. amdgpu).
+pub struct ProcfsSource {
+    proc_root: PathBuf,
+    sys_root: PathBuf,
+    /// Logical cpus in the first and second group. [None] means all cpus.
+    cpu_groups: (Option<Vec<usize>>, Option<Vec<usize>>),
+    /// Cpu times from the previous update, by logical cpu
+    previous_times: HashMap<usize, CpuTimes>,
+}
+
+impl ProcfsSource {
+    pub fn new() -> Self {
+        Self::with_roots("/proc", "/sys")
+    }
+
+    /// Creates a source reading from the given directories instead of /proc and /sys.
+    pub fn with_roots<P: Into<PathBuf>, S: Into<PathBuf>>(proc_root: P, sys_root: S) -> Self {
+        let sys_root = sys_root.into();
+        let read_cpu_list = |name: &str| {
+            std::fs::read_to_string(sys_root.join("devices").join(name).join("cpus")).ok()
+                .and_then(|list| parse_cpu_list(&list))
+        };
+        let cpu_groups = match (read_cpu_list("cpu_core"), read_cpu_list("cpu_atom")) {
+            (Some(p_cores), Some(e_cores)) => (Some(p_cores), Some(e_cores)),
+            _ => (None, None),
+        };
+        Self {
+            proc_root: proc_root.into(),
+            sys_root,
+            cpu_groups,
+            previous_times: HashMap::new(),
+        }
+    }
+
+    fn update_cpu_usage(&mut self, stats: &mut PerformanceStatistics) -> anyhow::Result<()> {
+        let path = self.proc_root.join("stat");
+        let contents = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let times = parse_proc_stat(&contents);
+        if times.is_empty() {
+            return Err(anyhow::anyhow!("Found no cpus in {:?}", path));
+        }
+
+        let usage = |group: &Option<Vec<usize>>| {
+            let (busy, total) = times.iter()
+                .filter(|(cpu, _)| group.as_ref().map(|cpus| cpus.contains(cpu)).unwrap_or(true))
+                .map(|(cpu, now)| now.since(&self.previous_times.get(cpu).copied().unwrap_or_default()))
+                .fold((0u64, 0u64), |(busy, total), delta| (busy + delta.busy(), total + delta.total()));
+            if total == 0 { 0.0 } else { busy as f32 / total as f32 }
+        };
+        stats.cpu_usage_group_1 = usage(&self.cpu_groups.0);
+        stats.cpu_usage_group_2 = usage(&self.cpu_groups.1);
+
+        self.previous_times = times;
+        Ok(())
+    }
+
+    fn update_memory_usage(&self, stats: &mut PerformanceStatistics) -> anyhow::Result<()> {
+        let path = self.proc_root.join("meminfo");
+        let contents = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let field = |name: &str| {
+            contents.lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+                .ok_or_else(|| anyhow::anyhow!("Found no '{}' in {:?}", name, path))
+        };
+        let total = field("MemTotal")?;
+        let available = field("MemAvailable")?;
+        if total > 0 {
+            stats.memory_usage = 1.0 - available as f32 / total as f32;
+        }
+        Ok(())
+    }
+
+    fn update_hwmon(&self, stats: &mut PerformanceStatistics) {
+        let Ok(entries) = std::fs::read_dir(self.sys_root.join("class").join("hwmon")) else { return };
+        let mut hwmons = entries.filter_map(|entry| entry.ok().map(|e| e.path())).collect::<Vec<PathBuf>>();
+        // read_dir order is arbitrary, this makes us pick the same sensor every time
+        hwmons.sort();
+
+        if let Some(temp) = find_hwmon(&hwmons, &CPU_HWMON_DRIVERS).and_then(|(hwmon, label)| read_temperature(hwmon, label)) {
+            stats.cpu_temperature = temp;
+        }
+        if let Some((hwmon, label)) = find_hwmon(&hwmons, &GPU_HWMON_DRIVERS) {
+            if let Some(temp) = read_temperature(hwmon, label) {
+                stats.gpu_temperature = temp;
+            }
+            let device = hwmon.join("device");
+            if let Some(busy) = read_number(&device.join("gpu_busy_percent")) {
+                stats.gpu_usage = busy as f32 / 100.0;
+            }
+            if let (Some(used), Some(total)) = (read_number(&device.join("mem_info_vram_used")), read_number(&device.join("mem_info_vram_total"))) {
+                if total > 0 {
+                    stats.vram_usage = used as f32 / total as f32;
+                }
+            }
+        }
+    }
+}
+
+impl StatisticsSource for ProcfsSource {
+    fn update(&mut self, stats: &mut PerformanceStatistics) -> anyhow::Result<()> {
+        self.update_cpu_usage(stats)?;
+        self.update_memory_usage(stats)?;
+        self.update_hwmon(stats);
+        Ok(())
+    }
+}
+
+/// Time spent by a cpu in different states, in clock ticks.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+struct CpuTimes {
+    idle: u64,
+    total: u64,
+}
+
+impl CpuTimes {
+    fn since(&self, earlier: &CpuTimes) -> CpuTimes {
+        CpuTimes {
+            idle: self.idle.saturating_sub(earlier.idle),
+            total: self.total.saturating_sub(earlier.total),
+        }
+    }
+    fn busy(&self) -> u64 {
+        self.total.saturating_sub(self.idle)
+    }
+    fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+/// Parses the per-cpu lines (`cpuN ...`) of /proc/stat.
+fn parse_proc_stat(contents: &str) -> HashMap<usize, CpuTimes> {
+    contents.lines().filter_map(|line| {
+        let mut fields = line.split_whitespace();
+        let cpu = fields.next()?.strip_prefix("cpu")?.parse::<usize>().ok()?;
+        // user nice system idle iowait irq softirq steal. guest time is already included in user.
+        let values = fields.take(8).map(|f| f.parse::<u64>().ok()).collect::<Option<Vec<u64>>>()?;
+        if values.len() < 4 {
+            return None;
+        }
+        let idle = values[3] + values.get(4).copied().unwrap_or(0);
+        Some((cpu, CpuTimes { idle, total: values.iter().sum() }))
+    }).collect()
+}
+
+/// Parses a sysfs cpu list such as `0-3,8,10-11`.
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => cpus.extend(start.parse::<usize>().ok()?..=end.parse::<usize>().ok()?),
+            None => cpus.push(part.parse().ok()?),
+        }
+    }
+    if cpus.is_empty() { None } else { Some(cpus) }
+}
+
+/// Finds the first hwmon directory using one of `drivers`, and the sensor label to prefer for it.
+fn find_hwmon<'a>(hwmons: &'a [PathBuf], drivers: &[(&str, &'static str)]) -> Option<(&'a PathBuf, &'static str)> {
+    hwmons.iter().find_map(|hwmon| {
+        let name = read_trimmed(&hwmon.join("name"))?;
+        drivers.iter().find(|(driver, _)| *driver == name).map(|(_, label)| (hwmon, *label))
+    })
+}
+
+/// Reads a temperature in degrees celsius from a hwmon directory.
+///
+/// Uses the sensor labelled `preferred_label` if there is one, otherwise the first sensor.
+fn read_temperature(hwmon: &Path, preferred_label: &str) -> Option<f32> {
+    let labelled = (1..=32).find(|i| {
+        read_trimmed(&hwmon.join(format!("temp{}_label", i))).map(|label| label == preferred_label).unwrap_or(false)
+    });
+    let sensor = labelled.unwrap_or(1);
+    read_number(&hwmon.join(format!("temp{}_input", sensor))).map(|millidegrees| millidegrees as f32 / 1000.0)
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_number(path: &Path) -> Option<u64> {
+    read_trimmed(path)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory tree under the system temp directory, removed when dropped.
+    struct Fixture {
+        root: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("oculed-procfs-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root).unwrap();
+            Fixture { root }
+        }
+        fn write(&self, path: &str, contents: &str) {
+            let path = self.root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        fn source(&self) -> ProcfsSource {
+            ProcfsSource::with_roots(self.root.join("proc"), self.root.join("sys"))
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    const MEMINFO: &str = "MemTotal:       16000000 kB\nMemFree:         2000000 kB\nMemAvailable:    4000000 kB\n";
+
+    #[test]
+    fn cpu_usage_is_computed_between_updates() {
+        let fixture = Fixture::new("cpu");
+        fixture.write("proc/meminfo", MEMINFO);
+        fixture.write("proc/stat", "cpu  200 0 200 1600 0 0 0 0 0 0\ncpu0 100 0 100 800 0 0 0 0 0 0\ncpu1 100 0 100 800 0 0 0 0 0 0\nintr 1234\n");
+        let mut source = fixture.source();
+        let mut stats = PerformanceStatistics::default();
+        source.update(&mut stats).unwrap();
+        assert_eq!(stats.cpu_usage_group_1, 0.2);
+
+        // cpu0 fully busy, cpu1 idle (half of it in iowait)
+        fixture.write("proc/stat", "cpu  300 0 200 1700 0 0 0 0 0 0\ncpu0 200 0 100 800 0 0 0 0 0 0\ncpu1 100 0 100 850 50 0 0 0 0 0\n");
+        source.update(&mut stats).unwrap();
+        assert_eq!(stats.cpu_usage_group_1, 0.5);
+        assert_eq!(stats.cpu_usage_group_2, 0.5);
+    }
+
+    #[test]
+    fn hybrid_cpus_are_split_into_groups() {
+        let fixture = Fixture::new("hybrid");
+        fixture.write("proc/meminfo", MEMINFO);
+        fixture.write("sys/devices/cpu_core/cpus", "0-1\n");
+        fixture.write("sys/devices/cpu_atom/cpus", "2\n");
+        fixture.write("proc/stat", "cpu0 0 0 0 0\ncpu1 0 0 0 0\ncpu2 0 0 0 0\n");
+        let mut source = fixture.source();
+        let mut stats = PerformanceStatistics::default();
+        source.update(&mut stats).unwrap();
+
+        fixture.write("proc/stat", "cpu0 100 0 0 0\ncpu1 50 0 0 50\ncpu2 25 0 0 75\n");
+        source.update(&mut stats).unwrap();
+        assert_eq!(stats.cpu_usage_group_1, 0.75);
+        assert_eq!(stats.cpu_usage_group_2, 0.25);
+    }
+
+    #[test]
+    fn memory_usage_uses_available_memory() {
+        let fixture = Fixture::new("mem");
+        fixture.write("proc/stat", "cpu0 0 0 0 0\n");
+        fixture.write("proc/meminfo", MEMINFO);
+        let mut stats = PerformanceStatistics::default();
+        fixture.source().update(&mut stats).unwrap();
+        assert_eq!(stats.memory_usage, 0.75);
+    }
+
+    #[test]
+    fn missing_meminfo_is_an_error() {
+        let fixture = Fixture::new("nomem");
+        fixture.write("proc/stat", "cpu0 0 0 0 0\n");
+        let mut stats = PerformanceStatistics::default();
+        assert!(fixture.source().update(&mut stats).is_err());
+    }
+
+    #[test]
+    fn temperatures_are_read_from_hwmon() {
+        let fixture = Fixture::new("hwmon");
+        fixture.write("proc/stat", "cpu0 0 0 0 0\n");
+        fixture.write("proc/meminfo", MEMINFO);
+        fixture.write("sys/class/hwmon/hwmon0/name", "acpitz\n");
+        fixture.write("sys/class/hwmon/hwmon0/temp1_input", "27800\n");
+        fixture.write("sys/class/hwmon/hwmon1/name", "coretemp\n");
+        fixture.write("sys/class/hwmon/hwmon1/temp1_label", "Core 0\n");
+        fixture.write("sys/class/hwmon/hwmon1/temp1_input", "41000\n");
+        fixture.write("sys/class/hwmon/hwmon1/temp2_label", "Package id 0\n");
+        fixture.write("sys/class/hwmon/hwmon1/temp2_input", "45000\n");
+        fixture.write("sys/class/hwmon/hwmon2/name", "amdgpu\n");
+        fixture.write("sys/class/hwmon/hwmon2/temp1_input", "52000\n");
+        fixture.write("sys/class/hwmon/hwmon2/device/gpu_busy_percent", "30\n");
+        fixture.write("sys/class/hwmon/hwmon2/device/mem_info_vram_used", "1024\n");
+        fixture.write("sys/class/hwmon/hwmon2/device/mem_info_vram_total", "4096\n");
+
+        let mut stats = PerformanceStatistics::default();
+        fixture.source().update(&mut stats).unwrap();
+        assert_eq!(stats.cpu_temperature, 45.0);
+        assert_eq!(stats.gpu_temperature, 52.0);
+        assert_eq!(stats.gpu_usage, 0.3);
+        assert_eq!(stats.vram_usage, 0.25);
+    }
+
+    #[test]
+    fn cpu_lists_are_parsed() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n"), Some(vec![0, 1, 2, 3, 8, 10, 11]));
+        assert_eq!(parse_cpu_list(""), None);
+        assert_eq!(parse_cpu_list("a-b"), None);
+    }
+}
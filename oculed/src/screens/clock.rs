@@ -10,24 +10,32 @@ use super::Screen;
 pub struct ClockScreen<'a> {
     clock_widget: components::SimpleTextWidget<'a>,
     date_widget:  components::SimpleTextWidget<'a>,
+    /// See [chrono::format::strftime]
+    format: String,
+    /// No date is shown if this is [None]
+    date_format: Option<String>,
 }
 
 impl ClockScreen<'static> {
-    pub fn new() -> Self {
+    pub fn new(format: String, date_format: Option<String>) -> Self {
         ClockScreen {
             clock_widget: components::SimpleTextWidget::new("".to_string(), &fonts::ROADRAGE, 30.0),
             // clock_widget: components::SimpleTextWidget::new("".to_string(), &fonts::SYMTEXT, 30.0),
             // clock_widget: components::SimpleTextWidget::new("".to_string(), &fonts::ELFBOY, 56.0),
             // clock_widget: components::SimpleTextWidget::new("".to_string(), &fonts::ROBOTO, 36.0),
             date_widget: components::SimpleTextWidget::new("".to_string(), &fonts::SYMTEXT, 10.0),
+            format,
+            date_format,
         }
     }
     fn update(&mut self, _elapsed: &std::time::Duration) {
         let now = Local::now();
-        let clock_text = now.format("%H:%M").to_string();
+        let clock_text = now.format(&self.format).to_string();
         self.clock_widget.set_text(&clock_text);
-        // let date_text = now.format("%d %b %y").to_string();
-        // self.date_widget.set_text(date_text);
+        if let Some(date_format) = &self.date_format {
+            let date_text = now.format(date_format).to_string();
+            self.date_widget.set_text(&date_text);
+        }
     }
 }
 
@@ -35,22 +43,42 @@ impl components::Drawable for ClockScreen<'static> {
     fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: components::Bounds, elapsed: &std::time::Duration) {
         self.update(elapsed);
 
+        // Both are placed by their baselines, so they don't move when the text gets glyphs that reach higher or lower
         {
+            let metrics = self.clock_widget.metrics();
+            let baseline = bounds.pos.y + ((bounds.size.height as f32 + metrics.ascent - metrics.descent) / 2.0).round() as i32;
             let clock_bounds = components::EmptyBounds::new()
                 .with_size(self.clock_widget.size())
-                .center_in(&bounds);
+                .center_hor_in(&bounds)
+                .with_baseline_at(baseline, self.clock_widget.baseline());
             self.clock_widget.draw(canvas, clock_bounds, elapsed);
         }
-        // {
-        //     let date_bounds = EmptyBounds::new()
-        //         .with_size(self.date_widget.size())
-        //         .align_bottom(&canvas_bounds).center_hor_in(&bounds);
-        //     self.date_widget.draw(canvas, date_bounds, elapsed);
-        // }
+        if self.date_format.is_some() {
+            let baseline = bounds.bottom() - self.date_widget.metrics().descent.ceil() as i32;
+            let date_bounds = components::EmptyBounds::new()
+                .with_size(self.date_widget.size())
+                .center_hor_in(&bounds)
+                .with_baseline_at(baseline, self.date_widget.baseline());
+            self.date_widget.draw(canvas, date_bounds, elapsed);
+        }
     }
 }
 
 impl Screen for ClockScreen<'static> {
     fn on_mount(&mut self) {}
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_snapshot() {
+        // Formats without specifiers are printed as is, which keeps the snapshot stable
+        let mut clock = ClockScreen::new("12:34".to_string(), None);
+        graphics::snapshot!("clock", 128, 64).assert_matches(&mut clock);
+        let mut clock_with_date = ClockScreen::new("12:34".to_string(), Some("01 Jan".to_string()));
+        graphics::snapshot!("clock_with_date", 128, 64).assert_matches(&mut clock_with_date);
+    }
+}
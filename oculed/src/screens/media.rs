@@ -75,3 +75,34 @@ impl<M: MediaProvider> Drawable for MediaScreen<M> {
 impl<M: MediaProvider> Screen for MediaScreen<M> {
     fn on_mount(&mut self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use crate::media_provider::FakeMediaProvider;
+
+    #[test]
+    fn media_snapshot() {
+        let provider = Rc::new(Mutex::new(FakeMediaProvider {
+            track_name: Some("A track name too long to fit".to_string()),
+            artist: Some("Artist".to_string()),
+            paused: Some(false),
+        }));
+        let mut screen = MediaScreen::new(Rc::clone(&provider));
+        graphics::snapshot!("media_playing", 128, 64)
+            .with_frames(&[Duration::ZERO, Duration::from_secs(5), Duration::from_secs(1)])
+            .assert_matches(&mut screen);
+
+        provider.lock().unwrap().paused = Some(true);
+        graphics::snapshot!("media_paused", 128, 64).assert_matches(&mut screen);
+    }
+
+    #[test]
+    fn placeholder_without_media() {
+        let mut screen = MediaScreen::new(FakeMediaProvider::default());
+        graphics::snapshot!("media_none", 128, 64).assert_matches(&mut screen);
+    }
+}
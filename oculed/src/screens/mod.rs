@@ -11,6 +11,18 @@ pub trait Screen : components::Drawable {
     fn on_mount(&mut self);
 }
 
+impl Screen for Box<dyn Screen> {
+    fn on_mount(&mut self) {
+        self.as_mut().on_mount();
+    }
+}
+
+impl<S: Screen> Screen for components::Layer<S> {
+    fn on_mount(&mut self) {
+        self.inner_mut().on_mount();
+    }
+}
+
 /// Holds several [Screen]s and allows cycling between them (see [ScreenCollection::next_screen]).
 pub struct ScreenCollection<'a> {
     screens: Vec<&'a mut dyn Screen>,
@@ -23,7 +35,7 @@ impl<'a> ScreenCollection<'a> {
         let num_screens = screens.len();
         screens[0].on_mount();
         ScreenCollection{
-            screens: screens,
+            screens,
             active_screen: 0,
             indicator: ScreenIndicator::new(num_screens),
         }
@@ -41,6 +53,32 @@ impl<'a> ScreenCollection<'a> {
         self.screens[self.active_screen].on_mount();
         self.indicator.show(prev, true);
     }
+    pub fn active_screen(&self) -> usize {
+        self.active_screen
+    }
+    /// Moves `offset` screens forward, or back if negative, wrapping around at the ends.
+    pub fn move_screens(&mut self, offset: i32) {
+        let index = (self.active_screen as i64 + offset as i64).rem_euclid(self.screens.len() as i64) as usize;
+        if index != self.active_screen {
+            self.show_screen_from(index, offset < 0);
+        }
+    }
+    /// Switches to the screen at `index`. Returns false if there is no such screen.
+    pub fn show_screen(&mut self, index: usize) -> bool {
+        if index >= self.screens.len() {
+            return false;
+        }
+        if index != self.active_screen {
+            self.show_screen_from(index, index < self.active_screen);
+        }
+        true
+    }
+    fn show_screen_from(&mut self, index: usize, to_left: bool) {
+        let prev = self.active_screen;
+        self.active_screen = index;
+        self.screens[self.active_screen].on_mount();
+        self.indicator.show(prev, to_left);
+    }
 }
 
 impl<'a> components::Drawable for ScreenCollection<'a> {
@@ -76,7 +114,7 @@ impl components::Drawable for ScreenIndicator {
         let move_progress_smoothed = (std::f32::consts::FRAC_PI_2 * move_progress).sin();
         let at = move_progress_smoothed * target as f32 + (1.0-move_progress_smoothed) * from as f32;
 
-        let collapse_progress = ((self.elapsed.as_millis() as i32 - self.move_duration.as_millis() as i32 - self.wait_duration.as_millis() as i32) as f32 / self.collapse_duration.as_millis() as f32).min(1.0).max(0.0);
+        let collapse_progress = ((self.elapsed.as_millis() as i32 - self.move_duration.as_millis() as i32 - self.wait_duration.as_millis() as i32) as f32 / self.collapse_duration.as_millis() as f32).clamp(0.0, 1.0);
         let adjusted_width = ((rect_width-4) as f32 * (1.0 - collapse_progress)) as i32;
 
         canvas.draw_rect(at as i32 + (rect_width - adjusted_width) / 2, bounds.size.height as i32 - 1, adjusted_width as usize, 1);
@@ -100,7 +138,7 @@ impl ScreenIndicator {
             move_duration: std::time::Duration::from_millis(600),
             wait_duration: std::time::Duration::from_millis(200),
             collapse_duration: std::time::Duration::from_millis(400),
-            num_screens: num_screens,
+            num_screens,
             from: 0,
             to_left: true,
         }
@@ -114,4 +152,56 @@ impl ScreenIndicator {
         self.from = from;
         self.to_left = to_left;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Fills a square at a position unique to the screen.
+    struct Square(i32);
+
+    impl components::Drawable for Square {
+        fn draw(&mut self, canvas: &mut rendering::Bitmap, _bounds: components::Bounds, _elapsed: &Duration) {
+            canvas.draw_rect(self.0 * 8, 0, 6, 6);
+        }
+    }
+
+    impl Screen for Square {
+        fn on_mount(&mut self) {}
+    }
+
+    #[test]
+    fn indicator_moves_and_collapses() {
+        let (mut first, mut second, mut third) = (Square(0), Square(1), Square(2));
+        let mut screens = ScreenCollection::new(vec![&mut first, &mut second, &mut third]);
+        graphics::snapshot!("screen_collection_initial", 24, 8).assert_matches(&mut screens);
+
+        screens.next_screen();
+        graphics::snapshot!("screen_collection_next", 24, 8)
+            .with_interval(Duration::from_millis(300), 5)
+            .assert_matches(&mut screens);
+
+        // Wrap around from the first screen to the last
+        screens.previous_screen();
+        screens.previous_screen();
+        graphics::snapshot!("screen_collection_wrap", 24, 8)
+            .with_frames(&[Duration::ZERO, Duration::from_millis(300)])
+            .assert_matches(&mut screens);
+    }
+
+    #[test]
+    fn screens_can_be_jumped_to() {
+        let (mut first, mut second, mut third) = (Square(0), Square(1), Square(2));
+        let mut screens = ScreenCollection::new(vec![&mut first, &mut second, &mut third]);
+        screens.move_screens(-1);
+        assert_eq!(screens.active_screen, 2);
+        screens.move_screens(5);
+        assert_eq!(screens.active_screen, 1);
+        assert!(screens.show_screen(0));
+        assert_eq!(screens.active_screen, 0);
+        assert!(!screens.show_screen(3));
+        assert_eq!(screens.active_screen, 0);
+    }
+}
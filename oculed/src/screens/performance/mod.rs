@@ -1,14 +1,14 @@
 mod performance_with_mem;
 mod performance_with_temp;
+mod performance_history;
 
 pub use performance_with_mem::PerformanceWithMemoryScreen;
 pub use performance_with_temp::PerformanceWithTemperatureScreen;
+pub use performance_history::PerformanceHistoryScreen;
 
-use crate::components::{Widget, Bounds, Size, Drawable};
+use crate::components::{Widget, Bounds, Size, Drawable, update_smooth_value};
 use crate::rendering;
 
-const SMOOTHING_FACTOR: f32 = 0.2;
-
 /// A horizontal bar displaying a value in the range [0.0, 1.0]
 struct BarWidget {
     value: f32,
@@ -101,8 +101,3 @@ fn draw_bar_limits(canvas: &mut rendering::Bitmap, bounds: Bounds) {
     canvas.draw_rect(bounds.right() - 5, bounds.bottom() - 1, 4, 1);
     canvas.draw_rect(bounds.right() - 1, bounds.pos.y, 1, bounds.size.height as usize);
 }
-
-fn update_smooth_value(curr_val: f32, target: f32, elapsed: &std::time::Duration) -> f32 {
-    let progress = (elapsed.as_secs_f32() / SMOOTHING_FACTOR).clamp(0.0, 1.0);
-    curr_val * (1.0 - progress) + target * progress
-}
\ No newline at end of file
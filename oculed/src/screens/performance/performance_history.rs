@@ -0,0 +1,94 @@
+use super::super::Screen;
+use crate::components::{Bounds, Drawable, EmptyBounds, GraphScale, GraphStyle, GraphWidget, SimpleTextWidget, Widget};
+use crate::fonts;
+use crate::rendering;
+use crate::performance_monitor;
+use std::sync::{Arc, Mutex};
+
+/**
+ * Displays graphs of the cpu and gpu usage over the last minute
+ */
+pub struct PerformanceHistoryScreen {
+    history: Arc<Mutex<performance_monitor::PerformanceHistory>>,
+    cpu_widgets: (SimpleTextWidget<'static>, GraphWidget),
+    gpu_widgets: (SimpleTextWidget<'static>, GraphWidget),
+}
+
+const TEXT_PADDING: u32 = 5;
+const GRAPH_SPACING: u32 = 4;
+const FONT_SIZE: f32 = 9.0;
+
+impl PerformanceHistoryScreen {
+    pub fn new(history: Arc<Mutex<performance_monitor::PerformanceHistory>>) -> Self {
+        let capacity = history.lock().unwrap().capacity();
+        let graph = || GraphWidget::new(capacity)
+            .with_style(GraphStyle::Area)
+            .with_scale(GraphScale::Fixed { min: 0.0, max: 1.0 })
+            .with_grid_ticks(1);
+        PerformanceHistoryScreen {
+            history,
+            cpu_widgets: (SimpleTextWidget::new("CPU".to_string(), &fonts::PIXELOID, FONT_SIZE), graph()),
+            gpu_widgets: (SimpleTextWidget::new("GPU".to_string(), &fonts::PIXELOID, FONT_SIZE), graph()),
+        }
+    }
+}
+
+impl Drawable for PerformanceHistoryScreen {
+    fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
+        {
+            let history = self.history.lock().unwrap();
+            self.cpu_widgets.1.set_values(history.series(|stats| (stats.cpu_usage_group_1 + stats.cpu_usage_group_2) / 2.0));
+            self.gpu_widgets.1.set_values(history.series(|stats| stats.gpu_usage));
+        }
+
+        let text_width = TEXT_PADDING + self.cpu_widgets.0.size().width.max(self.gpu_widgets.0.size().width);
+        let text_bounds = EmptyBounds::new().with_width(text_width).with_x(bounds.pos.x);
+        let graph_height = bounds.size.height.saturating_sub(GRAPH_SPACING) / 2;
+        let graph_bounds = EmptyBounds::new()
+            .with_height(graph_height)
+            .between_hor(text_bounds.right(), bounds.right());
+
+        for (widgets, graph_bounds) in [
+            (&mut self.cpu_widgets, graph_bounds.align_top(&bounds)),
+            (&mut self.gpu_widgets, graph_bounds.align_bottom(&bounds)),
+        ] {
+            widgets.1.draw(canvas, graph_bounds, elapsed);
+            let label_bounds = text_bounds.with_height(widgets.0.size().height).center_ver_in(&graph_bounds);
+            widgets.0.draw(canvas, label_bounds, elapsed);
+        }
+    }
+}
+
+impl Screen for PerformanceHistoryScreen {
+    fn on_mount(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_is_graphed() {
+        let mut history = performance_monitor::PerformanceHistory::new(performance_monitor::HISTORY_LENGTH);
+        for i in 0..40 {
+            let t = i as f32 / 40.0;
+            history.push(performance_monitor::PerformanceStatistics {
+                cpu_usage_group_1: t,
+                cpu_usage_group_2: t * t,
+                gpu_usage: if i % 10 < 5 { 0.9 } else { 0.2 },
+                ..Default::default()
+            });
+        }
+        let mut screen = PerformanceHistoryScreen::new(Arc::new(Mutex::new(history)));
+        graphics::snapshot!("performance_history", 128, 64).assert_matches(&mut screen);
+    }
+
+    #[test]
+    fn tiny_displays_are_drawn() {
+        let history = performance_monitor::PerformanceHistory::new(performance_monitor::HISTORY_LENGTH);
+        let mut screen = PerformanceHistoryScreen::new(Arc::new(Mutex::new(history)));
+        let mut canvas = rendering::Bitmap::new(32, 2);
+        let bounds = Bounds::cover_bitmap(&canvas);
+        screen.draw(&mut canvas, bounds, &std::time::Duration::ZERO);
+    }
+}
@@ -27,7 +27,7 @@ const FONT_SIZE: f32 = 9.0;
 impl PerformanceWithMemoryScreen {
     pub fn new(stats: Arc<Mutex<performance_monitor::PerformanceStatistics>>) -> Self {
         PerformanceWithMemoryScreen {
-            stats: stats,
+            stats,
             cpu_widgets: (SimpleTextWidget::new("CPU".to_string(), &fonts::PIXELOID, FONT_SIZE), DoubleBarWidget::new()),
             mem_widgets: (SimpleTextWidget::new("MEM".to_string(), &fonts::PIXELOID, FONT_SIZE), BarWidget::new()),
             gpu_widgets: (SimpleTextWidget::new("GPU".to_string(), &fonts::PIXELOID, FONT_SIZE), DoubleBarWidget::new()),
@@ -46,7 +46,7 @@ impl Drawable for PerformanceWithMemoryScreen {
 
 
         let canvas_bounds = bounds
-            .move_y(VERTICAL_PADDING as i32).with_height(bounds.size.height as u32 - 2 * VERTICAL_PADDING);
+            .move_y(VERTICAL_PADDING as i32).with_height(bounds.size.height - 2 * VERTICAL_PADDING);
         let text_width = TEXT_PADDING + self.cpu_widgets.0.size().width.max(self.mem_widgets.0.size().width.max(self.gpu_widgets.0.size().width));
         let text_bounds = EmptyBounds::new().with_width(text_width).with_x(0);
         let bar_bounds = EmptyBounds::new()
@@ -85,3 +85,26 @@ impl Screen for PerformanceWithMemoryScreen {
         self.gpu_widgets.1.set_values(0.0, 0.0);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn bars_fill_up_smoothly() {
+        let stats = performance_monitor::PerformanceStatistics {
+            memory_usage: 0.5,
+            cpu_usage_group_1: 0.25,
+            cpu_usage_group_2: 0.75,
+            cpu_temperature: 65.0,
+            gpu_usage: 1.0,
+            vram_usage: 0.1,
+            gpu_temperature: 48.0,
+        };
+        let mut screen = PerformanceWithMemoryScreen::new(Arc::new(Mutex::new(stats)));
+        graphics::snapshot!("performance_with_mem", 128, 64)
+            .with_interval(Duration::from_millis(100), 4)
+            .assert_matches(&mut screen);
+    }
+}
@@ -19,6 +19,8 @@ pub struct PerformanceWithTemperatureScreen {
     stats: Arc<Mutex<performance_monitor::PerformanceStatistics>>,
     cpu_widgets: (BitmapWidget, SimpleTextWidget<'static>, DoubleBarWidget),
     gpu_widgets: (BitmapWidget, SimpleTextWidget<'static>, DoubleBarWidget),
+    /// Drawn after both temperatures
+    temperature_unit: SimpleTextWidget<'static>,
 }
 
 const TEXT_WIDTH: u32 = 45;
@@ -40,18 +42,35 @@ impl PerformanceWithTemperatureScreen {
                 SimpleTextWidget::new("".to_string(), &fonts::PIXELOID, FONT_SIZE),
                 DoubleBarWidget::new(),
             ),
+            temperature_unit: SimpleTextWidget::new("°C".to_string(), &fonts::PIXELOID, FONT_SIZE),
         }
     }
 }
 
+/// Draws a temperature and its unit centered in `bounds`, with the unit sitting on the temperature's baseline.
+fn draw_temperature(value: &mut SimpleTextWidget, unit: &mut SimpleTextWidget, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
+    let width = value.size().width + unit.size().width;
+    let value_bounds = EmptyBounds::new()
+        .with_size(value.size()).center_ver_in(&bounds)
+        .with_width(width).center_hor_in(&bounds)
+        .with_width(value.size().width);
+    let baseline = value_bounds.pos.y + value.baseline() as i32;
+    value.draw(canvas, value_bounds, elapsed);
+    let unit_bounds = EmptyBounds::new()
+        .with_size(unit.size())
+        .right_of(&value_bounds)
+        .with_baseline_at(baseline, unit.baseline());
+    unit.draw(canvas, unit_bounds, elapsed);
+}
+
 impl Drawable for PerformanceWithTemperatureScreen {
     fn draw(&mut self, canvas: &mut rendering::Bitmap, bounds: Bounds, elapsed: &std::time::Duration) {
         {
             let stats = self.stats.lock().unwrap();
             self.cpu_widgets.2.set_values_smoothed(stats.cpu_usage_group_1, stats.cpu_usage_group_2);
-            self.cpu_widgets.1.set_text(&format!("{} C", stats.cpu_temperature));
+            self.cpu_widgets.1.set_text(&stats.cpu_temperature.to_string());
             self.gpu_widgets.2.set_values_smoothed(stats.gpu_usage, stats.vram_usage);
-            self.gpu_widgets.1.set_text(&format!("{} C", stats.gpu_temperature));
+            self.gpu_widgets.1.set_text(&stats.gpu_temperature.to_string());
         };
         let separator_pos = BAR_HEIGHT as i32 + SEPARATOR_MARGIN;
 
@@ -63,15 +82,13 @@ impl Drawable for PerformanceWithTemperatureScreen {
                 .center_hor_in(&EmptyBounds::new().between_hor(canvas.width as i32 / 2, canvas.width as i32))
                 .move_x(3);
             self.cpu_widgets.0.draw(canvas, icon_bounds, elapsed);
-            let text_bounds_outer = EmptyBounds::new().with_height(self.cpu_widgets.1.size().height)
-                .with_width(TEXT_WIDTH).align_right(&bounds);
+            let text_bounds = EmptyBounds::new().with_width(TEXT_WIDTH).align_right(&bounds);
             let bar_bounds = EmptyBounds::new()
                 .with_height(BAR_HEIGHT).with_y(0)
-                .between_hor(0, text_bounds_outer.pos.x);
+                .between_hor(0, text_bounds.pos.x);
             self.cpu_widgets.2.draw(canvas, bar_bounds, elapsed);
-            let text_bounds_inner = text_bounds_outer.center_ver_in(&bar_bounds)
-                .with_width(self.cpu_widgets.1.size().width).center_hor_in(&text_bounds_outer);
-            self.cpu_widgets.1.draw(canvas, text_bounds_inner, elapsed)
+            let text_bounds = text_bounds.with_y(bar_bounds.pos.y).with_height(BAR_HEIGHT);
+            draw_temperature(&mut self.cpu_widgets.1, &mut self.temperature_unit, canvas, text_bounds, elapsed);
         }
         {
             let icon_bounds = EmptyBounds::new()
@@ -79,15 +96,13 @@ impl Drawable for PerformanceWithTemperatureScreen {
                 .center_ver_in(&bounds)
                 .center_hor_in(&EmptyBounds::new().between_hor(0, canvas.width as i32 / 2));
             self.gpu_widgets.0.draw(canvas, icon_bounds, elapsed);
-            let text_bounds_outer = EmptyBounds::new().with_height(self.gpu_widgets.1.size().height)
-                .with_width(TEXT_WIDTH).align_left(&bounds);
+            let text_bounds = EmptyBounds::new().with_width(TEXT_WIDTH).align_left(&bounds);
             let bar_bounds = EmptyBounds::new()
                 .with_height(BAR_HEIGHT).align_bottom(&bounds)
-                .between_hor(text_bounds_outer.right(), bounds.right());
+                .between_hor(text_bounds.right(), bounds.right());
             self.gpu_widgets.2.draw(canvas, bar_bounds, elapsed);
-            let text_bounds_inner = text_bounds_outer.center_ver_in(&bar_bounds)
-                .with_width(self.gpu_widgets.1.size().width).center_hor_in(&text_bounds_outer);
-            self.gpu_widgets.1.draw(canvas, text_bounds_inner, elapsed)
+            let text_bounds = text_bounds.with_y(bar_bounds.pos.y).with_height(BAR_HEIGHT);
+            draw_temperature(&mut self.gpu_widgets.1, &mut self.temperature_unit, canvas, text_bounds, elapsed);
         }
 
         canvas.draw_rect_with_slits(0, separator_pos, bounds.size.width as usize / 2, 1, 2);
@@ -103,3 +118,26 @@ impl Screen for PerformanceWithTemperatureScreen {
         self.gpu_widgets.2.set_values(0.0, 0.0);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn bars_fill_up_smoothly() {
+        let stats = performance_monitor::PerformanceStatistics {
+            memory_usage: 0.5,
+            cpu_usage_group_1: 0.25,
+            cpu_usage_group_2: 0.75,
+            cpu_temperature: 65.0,
+            gpu_usage: 1.0,
+            vram_usage: 0.1,
+            gpu_temperature: 48.0,
+        };
+        let mut screen = PerformanceWithTemperatureScreen::new(Arc::new(Mutex::new(stats)));
+        graphics::snapshot!("performance_with_temp", 128, 64)
+            .with_interval(Duration::from_millis(100), 4)
+            .assert_matches(&mut screen);
+    }
+}
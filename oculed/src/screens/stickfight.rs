@@ -11,7 +11,7 @@ pub struct StickFightScreen {
 }
 
 impl StickFightScreen {
-    pub fn new(width: usize, height: usize) -> ImageResult<Self> {
+    pub fn new(width: usize, height: usize, fps: f32) -> ImageResult<Self> {
         let cursor = std::io::Cursor::new(include_bytes!("../../resources/gifs/stickfight.gif"));
         let decoder = image::codecs::gif::GifDecoder::new(cursor).unwrap();
         let frames = decoder.into_frames().collect_frames()?;
@@ -22,7 +22,7 @@ impl StickFightScreen {
 
         let video = Video::from_images(&images);
         Ok(Self {
-            widget: VideoWidget::new(video, 15.0)
+            widget: VideoWidget::new(video, fps)
         })
     }
 }
@@ -39,3 +39,19 @@ impl Screen for StickFightScreen {
         self.widget.reset();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use graphics::testing::SnapshotFormat;
+
+    #[test]
+    fn stickfight_snapshot() {
+        let mut screen = StickFightScreen::new(128, 64, 15.0).unwrap();
+        graphics::snapshot!("stickfight", 128, 64)
+            .with_interval(Duration::from_millis(100), 4)
+            .with_format(SnapshotFormat::Png)
+            .assert_matches(&mut screen);
+    }
+}